@@ -0,0 +1 @@
+UPDATE roles SET permissions = '{}' WHERE typ = 'admin';
@@ -0,0 +1 @@
+UPDATE roles SET permissions = enum_range(NULL::permission_type) WHERE typ = 'admin';
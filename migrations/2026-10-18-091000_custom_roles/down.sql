@@ -0,0 +1,19 @@
+UPDATE memberships SET role_id = member.role_id
+    FROM roles custom, roles member
+    WHERE memberships.role_id = custom.role_id
+    AND custom.typ = 'custom'
+    AND member.group_id = custom.group_id
+    AND member.typ = 'member';
+DELETE FROM roles WHERE typ = 'custom';
+
+DROP INDEX roles_group_id_typ_key;
+ALTER TABLE roles DROP CONSTRAINT roles_group_id_name_key;
+ALTER TABLE roles ADD CONSTRAINT roles_group_id_typ_key UNIQUE (group_id, typ);
+ALTER TABLE roles DROP COLUMN position;
+
+ALTER TYPE role_type RENAME TO role_type_old;
+CREATE TYPE role_type AS ENUM ('admin', 'curator', 'member');
+ALTER TABLE roles ALTER COLUMN typ DROP DEFAULT;
+ALTER TABLE roles ALTER COLUMN typ TYPE role_type USING typ::text::role_type;
+ALTER TABLE roles ALTER COLUMN typ SET DEFAULT 'member';
+DROP TYPE role_type_old;
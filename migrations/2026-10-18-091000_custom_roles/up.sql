@@ -0,0 +1,11 @@
+ALTER TYPE role_type RENAME TO role_type_old;
+CREATE TYPE role_type AS ENUM ('admin', 'curator', 'custom', 'member');
+ALTER TABLE roles ALTER COLUMN typ DROP DEFAULT;
+ALTER TABLE roles ALTER COLUMN typ TYPE role_type USING typ::text::role_type;
+ALTER TABLE roles ALTER COLUMN typ SET DEFAULT 'member';
+DROP TYPE role_type_old;
+
+ALTER TABLE roles ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE roles DROP CONSTRAINT roles_group_id_typ_key;
+ALTER TABLE roles ADD CONSTRAINT roles_group_id_name_key UNIQUE (group_id, name);
+CREATE UNIQUE INDEX roles_group_id_typ_key ON roles (group_id, typ) WHERE typ <> 'custom';
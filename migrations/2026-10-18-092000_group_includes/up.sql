@@ -0,0 +1,10 @@
+CREATE TABLE group_includes (
+    parent_id INTEGER NOT NULL REFERENCES groups,
+    child_id INTEGER NOT NULL REFERENCES groups,
+    added_by UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000000',
+    added_ts TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (parent_id, child_id),
+    CHECK (parent_id <> child_id)
+);
+
+CREATE INDEX group_includes_child_id_idx ON group_includes (child_id);
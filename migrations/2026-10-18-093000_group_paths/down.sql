@@ -0,0 +1,16 @@
+DROP VIEW groups_list;
+CREATE VIEW groups_list AS
+SELECT
+    groups.name,
+    groups.typ,
+    groups.trust,
+    count(memberships.user_uuid) AS members_count
+FROM
+    GROUPS
+    JOIN memberships ON groups.group_id = memberships.group_id
+GROUP BY
+    groups.group_id;
+
+DROP INDEX groups_path_idx;
+ALTER TABLE groups ALTER COLUMN path DROP DEFAULT;
+UPDATE groups SET path = '/access_information/mozillians/';
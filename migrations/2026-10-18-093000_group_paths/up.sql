@@ -0,0 +1,17 @@
+UPDATE groups SET path = '/' WHERE path = '/access_information/mozillians/';
+ALTER TABLE groups ALTER COLUMN path SET DEFAULT '/';
+CREATE INDEX groups_path_idx ON groups (path);
+
+DROP VIEW groups_list;
+CREATE VIEW groups_list AS
+SELECT
+    groups.name,
+    groups.typ,
+    groups.trust,
+    groups.path,
+    count(memberships.user_uuid) AS members_count
+FROM
+    GROUPS
+    JOIN memberships ON groups.group_id = memberships.group_id
+GROUP BY
+    groups.group_id;
@@ -0,0 +1,16 @@
+DROP VIEW groups_list;
+CREATE VIEW groups_list AS
+SELECT
+    groups.name,
+    groups.typ,
+    groups.trust,
+    groups.path,
+    count(memberships.user_uuid) AS members_count
+FROM
+    GROUPS
+    JOIN memberships ON groups.group_id = memberships.group_id
+GROUP BY
+    groups.group_id;
+
+ALTER TABLE groups DROP COLUMN archived_at;
+ALTER TABLE groups DROP COLUMN inactive_since;
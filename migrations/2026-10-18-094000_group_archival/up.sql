@@ -0,0 +1,18 @@
+ALTER TABLE groups ADD COLUMN inactive_since TIMESTAMP;
+ALTER TABLE groups ADD COLUMN archived_at TIMESTAMP;
+
+DROP VIEW groups_list;
+CREATE VIEW groups_list AS
+SELECT
+    groups.name,
+    groups.typ,
+    groups.trust,
+    groups.path,
+    count(memberships.user_uuid) AS members_count
+FROM
+    GROUPS
+    JOIN memberships ON groups.group_id = memberships.group_id
+WHERE
+    groups.active
+GROUP BY
+    groups.group_id;
@@ -0,0 +1,2 @@
+DROP TABLE pending_memberships;
+ALTER TABLE invitations DROP COLUMN starts_at;
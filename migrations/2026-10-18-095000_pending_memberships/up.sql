@@ -0,0 +1,12 @@
+ALTER TABLE invitations ADD COLUMN starts_at TIMESTAMP;
+
+CREATE TABLE pending_memberships (
+    user_uuid UUID NOT NULL,
+    group_id INTEGER NOT NULL REFERENCES groups,
+    group_expiration INTEGER,
+    starts_at TIMESTAMP NOT NULL,
+    added_by UUID NOT NULL,
+    added_ts TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_uuid, group_id)
+);
+CREATE INDEX pending_memberships_starts_at_idx ON pending_memberships (starts_at);
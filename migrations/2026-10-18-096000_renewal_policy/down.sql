@@ -0,0 +1,2 @@
+ALTER TABLE groups DROP COLUMN max_membership_days;
+ALTER TABLE groups DROP COLUMN self_renewal_days;
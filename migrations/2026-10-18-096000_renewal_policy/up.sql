@@ -0,0 +1,2 @@
+ALTER TABLE groups ADD COLUMN self_renewal_days INTEGER;
+ALTER TABLE groups ADD COLUMN max_membership_days INTEGER;
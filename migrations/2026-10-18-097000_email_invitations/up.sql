@@ -0,0 +1,10 @@
+CREATE TABLE email_invitations (
+    email VARCHAR NOT NULL,
+    group_id INTEGER NOT NULL REFERENCES groups,
+    invitation_expiration TIMESTAMP,
+    group_expiration INTEGER,
+    added_by UUID NOT NULL,
+    added_ts TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (email, group_id)
+);
+CREATE INDEX email_invitations_invitation_expiration_idx ON email_invitations (invitation_expiration);
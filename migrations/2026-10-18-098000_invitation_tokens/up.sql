@@ -0,0 +1 @@
+ALTER TABLE invitations ADD COLUMN token_nonce UUID;
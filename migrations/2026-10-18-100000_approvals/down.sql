@@ -0,0 +1,2 @@
+DROP TABLE approvals;
+ALTER TABLE groups DROP COLUMN required_approvals;
@@ -0,0 +1,9 @@
+ALTER TABLE groups ADD COLUMN required_approvals INTEGER;
+
+CREATE TABLE approvals (
+    group_id INTEGER NOT NULL REFERENCES groups,
+    user_uuid UUID NOT NULL,
+    approver_uuid UUID NOT NULL,
+    approved_ts TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (group_id, user_uuid, approver_uuid)
+);
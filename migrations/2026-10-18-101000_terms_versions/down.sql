@@ -0,0 +1,4 @@
+DROP TABLE terms_acceptances;
+DROP TABLE terms_versions;
+ALTER TABLE terms DROP COLUMN require_reacceptance;
+ALTER TABLE terms DROP COLUMN version;
@@ -0,0 +1,27 @@
+ALTER TABLE terms ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE terms ADD COLUMN require_reacceptance BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE terms_versions (
+    group_id INTEGER NOT NULL REFERENCES groups,
+    version INTEGER NOT NULL,
+    text TEXT NOT NULL,
+    added_by UUID NOT NULL,
+    added_ts TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (group_id, version)
+);
+
+CREATE TABLE terms_acceptances (
+    group_id INTEGER NOT NULL REFERENCES groups,
+    user_uuid UUID NOT NULL,
+    version INTEGER NOT NULL,
+    accepted_ts TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (group_id, user_uuid, version)
+);
+
+INSERT INTO terms_versions (group_id, version, text, added_by)
+SELECT group_id, 1, text, '00000000-0000-0000-0000-000000000000' FROM terms;
+
+-- members of groups with terms accepted them when joining
+INSERT INTO terms_acceptances (group_id, user_uuid, version, accepted_ts)
+SELECT m.group_id, m.user_uuid, 1, m.added_ts FROM memberships m
+INNER JOIN terms t ON t.group_id = m.group_id;
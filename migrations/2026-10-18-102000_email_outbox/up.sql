@@ -0,0 +1,17 @@
+CREATE TYPE email_status_type AS ENUM ('pending', 'sent', 'failed');
+
+CREATE TABLE email_outbox (
+    email_id SERIAL PRIMARY KEY,
+    to_address VARCHAR,
+    bcc VARCHAR[],
+    from_address VARCHAR NOT NULL,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    status email_status_type NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt TIMESTAMP NOT NULL DEFAULT NOW(),
+    created TIMESTAMP NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMP
+);
+CREATE INDEX email_outbox_status_next_attempt_idx ON email_outbox (status, next_attempt);
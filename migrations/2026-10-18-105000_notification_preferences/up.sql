@@ -0,0 +1,9 @@
+CREATE TYPE notification_category_type AS ENUM ('invitations', 'expirations', 'requests');
+
+CREATE TABLE notification_preferences (
+    user_uuid UUID NOT NULL,
+    category notification_category_type NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    updated TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_uuid, category)
+);
@@ -0,0 +1 @@
+ALTER TABLE memberships DROP COLUMN self_renewed_at;
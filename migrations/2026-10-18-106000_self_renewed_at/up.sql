@@ -0,0 +1 @@
+ALTER TABLE memberships ADD COLUMN self_renewed_at TIMESTAMP;
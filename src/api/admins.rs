@@ -1,6 +1,7 @@
 use crate::api::error::ApiError;
 use crate::db::operations;
 use crate::db::Pool;
+use crate::mail::manager::MailMan;
 use crate::user::User;
 use actix_web::dev::HttpServiceFactory;
 use actix_web::web;
@@ -28,6 +29,7 @@ async fn add_admin<T: AsyncCisClientTrait>(
     scope_and_user: ScopeAndUser,
     add_admin: web::Json<AddAdmin>,
     cis_client: web::Data<T>,
+    mail_man: web::Data<MailMan>,
 ) -> Result<HttpResponse, ApiError> {
     let pool_f = pool.clone();
     let user_uuid = add_admin.member_uuid;
@@ -39,6 +41,7 @@ async fn add_admin<T: AsyncCisClientTrait>(
         &host,
         &User { user_uuid },
         Arc::clone(&*cis_client),
+        &mail_man,
     )
     .await?;
     Ok(HttpResponse::Ok().json(""))
@@ -50,6 +53,7 @@ pub async fn downgrade(
     path: web::Path<(String, Uuid)>,
     downgrade_admin: web::Json<DowngradeAdmin>,
     scope_and_user: ScopeAndUser,
+    mail_man: web::Data<MailMan>,
 ) -> Result<HttpResponse, ApiError> {
     let (group_name, user_uuid) = path.into_inner();
     let host = operations::users::user_by_id(&pool, &scope_and_user.user_id)?;
@@ -62,6 +66,7 @@ pub async fn downgrade(
         &host,
         &user,
         downgrade_admin.group_expiration,
+        &mail_man,
     )
     .map(|_| HttpResponse::Created().json(""))
     .map_err(ApiError::GenericBadRequest)
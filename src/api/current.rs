@@ -1,13 +1,19 @@
 use crate::api::error::ApiError;
+use crate::capabilities::Providers;
 use crate::db::operations;
 use crate::db::Pool;
+use crate::mail::manager::MailMan;
+use crate::tokens::TokenSecret;
+use crate::utils::to_utc;
 use actix_web::dev::HttpServiceFactory;
 use actix_web::web;
 use actix_web::HttpRequest;
 use actix_web::HttpResponse;
+use chrono::NaiveDateTime;
 use cis_client::AsyncCisClientTrait;
 use dino_park_gate::scope::ScopeAndUser;
 use serde::Deserialize;
+use serde::Serialize;
 use std::sync::Arc;
 
 #[derive(Deserialize, Default)]
@@ -15,26 +21,114 @@ struct ForceLeave {
     force: Option<bool>,
 }
 
+const JUSTIFICATION_MAX_LEN: usize = 2000;
+
+#[derive(Deserialize)]
+struct MembershipRequest {
+    justification: Option<String>,
+}
+
+impl MembershipRequest {
+    pub fn checked(self) -> Result<Option<String>, ApiError> {
+        match self.justification {
+            Some(justification) if justification.len() > JUSTIFICATION_MAX_LEN => {
+                Err(ApiError::InputToLong)
+            }
+            Some(justification) if justification.trim().is_empty() => Ok(None),
+            justification => Ok(justification),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct InvitationAcceptance {
+    #[serde(default)]
+    accepted_terms: bool,
+}
+
+#[derive(Deserialize)]
+struct InvitationTokenAcceptance {
+    token: String,
+    #[serde(default)]
+    accepted_terms: bool,
+}
+
+#[derive(Serialize)]
+struct Renewal {
+    #[serde(serialize_with = "to_utc")]
+    expiration: NaiveDateTime,
+}
+
 #[guard(Authenticated)]
 async fn join<T: AsyncCisClientTrait>(
     _: HttpRequest,
     pool: web::Data<Pool>,
     group_name: web::Path<String>,
     scope_and_user: ScopeAndUser,
+    acceptance: Option<web::Json<InvitationAcceptance>>,
     cis_client: web::Data<T>,
+    mail_man: web::Data<MailMan>,
+    providers: web::Data<Providers>,
 ) -> Result<HttpResponse, ApiError> {
+    let accepted_terms = acceptance.map(|a| a.accepted_terms).unwrap_or_default();
     let user = operations::users::user_by_id(&pool, &scope_and_user.user_id)?;
     operations::invitations::accept_invitation(
         &pool,
         &scope_and_user,
         &group_name,
         &user,
+        accepted_terms,
+        Arc::clone(&*cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(""))
+}
+
+#[guard(Authenticated)]
+async fn join_with_token<T: AsyncCisClientTrait>(
+    _: HttpRequest,
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+    scope_and_user: ScopeAndUser,
+    acceptance: web::Json<InvitationTokenAcceptance>,
+    cis_client: web::Data<T>,
+    mail_man: web::Data<MailMan>,
+    providers: web::Data<Providers>,
+    token_secret: web::Data<TokenSecret>,
+) -> Result<HttpResponse, ApiError> {
+    let acceptance = acceptance.into_inner();
+    let user = operations::users::user_by_id(&pool, &scope_and_user.user_id)?;
+    operations::invitations::accept_invitation_token(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        &user,
+        &acceptance.token,
+        &token_secret,
+        acceptance.accepted_terms,
         Arc::clone(&*cis_client),
+        &mail_man,
+        &providers,
     )
     .await?;
     Ok(HttpResponse::Ok().json(""))
 }
 
+#[guard(Authenticated)]
+async fn renew(
+    _: HttpRequest,
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+    scope_and_user: ScopeAndUser,
+    mail_man: web::Data<MailMan>,
+) -> Result<HttpResponse, ApiError> {
+    let expiration =
+        operations::members::self_renew(&pool, &scope_and_user, &group_name, &mail_man)?;
+    Ok(HttpResponse::Ok().json(Renewal { expiration }))
+}
+
 #[guard(Authenticated)]
 async fn leave<T: AsyncCisClientTrait>(
     _: HttpRequest,
@@ -43,6 +137,8 @@ async fn leave<T: AsyncCisClientTrait>(
     scope_and_user: ScopeAndUser,
     force: web::Query<ForceLeave>,
     cis_client: web::Data<T>,
+    mail_man: web::Data<MailMan>,
+    providers: web::Data<Providers>,
 ) -> Result<HttpResponse, ApiError> {
     operations::members::leave(
         &pool,
@@ -50,6 +146,8 @@ async fn leave<T: AsyncCisClientTrait>(
         &group_name,
         force.force.unwrap_or_default(),
         Arc::clone(&*cis_client),
+        &mail_man,
+        &providers,
     )
     .await?;
     Ok(HttpResponse::Ok().json(""))
@@ -61,8 +159,19 @@ async fn request(
     pool: web::Data<Pool>,
     group_name: web::Path<String>,
     scope_and_user: ScopeAndUser,
+    membership_request: Option<web::Json<Option<MembershipRequest>>>,
 ) -> Result<HttpResponse, ApiError> {
-    match operations::requests::request_membership(&pool, &scope_and_user, &group_name, None) {
+    let justification = match membership_request.and_then(|r| r.into_inner()) {
+        Some(membership_request) => membership_request.checked()?,
+        None => None,
+    };
+    match operations::requests::request_membership(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        None,
+        justification,
+    ) {
         Ok(_) => Ok(HttpResponse::Created().json("")),
         Err(e) => Err(ApiError::GenericBadRequest(e)),
     }
@@ -116,8 +225,24 @@ async fn requests(
     }
 }
 
+#[guard(Authenticated)]
+async fn permissions(
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    match operations::permissions::effective_permissions(&pool, &scope_and_user, &group_name) {
+        Ok(actions) => Ok(HttpResponse::Ok().json(actions)),
+        Err(e) => Err(ApiError::GenericBadRequest(e)),
+    }
+}
+
 pub fn current_app<T: AsyncCisClientTrait + 'static>() -> impl HttpServiceFactory {
     web::scope("/self")
+        .service(
+            web::resource("/invitations/{group_name}/accept")
+                .route(web::post().to(join_with_token::<T>)),
+        )
         .service(
             web::resource("/invitations/{group_name}")
                 .route(web::delete().to(reject_invitation))
@@ -130,5 +255,7 @@ pub fn current_app<T: AsyncCisClientTrait + 'static>() -> impl HttpServiceFactor
                 .route(web::delete().to(cancel_request)),
         )
         .service(web::resource("/requests").route(web::get().to(requests)))
+        .service(web::resource("/permissions/{group_name}").route(web::get().to(permissions)))
+        .service(web::resource("/{group_name}/renew").route(web::post().to(renew)))
         .service(web::resource("/{group_name}").route(web::delete().to(leave::<T>)))
 }
@@ -1,7 +1,7 @@
 use crate::api::error::ApiError;
 use crate::db::operations;
 use crate::db::Pool;
-use crate::mail::manager::send_email_raw;
+use crate::mail::manager::MailMan;
 use crate::mail::Email;
 use crate::mail::Message;
 use actix_web::dev::HttpServiceFactory;
@@ -26,12 +26,16 @@ pub struct GroupEmail {
 }
 
 #[guard(Staff, Admin, Medium)]
-async fn email_bcc(bcc_email: web::Form<BccEmail>) -> Result<HttpResponse, ApiError> {
+async fn email_bcc(
+    bcc_email: web::Form<BccEmail>,
+    mail_man: web::Data<MailMan>,
+) -> Result<HttpResponse, ApiError> {
     let bcc_email = bcc_email.into_inner();
     let email = Email {
         message: Message {
             body: bcc_email.body,
             subject: bcc_email.subject,
+            html: None,
         },
         bcc: Some(
             bcc_email
@@ -42,7 +46,7 @@ async fn email_bcc(bcc_email: web::Form<BccEmail>) -> Result<HttpResponse, ApiEr
         ),
         ..Default::default()
     };
-    send_email_raw(email);
+    mail_man.send_email_raw(email);
     Ok(HttpResponse::Ok().body("ok"))
 }
 
@@ -51,6 +55,7 @@ async fn email_group(
     pool: web::Data<Pool>,
     scope_and_user: ScopeAndUser,
     group_email: web::Form<GroupEmail>,
+    mail_man: web::Data<MailMan>,
 ) -> Result<HttpResponse, ApiError> {
     let GroupEmail {
         body,
@@ -63,13 +68,17 @@ async fn email_group(
         &scope_and_user,
         &group_name,
     )?);
-    let message = Message { body, subject };
+    let message = Message {
+        body,
+        subject,
+        html: None,
+    };
     let email = Email {
         bcc,
         message,
         ..Default::default()
     };
-    send_email_raw(email);
+    mail_man.send_email_raw(email);
     Ok(HttpResponse::Ok().body("ok"))
 }
 
@@ -2,12 +2,15 @@ use crate::api::error::ApiError;
 use crate::api::models::DisplayGroup;
 use crate::api::models::DisplayGroupDetails;
 use crate::api::models::GroupInfo;
+use crate::capabilities::Providers;
 use crate::db::operations;
 use crate::db::operations::models::GroupUpdate;
 use crate::db::operations::models::NewGroup;
 use crate::db::operations::models::SortGroupsBy;
 use crate::db::types::GroupType;
 use crate::db::Pool;
+use crate::mail::manager::MailMan;
+use crate::utils::breadcrumb;
 use actix_web::dev::HttpServiceFactory;
 use actix_web::web;
 use actix_web::HttpResponse;
@@ -27,12 +30,18 @@ struct ListGroupsQuery {
     s: i64,
     #[serde(default)]
     by: SortGroupsBy,
+    parent: Option<String>,
 }
 
 fn default_groups_list_size() -> i64 {
     20
 }
 
+#[derive(Deserialize)]
+struct IncludeGroup {
+    group_name: String,
+}
+
 #[guard(Authenticated)]
 async fn get_group(
     pool: web::Data<Pool>,
@@ -49,7 +58,7 @@ async fn list_groups(
     query: web::Query<ListGroupsQuery>,
 ) -> Result<HttpResponse, ApiError> {
     let query = query.into_inner();
-    operations::groups::list_groups(&pool, query.f, query.by, query.s, query.n)
+    operations::groups::list_groups(&pool, query.f, query.parent, query.by, query.s, query.n)
         .map(|groups| HttpResponse::Ok().json(groups))
         .map_err(ApiError::GenericBadRequest)
 }
@@ -86,15 +95,67 @@ async fn add_group<T: AsyncCisClientTrait>(
     Ok(HttpResponse::Created().json(""))
 }
 
+#[guard(Ndaed, None, Medium)]
+async fn add_subgroup<T: AsyncCisClientTrait>(
+    cis_client: web::Data<T>,
+    pool: web::Data<Pool>,
+    parent: web::Path<String>,
+    scope_and_user: ScopeAndUser,
+    new_group: web::Json<NewGroup>,
+) -> Result<HttpResponse, ApiError> {
+    let new_group = new_group.into_inner().checked()?;
+    info!(
+        "trying to create new group: {} in {}",
+        new_group.name, parent
+    );
+    operations::groups::add_new_subgroup(
+        &pool,
+        &scope_and_user,
+        &parent,
+        new_group,
+        Arc::clone(&*cis_client),
+    )
+    .await?;
+    Ok(HttpResponse::Created().json(""))
+}
+
+#[guard(Ndaed, None, Medium)]
+async fn reactivate_group<T: AsyncCisClientTrait>(
+    cis_client: web::Data<T>,
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+    scope_and_user: ScopeAndUser,
+    providers: web::Data<Providers>,
+) -> Result<HttpResponse, ApiError> {
+    operations::groups::reactivate_group(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        Arc::clone(&*cis_client),
+        &providers,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(""))
+}
+
 #[guard(Staff, Creator, Medium)]
 async fn delete_group<T: AsyncCisClientTrait>(
     cis_client: web::Data<T>,
     pool: web::Data<Pool>,
     group_name: web::Path<String>,
     scope_and_user: ScopeAndUser,
+    mail_man: web::Data<MailMan>,
+    providers: web::Data<Providers>,
 ) -> Result<HttpResponse, ApiError> {
-    operations::groups::delete_group(&pool, &scope_and_user, &group_name, Arc::clone(&cis_client))
-        .await?;
+    operations::groups::delete_group(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        Arc::clone(&cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
     Ok(HttpResponse::Created().json(""))
 }
 
@@ -162,7 +223,11 @@ async fn group_details(
             created: group.group.created,
             terms: group.terms,
             trust: group.group.trust,
+            self_renewal_days: group.group.self_renewal_days,
+            max_membership_days: group.group.max_membership_days,
+            required_approvals: group.group.required_approvals,
         },
+        breadcrumb: breadcrumb(&group.group.path),
         member_count,
         invitation_count,
         renewal_count,
@@ -171,6 +236,65 @@ async fn group_details(
     Ok(HttpResponse::Ok().json(result))
 }
 
+#[guard(Ndaed)]
+async fn approvals(
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    operations::groups::approvals(&pool, &scope_and_user, &group_name)
+        .map(|approvals| HttpResponse::Ok().json(approvals))
+        .map_err(ApiError::GenericBadRequest)
+}
+
+#[guard(Authenticated)]
+async fn get_includes(
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    operations::includes::get_included_groups(&pool, &group_name)
+        .map(|groups| HttpResponse::Ok().json(groups))
+        .map_err(ApiError::GenericBadRequest)
+}
+
+#[guard(Ndaed, None, Medium)]
+async fn add_include<T: AsyncCisClientTrait>(
+    cis_client: web::Data<T>,
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+    scope_and_user: ScopeAndUser,
+    include_group: web::Json<IncludeGroup>,
+) -> Result<HttpResponse, ApiError> {
+    operations::includes::add_include(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        &include_group.group_name,
+        Arc::clone(&*cis_client),
+    )
+    .await?;
+    Ok(HttpResponse::Created().json(""))
+}
+
+#[guard(Ndaed, None, Medium)]
+async fn remove_include<T: AsyncCisClientTrait>(
+    cis_client: web::Data<T>,
+    pool: web::Data<Pool>,
+    path: web::Path<(String, String)>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    let (group_name, included_group_name) = path.into_inner();
+    operations::includes::remove_include(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        &included_group_name,
+        Arc::clone(&*cis_client),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(""))
+}
+
 pub fn groups_app<T: AsyncCisClientTrait + 'static>() -> impl HttpServiceFactory {
     web::scope("/groups")
         .service(
@@ -185,4 +309,18 @@ pub fn groups_app<T: AsyncCisClientTrait + 'static>() -> impl HttpServiceFactory
                 .route(web::delete().to(delete_group::<T>)),
         )
         .service(web::resource("/{group_name}/details").route(web::get().to(group_details)))
+        .service(web::resource("/{group_name}/approvals").route(web::get().to(approvals)))
+        .service(web::resource("/{group_name}/subgroups").route(web::post().to(add_subgroup::<T>)))
+        .service(
+            web::resource("/{group_name}/reactivate").route(web::post().to(reactivate_group::<T>)),
+        )
+        .service(
+            web::resource("/{group_name}/includes")
+                .route(web::get().to(get_includes))
+                .route(web::post().to(add_include::<T>)),
+        )
+        .service(
+            web::resource("/{group_name}/includes/{included_group_name}")
+                .route(web::delete().to(remove_include::<T>)),
+        )
 }
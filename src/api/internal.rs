@@ -1,6 +1,8 @@
 use crate::api::error::ApiError;
+use crate::capabilities::Providers;
 use crate::db::operations;
 use crate::db::Pool;
+use crate::mail::manager::MailMan;
 use crate::user::User;
 use actix_multipart::Multipart;
 use actix_web::dev::HttpServiceFactory;
@@ -12,6 +14,7 @@ use cis_profile::schema::Profile;
 use futures::StreamExt;
 use futures::TryFutureExt;
 use futures::TryStreamExt;
+use serde::Deserialize;
 use serde::Serialize;
 use std::sync::Arc;
 use uuid::Uuid;
@@ -27,12 +30,47 @@ pub struct NotificationStatus {
     expire_second: usize,
 }
 
+#[derive(Serialize)]
+pub struct DeliveryStatus {
+    sent: usize,
+}
+
+#[derive(Serialize)]
+pub struct ActivationStatus {
+    activated: usize,
+}
+
+#[derive(Serialize)]
+pub struct ArchivalStatus {
+    flagged: usize,
+    archived: usize,
+}
+
+#[derive(Deserialize)]
+pub struct ArchivalQuery {
+    #[serde(default = "default_archive_after_days")]
+    days: i64,
+}
+
+fn default_archive_after_days() -> i64 {
+    30
+}
+
 async fn update_user<T: AsyncCisClientTrait>(
     pool: web::Data<Pool>,
     profile: web::Json<Profile>,
     cis_client: web::Data<T>,
+    mail_man: web::Data<MailMan>,
+    providers: web::Data<Providers>,
 ) -> Result<HttpResponse, ApiError> {
-    operations::users::update_user_cache(&pool, &profile, Arc::clone(&*cis_client)).await?;
+    operations::users::update_user_cache(
+        &pool,
+        &profile,
+        Arc::clone(&*cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
     Ok(HttpResponse::Ok().json(""))
 }
 
@@ -51,39 +89,104 @@ async fn delete_user(
 async fn expire_all<T: AsyncCisClientTrait>(
     pool: web::Data<Pool>,
     cis_client: web::Data<T>,
+    mail_man: web::Data<MailMan>,
+    providers: web::Data<Providers>,
 ) -> Result<HttpResponse, ApiError> {
     operations::expirations::expire_requests(&pool)?;
     operations::expirations::expire_invitations(&pool)?;
-    operations::expirations::expire_memberships(&pool, Arc::clone(&*cis_client)).await?;
+    operations::expirations::expire_memberships(
+        &pool,
+        Arc::clone(&*cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
     Ok(HttpResponse::Ok().json(""))
 }
 
-async fn expiration_notifications(pool: web::Data<Pool>) -> Result<HttpResponse, ApiError> {
-    let expire_first = operations::expirations::expiration_notification(&pool, true)?;
-    let expire_second = operations::expirations::expiration_notification(&pool, false)?;
+async fn activate_all<T: AsyncCisClientTrait>(
+    pool: web::Data<Pool>,
+    cis_client: web::Data<T>,
+    mail_man: web::Data<MailMan>,
+    providers: web::Data<Providers>,
+) -> Result<HttpResponse, ApiError> {
+    let activated = operations::members::activate_pending_memberships(
+        &pool,
+        Arc::clone(&*cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(ActivationStatus { activated }))
+}
+
+async fn expiration_notifications(
+    pool: web::Data<Pool>,
+    mail_man: web::Data<MailMan>,
+) -> Result<HttpResponse, ApiError> {
+    let expire_first = operations::expirations::expiration_notification(&pool, true, &mail_man)?;
+    let expire_second = operations::expirations::expiration_notification(&pool, false, &mail_man)?;
     Ok(HttpResponse::Ok().json(NotificationStatus {
         expire_first,
         expire_second,
     }))
 }
 
-async fn requests_notifications(pool: web::Data<Pool>) -> Result<HttpResponse, ApiError> {
-    operations::requests::pending_requests_notification(&pool)?;
+async fn requests_notifications(
+    pool: web::Data<Pool>,
+    mail_man: web::Data<MailMan>,
+) -> Result<HttpResponse, ApiError> {
+    operations::requests::pending_requests_notification(&pool, &mail_man)?;
     Ok(HttpResponse::Ok().json(""))
 }
 
-async fn all_notifications(pool: web::Data<Pool>) -> Result<HttpResponse, ApiError> {
-    operations::requests::pending_requests_notification(&pool)?;
-    operations::expirations::expiration_notification(&pool, true)?;
-    operations::expirations::expiration_notification(&pool, false)?;
+async fn all_notifications(
+    pool: web::Data<Pool>,
+    mail_man: web::Data<MailMan>,
+) -> Result<HttpResponse, ApiError> {
+    operations::requests::pending_requests_notification(&pool, &mail_man)?;
+    operations::expirations::expiration_notification(&pool, true, &mail_man)?;
+    operations::expirations::expiration_notification(&pool, false, &mail_man)?;
     Ok(HttpResponse::Ok().json(""))
 }
 
-async fn anonymous_notifications(pool: web::Data<Pool>) -> Result<HttpResponse, ApiError> {
-    operations::members::notify_anonymous_members(&pool)?;
+async fn anonymous_notifications(
+    pool: web::Data<Pool>,
+    mail_man: web::Data<MailMan>,
+) -> Result<HttpResponse, ApiError> {
+    operations::members::notify_anonymous_members(&pool, &mail_man)?;
     Ok(HttpResponse::Ok().json(""))
 }
 
+async fn deliver_emails(mail_man: web::Data<MailMan>) -> Result<HttpResponse, ApiError> {
+    let sent = mail_man.deliver_outbox().await?;
+    Ok(HttpResponse::Ok().json(DeliveryStatus { sent }))
+}
+
+async fn reload_email_templates(mail_man: web::Data<MailMan>) -> Result<HttpResponse, ApiError> {
+    mail_man.reload_templates()?;
+    Ok(HttpResponse::Ok().json(""))
+}
+
+async fn archive_groups<T: AsyncCisClientTrait>(
+    pool: web::Data<Pool>,
+    query: web::Query<ArchivalQuery>,
+    cis_client: web::Data<T>,
+    mail_man: web::Data<MailMan>,
+    providers: web::Data<Providers>,
+) -> Result<HttpResponse, ApiError> {
+    // flag first so groups which regained curators are not archived
+    let flagged = operations::groups::flag_unattended_groups(&pool, query.days, &mail_man)?;
+    let archived = operations::groups::archive_unattended_groups(
+        &pool,
+        query.days,
+        Arc::clone(&*cis_client),
+        &providers,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(ArchivalStatus { flagged, archived }))
+}
+
 async fn bulk_update_users(
     pool: web::Data<Pool>,
     mut multipart: Multipart,
@@ -115,10 +218,16 @@ pub fn internal_app<T: AsyncCisClientTrait + 'static>() -> impl HttpServiceFacto
         .service(web::resource("/update/user").route(web::post().to(update_user::<T>)))
         .service(web::resource("/delete/{user_uuid}").route(web::delete().to(delete_user)))
         .service(web::resource("/expire/all").route(web::post().to(expire_all::<T>)))
+        .service(web::resource("/activate/all").route(web::post().to(activate_all::<T>)))
         .service(
             web::resource("/notify/expiration").route(web::post().to(expiration_notifications)),
         )
         .service(web::resource("/notify/requests").route(web::post().to(requests_notifications)))
         .service(web::resource("/notify/all").route(web::post().to(all_notifications)))
         .service(web::resource("/notify/anonymous").route(web::post().to(anonymous_notifications)))
+        .service(web::resource("/mail/deliver").route(web::post().to(deliver_emails)))
+        .service(
+            web::resource("/mail/templates/reload").route(web::post().to(reload_email_templates)),
+        )
+        .service(web::resource("/archive/groups").route(web::post().to(archive_groups::<T>)))
 }
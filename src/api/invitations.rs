@@ -1,14 +1,24 @@
 use crate::api::error::ApiError;
 use crate::db::operations;
+use crate::db::operations::models::BulkInvitationRow;
 use crate::db::operations::models::InvitationEmail;
 use crate::db::Pool;
+use crate::import::api::field_name;
+use crate::import::api::read_field_limited;
+use crate::mail::manager::MailMan;
 use crate::user::User;
 use crate::utils::to_expiration_ts;
+use actix_multipart::Multipart;
 use actix_web::dev::HttpServiceFactory;
 use actix_web::web;
 use actix_web::HttpRequest;
 use actix_web::HttpResponse;
+use chrono::DateTime;
+use chrono::Utc;
+use csv::ReaderBuilder;
+use csv::Trim;
 use dino_park_gate::scope::ScopeAndUser;
+use futures::StreamExt;
 use serde::Deserialize;
 use uuid::Uuid;
 
@@ -23,6 +33,25 @@ pub struct Invitation {
     user_uuid: Uuid,
     invitation_expiration: Option<i32>,
     group_expiration: Option<i32>,
+    #[serde(default)]
+    starts_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct EmailInvitation {
+    email: String,
+    invitation_expiration: Option<i32>,
+    group_expiration: Option<i32>,
+}
+
+const BULK_INVITATION_MAX_BYTES: usize = 512 * 1024;
+const BULK_INVITATION_MAX_ROWS: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct BulkInvitationQuery {
+    #[serde(default)]
+    dry_run: bool,
+    invitation_expiration: Option<i32>,
 }
 
 #[guard(Ndaed, None, Medium)]
@@ -31,6 +60,7 @@ async fn delete_invitation(
     pool: web::Data<Pool>,
     path: web::Path<(String, Uuid)>,
     scope_and_user: ScopeAndUser,
+    mail_man: web::Data<MailMan>,
 ) -> Result<HttpResponse, ApiError> {
     let (group_name, user_uuid) = path.into_inner();
     let member = User { user_uuid };
@@ -41,6 +71,29 @@ async fn delete_invitation(
         &group_name,
         host,
         member,
+        &mail_man,
+    ) {
+        Ok(_) => Ok(HttpResponse::Ok().json("")),
+        Err(e) => Err(ApiError::GenericBadRequest(e)),
+    }
+}
+
+#[guard(Ndaed, None, Medium)]
+async fn approve_invitation(
+    _: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<(String, Uuid)>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    let (group_name, user_uuid) = path.into_inner();
+    let member = User { user_uuid };
+    let host = operations::users::user_by_id(&pool, &scope_and_user.user_id)?;
+    match operations::invitations::approve_invitation(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        host,
+        member,
     ) {
         Ok(_) => Ok(HttpResponse::Ok().json("")),
         Err(e) => Err(ApiError::GenericBadRequest(e)),
@@ -82,10 +135,12 @@ async fn invite_member(
     group_name: web::Path<String>,
     scope_and_user: ScopeAndUser,
     invitation: web::Json<Invitation>,
+    mail_man: web::Data<MailMan>,
 ) -> Result<HttpResponse, ApiError> {
     let invitation = invitation.into_inner();
     let invitation_expiration = invitation.invitation_expiration.map(to_expiration_ts);
     let group_expiration = invitation.group_expiration;
+    let starts_at = invitation.starts_at.map(|s| s.naive_utc());
     let member = User {
         user_uuid: invitation.user_uuid,
     };
@@ -98,12 +153,132 @@ async fn invite_member(
         member,
         invitation_expiration,
         group_expiration,
+        starts_at,
+        &mail_man,
+    ) {
+        Ok(_) => Ok(HttpResponse::Ok().json("")),
+        Err(e) => Err(ApiError::GenericBadRequest(e)),
+    }
+}
+
+#[guard(Ndaed, None, Medium)]
+async fn invite_email(
+    _: HttpRequest,
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+    scope_and_user: ScopeAndUser,
+    invitation: web::Json<EmailInvitation>,
+    mail_man: web::Data<MailMan>,
+) -> Result<HttpResponse, ApiError> {
+    let invitation = invitation.into_inner();
+    let host = operations::users::user_by_id(&pool, &scope_and_user.user_id)?;
+    match operations::invitations::invite_email(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        host,
+        &invitation.email,
+        invitation.invitation_expiration.map(to_expiration_ts),
+        invitation.group_expiration,
+        &mail_man,
     ) {
         Ok(_) => Ok(HttpResponse::Ok().json("")),
         Err(e) => Err(ApiError::GenericBadRequest(e)),
     }
 }
 
+#[guard(Ndaed)]
+async fn pending_email_invitations(
+    _: HttpRequest,
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    let host = operations::users::user_by_id(&pool, &scope_and_user.user_id)?;
+    match operations::invitations::pending_email_invitations(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        &host,
+    ) {
+        Ok(invitations) => Ok(HttpResponse::Ok().json(invitations)),
+        Err(e) => Err(ApiError::GenericBadRequest(e)),
+    }
+}
+
+#[guard(Ndaed, None, Medium)]
+async fn delete_email_invitation(
+    _: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<(String, String)>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    let (group_name, email) = path.into_inner();
+    let host = operations::users::user_by_id(&pool, &scope_and_user.user_id)?;
+    match operations::invitations::delete_email_invitation(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        host,
+        &email,
+    ) {
+        Ok(_) => Ok(HttpResponse::Ok().json("")),
+        Err(e) => Err(ApiError::GenericBadRequest(e)),
+    }
+}
+
+#[guard(Ndaed, None, Medium)]
+async fn bulk_invite(
+    _: HttpRequest,
+    mut multipart: Multipart,
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+    scope_and_user: ScopeAndUser,
+    query: web::Query<BulkInvitationQuery>,
+    mail_man: web::Data<MailMan>,
+) -> Result<HttpResponse, ApiError> {
+    let query = query.into_inner();
+    let mut rows = None;
+    while let Some(Ok(field)) = multipart.next().await {
+        if field_name(&field)? != "invitations" {
+            return Err(ApiError::MultipartError);
+        }
+        let buf = read_field_limited(field, BULK_INVITATION_MAX_BYTES).await?;
+        // accept both TSV and CSV uploads
+        let delimiter = if buf.iter().take_while(|b| **b != b'\n').any(|b| *b == b'\t') {
+            b'\t'
+        } else {
+            b','
+        };
+        let mut rdr = ReaderBuilder::new()
+            .delimiter(delimiter)
+            .trim(Trim::All)
+            .from_reader(buf.as_slice());
+        let r = rdr
+            .deserialize()
+            .collect::<Result<Vec<BulkInvitationRow>, _>>()
+            .map_err(|_| ApiError::MultipartError)?;
+        if r.len() > BULK_INVITATION_MAX_ROWS {
+            return Err(ApiError::InputToLong);
+        }
+        rows = Some(r);
+    }
+    let rows = rows.ok_or(ApiError::MultipartError)?;
+    let host = operations::users::user_by_id(&pool, &scope_and_user.user_id)?;
+    operations::invitations::bulk_invite(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        host,
+        rows,
+        query.invitation_expiration.map(to_expiration_ts),
+        query.dry_run,
+        &mail_man,
+    )
+    .map(|report| HttpResponse::Ok().json(report))
+    .map_err(Into::into)
+}
+
 #[guard(Ndaed)]
 async fn pending(
     _: HttpRequest,
@@ -156,11 +331,25 @@ async fn update_invitation_email(
 
 pub fn invitations_app() -> impl HttpServiceFactory {
     web::scope("/invitations")
+        .service(web::resource("/{group_name}/bulk").route(web::post().to(bulk_invite)))
+        .service(
+            web::resource("/{group_name}/by-email")
+                .route(web::get().to(pending_email_invitations))
+                .route(web::post().to(invite_email)),
+        )
+        .service(
+            web::resource("/{group_name}/by-email/{email}")
+                .route(web::delete().to(delete_email_invitation)),
+        )
         .service(
             web::resource("/{group_name}/email")
                 .route(web::get().to(invitation_email))
                 .route(web::post().to(update_invitation_email)),
         )
+        .service(
+            web::resource("/{group_name}/{user_uuid}/approve")
+                .route(web::post().to(approve_invitation)),
+        )
         .service(
             web::resource("/{group_name}/{user_uuid}")
                 .route(web::delete().to(delete_invitation))
@@ -1,14 +1,18 @@
 use crate::api::error::ApiError;
+use crate::capabilities::Providers;
 use crate::db::operations;
 use crate::db::operations::models::MembersQueryOptions;
 use crate::db::operations::models::SortMembersBy;
 use crate::db::types::RoleType;
 use crate::db::Pool;
+use crate::mail::manager::MailMan;
 use crate::user::User;
 use actix_web::dev::HttpServiceFactory;
 use actix_web::web;
 use actix_web::HttpRequest;
 use actix_web::HttpResponse;
+use chrono::DateTime;
+use chrono::Utc;
 use cis_client::AsyncCisClientTrait;
 use dino_park_gate::scope::ScopeAndUser;
 use serde::Deserialize;
@@ -20,6 +24,18 @@ pub struct RenewMember {
     group_expiration: Option<i32>,
 }
 
+#[derive(Deserialize)]
+pub struct BulkRenewMembers {
+    user_uuids: Option<Vec<Uuid>>,
+    expires_before: Option<DateTime<Utc>>,
+    group_expiration: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct AssignRole {
+    role_name: String,
+}
+
 #[derive(Clone, Deserialize)]
 pub enum MemberRoles {
     Any,
@@ -30,8 +46,13 @@ pub enum MemberRoles {
 impl MemberRoles {
     pub fn get_role_types(&self) -> Vec<RoleType> {
         match self {
-            MemberRoles::Any => vec![RoleType::Admin, RoleType::Curator, RoleType::Member],
-            MemberRoles::Member => vec![RoleType::Member],
+            MemberRoles::Any => vec![
+                RoleType::Admin,
+                RoleType::Curator,
+                RoleType::Custom,
+                RoleType::Member,
+            ],
+            MemberRoles::Member => vec![RoleType::Custom, RoleType::Member],
             MemberRoles::Curator => vec![RoleType::Admin, RoleType::Curator],
         }
     }
@@ -45,6 +66,7 @@ pub struct GetMembersQuery {
     s: Option<i64>,
     by: Option<SortMembersBy>,
     p: Option<bool>,
+    e: Option<bool>,
 }
 
 impl From<GetMembersQuery> for MembersQueryOptions {
@@ -57,6 +79,7 @@ impl From<GetMembersQuery> for MembersQueryOptions {
             offset: q.n,
             order: q.by.unwrap_or_default(),
             privileged: q.p.unwrap_or_default(),
+            effective: q.e.unwrap_or_default(),
         }
     }
 }
@@ -86,6 +109,8 @@ async fn remove_member<T: AsyncCisClientTrait>(
     path: web::Path<(String, Uuid)>,
     scope_and_user: ScopeAndUser,
     cis_client: web::Data<T>,
+    mail_man: web::Data<MailMan>,
+    providers: web::Data<Providers>,
 ) -> Result<HttpResponse, ApiError> {
     let (group_name, user_uuid) = path.into_inner();
     let user = User { user_uuid };
@@ -97,6 +122,8 @@ async fn remove_member<T: AsyncCisClientTrait>(
         &host,
         &user,
         Arc::clone(&*cis_client),
+        &mail_man,
+        &providers,
     )
     .await?;
     Ok(HttpResponse::Ok().json(""))
@@ -125,13 +152,59 @@ async fn renew_member(
     }
 }
 
+#[guard(Ndaed, None, Medium)]
+async fn bulk_renew_members(
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+    scope_and_user: ScopeAndUser,
+    bulk_renew: web::Json<BulkRenewMembers>,
+) -> Result<HttpResponse, ApiError> {
+    let bulk_renew = bulk_renew.into_inner();
+    let host = operations::users::user_by_id(&pool, &scope_and_user.user_id)?;
+    operations::members::bulk_renew(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        &host,
+        bulk_renew.user_uuids,
+        bulk_renew.expires_before.map(|e| e.naive_utc()),
+        bulk_renew.group_expiration,
+    )
+    .map(|results| HttpResponse::Ok().json(results))
+    .map_err(Into::into)
+}
+
+#[guard(Ndaed, None, Medium)]
+async fn assign_role(
+    pool: web::Data<Pool>,
+    path: web::Path<(String, Uuid)>,
+    scope_and_user: ScopeAndUser,
+    assign_role: web::Json<AssignRole>,
+) -> Result<HttpResponse, ApiError> {
+    let (group_name, user_uuid) = path.into_inner();
+    let user = User { user_uuid };
+    let host = operations::users::user_by_id(&pool, &scope_and_user.user_id)?;
+    operations::members::assign_role(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        &host,
+        &user,
+        &assign_role.role_name,
+    )
+    .map(|role| HttpResponse::Ok().json(role))
+    .map_err(Into::into)
+}
+
 pub fn members_app<T: AsyncCisClientTrait + 'static>() -> impl HttpServiceFactory {
     web::scope("/members")
         .service(web::resource("/{group_name}").route(web::get().to(get_members)))
+        .service(web::resource("/{group_name}/renew").route(web::post().to(bulk_renew_members)))
         .service(
             web::resource("/{group_name}/{user_uuid}").route(web::delete().to(remove_member::<T>)),
         )
         .service(
             web::resource("/{group_name}/{user_uuid}/renew").route(web::post().to(renew_member)),
         )
+        .service(web::resource("/{group_name}/{user_uuid}/role").route(web::put().to(assign_role)))
 }
@@ -8,7 +8,11 @@ pub mod internal;
 pub mod invitations;
 pub mod members;
 pub mod models;
+pub mod notifications;
+pub mod permissions;
 pub mod requests;
+pub mod roles;
+pub mod rules;
 pub mod sudo;
 pub mod terms;
 pub mod users;
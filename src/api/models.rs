@@ -38,6 +38,9 @@ pub struct GroupInfo {
     pub created: NaiveDateTime,
     pub terms: bool,
     pub trust: TrustType,
+    pub self_renewal_days: Option<i32>,
+    pub max_membership_days: Option<i32>,
+    pub required_approvals: Option<i32>,
 }
 
 #[derive(Serialize)]
@@ -47,6 +50,7 @@ pub struct DisplayGroupDetails {
     pub curator: bool,
     pub member: bool,
     pub group: GroupInfo,
+    pub breadcrumb: Vec<String>,
     pub member_count: i64,
     pub invitation_count: Option<i64>,
     pub renewal_count: Option<i64>,
@@ -0,0 +1,76 @@
+use crate::api::error::ApiError;
+use crate::db::operations;
+use crate::db::operations::models::NotificationPreferencesUpdate;
+use crate::db::Pool;
+use crate::tokens::TokenSecret;
+use actix_web::dev::HttpServiceFactory;
+use actix_web::web;
+use actix_web::HttpResponse;
+use dino_park_gate::scope::ScopeAndUser;
+
+#[guard(Authenticated)]
+async fn preferences(
+    pool: web::Data<Pool>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    operations::notifications::preferences(&pool, &scope_and_user)
+        .map(|preferences| HttpResponse::Ok().json(preferences))
+        .map_err(ApiError::GenericBadRequest)
+}
+
+#[guard(Authenticated)]
+async fn update_preferences(
+    pool: web::Data<Pool>,
+    scope_and_user: ScopeAndUser,
+    update: web::Json<NotificationPreferencesUpdate>,
+) -> Result<HttpResponse, ApiError> {
+    operations::notifications::update_preferences(&pool, &scope_and_user, update.into_inner())
+        .map(|preferences| HttpResponse::Ok().json(preferences))
+        .map_err(ApiError::GenericBadRequest)
+}
+
+async fn confirm_unsubscribe(
+    token: web::Path<String>,
+    token_secret: web::Data<TokenSecret>,
+) -> Result<HttpResponse, ApiError> {
+    let category = operations::notifications::unsubscribe_category(&token, &token_secret)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(format!(
+            "<!DOCTYPE html><html><body><form method=\"post\">\
+             <p>Do you want to stop receiving {} emails?</p>\
+             <button type=\"submit\">Unsubscribe</button></form></body></html>",
+            category.name()
+        )))
+}
+
+async fn unsubscribe(
+    pool: web::Data<Pool>,
+    token: web::Path<String>,
+    token_secret: web::Data<TokenSecret>,
+) -> Result<HttpResponse, ApiError> {
+    let category = operations::notifications::unsubscribe(&pool, &token, &token_secret)?;
+    Ok(HttpResponse::Ok().body(format!(
+        "You will no longer receive {} emails.",
+        category.name()
+    )))
+}
+
+pub fn notifications_app() -> impl HttpServiceFactory {
+    web::scope("/notifications").service(
+        web::resource("")
+            .route(web::get().to(preferences))
+            .route(web::put().to(update_preferences)),
+    )
+}
+
+/// Unsubscribe links are followed straight from emails, so this lives outside of the
+/// authenticated scope. `GET` only asks for confirmation, as link scanners and prefetching
+/// mail clients follow links too, and the confirmation `POST`s to unsubscribe.
+pub fn unsubscribe_app() -> impl HttpServiceFactory {
+    web::scope("/groups/api/v1/unsubscribe").service(
+        web::resource("/{token}")
+            .route(web::get().to(confirm_unsubscribe))
+            .route(web::post().to(unsubscribe)),
+    )
+}
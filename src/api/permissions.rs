@@ -0,0 +1,39 @@
+use crate::api::error::ApiError;
+use crate::db::operations;
+use crate::db::operations::models::GroupAction;
+use crate::db::Pool;
+use actix_web::dev::HttpServiceFactory;
+use actix_web::web;
+use actix_web::HttpResponse;
+use dino_park_gate::scope::ScopeAndUser;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+struct ExplainQuery {
+    action: GroupAction,
+    member: Option<Uuid>,
+}
+
+#[guard(Authenticated)]
+async fn explain(
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+    query: web::Query<ExplainQuery>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    let query = query.into_inner();
+    operations::permissions::explain(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        query.action,
+        query.member,
+    )
+    .map(|explanation| HttpResponse::Ok().json(explanation))
+    .map_err(Into::into)
+}
+
+pub fn permissions_app() -> impl HttpServiceFactory {
+    web::scope("/permissions").service(web::resource("/{group_name}").route(web::get().to(explain)))
+}
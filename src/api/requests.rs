@@ -1,29 +1,107 @@
 use crate::api::error::ApiError;
+use crate::capabilities::Providers;
 use crate::db::operations;
+use crate::db::operations::models::ApprovalStatus;
 use crate::db::Pool;
+use crate::mail::manager::MailMan;
 use crate::user::User;
 use actix_web::dev::HttpServiceFactory;
 use actix_web::web;
 use actix_web::HttpRequest;
 use actix_web::HttpResponse;
+use cis_client::AsyncCisClientTrait;
 use dino_park_gate::scope::ScopeAndUser;
+use serde::Deserialize;
+use serde::Serialize;
+use std::sync::Arc;
 use uuid::Uuid;
 
+const REASON_MAX_LEN: usize = 2000;
+
+#[derive(Deserialize)]
+pub struct Rejection {
+    reason: Option<String>,
+}
+
+impl Rejection {
+    pub fn checked(self) -> Result<Option<String>, ApiError> {
+        match self.reason {
+            Some(reason) if reason.len() > REASON_MAX_LEN => Err(ApiError::InputToLong),
+            Some(reason) if reason.trim().is_empty() => Ok(None),
+            reason => Ok(reason),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Approval {
+    group_expiration: Option<i32>,
+}
+
+#[derive(Serialize)]
+struct ApprovalResult {
+    status: ApprovalStatus,
+}
+
 #[guard(Ndaed, None, Medium)]
 async fn reject(
     _: HttpRequest,
     pool: web::Data<Pool>,
     path: web::Path<(String, Uuid)>,
     scope_and_user: ScopeAndUser,
+    rejection: Option<web::Json<Rejection>>,
+    mail_man: web::Data<MailMan>,
 ) -> Result<HttpResponse, ApiError> {
     let (group_name, user_uuid) = path.into_inner();
     let member = User { user_uuid };
-    match operations::requests::reject_request(&pool, &scope_and_user, &group_name, &member) {
+    let reason = match rejection {
+        Some(rejection) => rejection.into_inner().checked()?,
+        None => None,
+    };
+    match operations::requests::reject_request(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        &member,
+        reason,
+        &mail_man,
+    ) {
         Ok(_) => Ok(HttpResponse::Created().json("")),
         Err(e) => Err(ApiError::GenericBadRequest(e)),
     }
 }
 
+#[guard(Ndaed, None, Medium)]
+async fn approve<T: AsyncCisClientTrait>(
+    _: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<(String, Uuid)>,
+    scope_and_user: ScopeAndUser,
+    approval: web::Json<Approval>,
+    cis_client: web::Data<T>,
+    mail_man: web::Data<MailMan>,
+    providers: web::Data<Providers>,
+) -> Result<HttpResponse, ApiError> {
+    let (group_name, user_uuid) = path.into_inner();
+    let member = User { user_uuid };
+    let group_expiration = approval.into_inner().group_expiration;
+    match operations::requests::approve_request(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        &member,
+        group_expiration,
+        Arc::clone(&*cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await
+    {
+        Ok(status) => Ok(HttpResponse::Ok().json(ApprovalResult { status })),
+        Err(e) => Err(ApiError::GenericBadRequest(e)),
+    }
+}
+
 #[guard(Ndaed)]
 async fn pending(
     _: HttpRequest,
@@ -37,8 +115,12 @@ async fn pending(
     }
 }
 
-pub fn requests_app() -> impl HttpServiceFactory {
+pub fn requests_app<T: AsyncCisClientTrait + 'static>() -> impl HttpServiceFactory {
     web::scope("/requests")
+        .service(
+            web::resource("/{group_name}/{user_uuid}/approve").route(web::post().to(approve::<T>)),
+        )
+        .service(web::resource("/{group_name}/{user_uuid}/reject").route(web::post().to(reject)))
         .service(web::resource("/{group_name}/{user_uuid}").route(web::delete().to(reject)))
         .service(web::resource("/{group_name}").route(web::get().to(pending)))
 }
@@ -0,0 +1,96 @@
+use crate::api::error::ApiError;
+use crate::db::operations;
+use crate::db::types::PermissionType;
+use crate::db::types::RoleType;
+use crate::db::Pool;
+use actix_web::dev::HttpServiceFactory;
+use actix_web::web;
+use actix_web::HttpResponse;
+use dino_park_gate::scope::ScopeAndUser;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct RolePermissions {
+    permissions: Vec<PermissionType>,
+}
+
+#[derive(Deserialize)]
+pub struct CustomRole {
+    permissions: Vec<PermissionType>,
+    #[serde(default)]
+    position: i32,
+}
+
+#[guard(Ndaed, None, Medium)]
+async fn list_roles(
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    operations::roles::get_roles(&pool, &scope_and_user, &group_name)
+        .map(|roles| HttpResponse::Ok().json(roles))
+        .map_err(Into::into)
+}
+
+#[guard(Ndaed, None, Medium)]
+async fn update_role(
+    pool: web::Data<Pool>,
+    path: web::Path<(String, RoleType)>,
+    role_permissions: web::Json<RolePermissions>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    let (group_name, typ) = path.into_inner();
+    operations::roles::update_role_permissions(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        typ,
+        role_permissions.into_inner().permissions,
+    )
+    .map(|role| HttpResponse::Ok().json(role))
+    .map_err(Into::into)
+}
+
+#[guard(Ndaed, None, Medium)]
+async fn put_custom_role(
+    pool: web::Data<Pool>,
+    path: web::Path<(String, String)>,
+    custom_role: web::Json<CustomRole>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    let (group_name, name) = path.into_inner();
+    let custom_role = custom_role.into_inner();
+    operations::roles::upsert_custom_role(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        &name,
+        custom_role.permissions,
+        custom_role.position,
+    )
+    .map(|role| HttpResponse::Ok().json(role))
+    .map_err(Into::into)
+}
+
+#[guard(Ndaed, None, Medium)]
+async fn delete_custom_role(
+    pool: web::Data<Pool>,
+    path: web::Path<(String, String)>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    let (group_name, name) = path.into_inner();
+    operations::roles::delete_custom_role(&pool, &scope_and_user, &group_name, &name)
+        .map(|_| HttpResponse::Ok().json(""))
+        .map_err(Into::into)
+}
+
+pub fn roles_app() -> impl HttpServiceFactory {
+    web::scope("/roles")
+        .service(web::resource("/{group_name}").route(web::get().to(list_roles)))
+        .service(web::resource("/{group_name}/{role_type}").route(web::put().to(update_role)))
+        .service(
+            web::resource("/{group_name}/custom/{name}")
+                .route(web::put().to(put_custom_role))
+                .route(web::delete().to(delete_custom_role)),
+        )
+}
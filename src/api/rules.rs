@@ -0,0 +1,71 @@
+use crate::api::error::ApiError;
+use crate::db::operations;
+use crate::db::operations::models::NewRule;
+use crate::db::Pool;
+use actix_web::dev::HttpServiceFactory;
+use actix_web::web;
+use actix_web::HttpResponse;
+use cis_client::AsyncCisClientTrait;
+use dino_park_gate::scope::ScopeAndUser;
+use std::sync::Arc;
+
+#[guard(Authenticated)]
+async fn list_rules(
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    operations::rules::get_rules(&pool, &group_name)
+        .map(|rules| HttpResponse::Ok().json(rules))
+        .map_err(Into::into)
+}
+
+#[guard(Ndaed, None, Medium)]
+async fn add_rule<T: AsyncCisClientTrait>(
+    cis_client: web::Data<T>,
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+    new_rule: web::Json<NewRule>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    let new_rule = new_rule.into_inner().checked()?;
+    operations::rules::add_rule(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        new_rule,
+        Arc::clone(&*cis_client),
+    )
+    .await
+    .map(|rule| HttpResponse::Created().json(rule))
+    .map_err(Into::into)
+}
+
+#[guard(Ndaed, None, Medium)]
+async fn remove_rule<T: AsyncCisClientTrait>(
+    cis_client: web::Data<T>,
+    pool: web::Data<Pool>,
+    path: web::Path<(String, i32)>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    let (group_name, rule_id) = path.into_inner();
+    operations::rules::remove_rule(
+        &pool,
+        &scope_and_user,
+        &group_name,
+        rule_id,
+        Arc::clone(&*cis_client),
+    )
+    .await
+    .map(|_| HttpResponse::Ok().json(""))
+    .map_err(Into::into)
+}
+
+pub fn rules_app<T: AsyncCisClientTrait + 'static>() -> impl HttpServiceFactory {
+    web::scope("/rules")
+        .service(
+            web::resource("/{group_name}")
+                .route(web::get().to(list_rules))
+                .route(web::post().to(add_rule::<T>)),
+        )
+        .service(web::resource("/{group_name}/{rule_id}").route(web::delete().to(remove_rule::<T>)))
+}
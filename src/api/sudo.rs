@@ -1,14 +1,16 @@
 use crate::api::error::ApiError;
+use crate::capabilities::Providers;
 use crate::cis::operations::send_groups_to_cis;
 use crate::db::operations;
 use crate::db::types::TrustType;
 use crate::db::Pool;
-use crate::mail::manager::subscribe_nda;
-use crate::mail::manager::unsubscribe_nda;
+use crate::mail::manager::MailMan;
 use crate::user::User;
 use actix_web::dev::HttpServiceFactory;
 use actix_web::web;
 use actix_web::HttpResponse;
+use chrono::DateTime;
+use chrono::Utc;
 use cis_client::AsyncCisClientTrait;
 use dino_park_gate::scope::ScopeAndUser;
 use serde::Deserialize;
@@ -31,6 +33,8 @@ pub struct AddUser {
     group_expiration: Option<i32>,
     #[serde(default)]
     no_host: bool,
+    #[serde(default)]
+    starts_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Deserialize)]
@@ -59,6 +63,8 @@ async fn add_member<T: AsyncCisClientTrait>(
     scope_and_user: ScopeAndUser,
     add_member: web::Json<AddUser>,
     cis_client: web::Data<T>,
+    mail_man: web::Data<MailMan>,
+    providers: web::Data<Providers>,
 ) -> Result<HttpResponse, ApiError> {
     let user_uuid = add_member.user_uuid;
     let host = if add_member.no_host {
@@ -73,7 +79,10 @@ async fn add_member<T: AsyncCisClientTrait>(
         &host,
         &User { user_uuid },
         add_member.group_expiration,
+        add_member.starts_at.map(|s| s.naive_utc()),
         Arc::clone(&*cis_client),
+        &mail_man,
+        &providers,
     )
     .await?;
     Ok(HttpResponse::Ok().json(""))
@@ -86,6 +95,7 @@ async fn add_admin<T: AsyncCisClientTrait>(
     scope_and_user: ScopeAndUser,
     add_admin: web::Json<AddUser>,
     cis_client: web::Data<T>,
+    mail_man: web::Data<MailMan>,
 ) -> Result<HttpResponse, ApiError> {
     let user_uuid = add_admin.user_uuid;
     let host = if add_admin.no_host {
@@ -100,6 +110,7 @@ async fn add_admin<T: AsyncCisClientTrait>(
         &host,
         &User { user_uuid },
         Arc::clone(&*cis_client),
+        &mail_man,
     )
     .await?;
     Ok(HttpResponse::Ok().json(""))
@@ -138,6 +149,8 @@ async fn remove_member<T: AsyncCisClientTrait>(
     path: web::Path<(String, Uuid)>,
     scope_and_user: ScopeAndUser,
     cis_client: web::Data<T>,
+    mail_man: web::Data<MailMan>,
+    providers: web::Data<Providers>,
 ) -> Result<HttpResponse, ApiError> {
     let (group_name, user_uuid) = path.into_inner();
     let host = operations::users::user_by_id(&pool.clone(), &scope_and_user.user_id)?;
@@ -148,6 +161,8 @@ async fn remove_member<T: AsyncCisClientTrait>(
         &host,
         &User { user_uuid },
         Arc::clone(&*cis_client),
+        &mail_man,
+        &providers,
     )
     .await?;
     Ok(HttpResponse::Ok().json(""))
@@ -236,9 +251,10 @@ async fn delete_inactive_users(
 async fn subscribe_nda_mailing_list(
     pool: web::Data<Pool>,
     user_uuid: web::Path<Uuid>,
+    mail_man: web::Data<MailMan>,
 ) -> Result<HttpResponse, ApiError> {
     let user_profile = operations::users::user_profile_by_uuid(&pool, &user_uuid)?;
-    subscribe_nda(user_profile.email);
+    mail_man.subscribe_nda(user_profile.email);
     Ok(HttpResponse::Ok().json(""))
 }
 
@@ -246,9 +262,10 @@ async fn subscribe_nda_mailing_list(
 async fn unsubscribe_nda_mailing_list(
     pool: web::Data<Pool>,
     user_uuid: web::Path<Uuid>,
+    mail_man: web::Data<MailMan>,
 ) -> Result<HttpResponse, ApiError> {
     let user_profile = operations::users::user_profile_by_uuid(&pool, &user_uuid)?;
-    unsubscribe_nda(user_profile.email);
+    mail_man.unsubscribe_nda(user_profile.email);
     Ok(HttpResponse::Ok().json(""))
 }
 
@@ -270,6 +287,8 @@ async fn change_trust<T: AsyncCisClientTrait>(
     scope_and_user: ScopeAndUser,
     trust_change: web::Json<ChangeTrust>,
     cis_client: web::Data<T>,
+    mail_man: web::Data<MailMan>,
+    providers: web::Data<Providers>,
 ) -> Result<HttpResponse, ApiError> {
     operations::groups::update_group_trust(
         &pool,
@@ -277,6 +296,8 @@ async fn change_trust<T: AsyncCisClientTrait>(
         &group_name,
         &trust_change.trust,
         Arc::clone(&*cis_client),
+        &mail_man,
+        &providers,
     )
     .await?;
     Ok(HttpResponse::Ok().json(""))
@@ -301,6 +322,7 @@ async fn transfer_membership<T: AsyncCisClientTrait>(
     transfer: web::Json<TransferMemberShip>,
     scope_and_user: ScopeAndUser,
     cis_client: web::Data<T>,
+    mail_man: web::Data<MailMan>,
 ) -> Result<HttpResponse, ApiError> {
     operations::members::transfer(
         &pool,
@@ -313,6 +335,7 @@ async fn transfer_membership<T: AsyncCisClientTrait>(
             user_uuid: transfer.new_user_uuid,
         },
         Arc::clone(&*cis_client),
+        &mail_man,
     )
     .await
     .map(|_| HttpResponse::Ok().json(""))
@@ -330,6 +353,28 @@ async fn raw_data(
         .map_err(Into::into)
 }
 
+#[guard(Staff, Admin, Medium)]
+async fn failed_emails(
+    pool: web::Data<Pool>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    operations::outbox::failed_emails(&pool, &scope_and_user)
+        .map(|emails| HttpResponse::Ok().json(emails))
+        .map_err(Into::into)
+}
+
+#[guard(Staff, Admin, Medium)]
+async fn resend_email(
+    pool: web::Data<Pool>,
+    scope_and_user: ScopeAndUser,
+    email_id: web::Path<i32>,
+    mail_man: web::Data<MailMan>,
+) -> Result<HttpResponse, ApiError> {
+    operations::outbox::resend_email(&pool, &scope_and_user, email_id.into_inner(), &mail_man)
+        .map(|_| HttpResponse::Ok().json(""))
+        .map_err(Into::into)
+}
+
 pub fn sudo_app<T: AsyncCisClientTrait + 'static>() -> impl HttpServiceFactory {
     web::scope("/sudo")
         .service(web::resource("/transfer").route(web::post().to(transfer_membership::<T>)))
@@ -369,5 +414,7 @@ pub fn sudo_app<T: AsyncCisClientTrait + 'static>() -> impl HttpServiceFactory {
                 .route(web::post().to(subscribe_nda_mailing_list))
                 .route(web::delete().to(unsubscribe_nda_mailing_list)),
         )
+        .service(web::resource("/mail/failed").route(web::get().to(failed_emails)))
+        .service(web::resource("/mail/{email_id}/resend").route(web::post().to(resend_email)))
         .service(web::resource("/logs/all/raw").route(web::get().to(all_raw_logs)))
 }
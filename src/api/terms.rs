@@ -6,24 +6,37 @@ use actix_web::web;
 use actix_web::HttpResponse;
 use dino_park_gate::scope::ScopeAndUser;
 use serde::Deserialize;
+use serde::Serialize;
 
 const TERMS_MAX_LEN: usize = 7000;
 
 #[derive(Deserialize)]
 pub struct TermsUpdate {
     text: String,
+    #[serde(default)]
+    require_reacceptance: Option<bool>,
 }
 
 impl TermsUpdate {
-    pub fn checked(self) -> Result<String, ApiError> {
+    pub fn checked(self) -> Result<Self, ApiError> {
         if self.text.len() <= TERMS_MAX_LEN {
-            Ok(self.text)
+            Ok(self)
         } else {
             Err(ApiError::InputToLong)
         }
     }
 }
 
+#[derive(Deserialize)]
+pub struct TermsAcceptance {
+    version: Option<i32>,
+}
+
+#[derive(Serialize)]
+struct AcceptedTerms {
+    version: i32,
+}
+
 #[guard(Authenticated)]
 async fn view_terms(
     pool: web::Data<Pool>,
@@ -54,23 +67,62 @@ async fn update_terms(
     terms_update: web::Json<TermsUpdate>,
     scope_and_user: ScopeAndUser,
 ) -> Result<HttpResponse, ApiError> {
+    let terms_update = terms_update.into_inner().checked()?;
     match operations::terms::update_terms(
         &pool,
         &scope_and_user,
         &group_name,
-        terms_update.into_inner().checked()?,
+        terms_update.text,
+        terms_update.require_reacceptance,
     ) {
         Ok(_) => Ok(HttpResponse::Created().json("")),
         Err(e) => Err(ApiError::GenericBadRequest(e)),
     }
 }
 
+#[guard(Authenticated)]
+async fn terms_versions(
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    operations::terms::terms_versions(&pool, &group_name)
+        .map(|versions| HttpResponse::Ok().json(versions))
+        .map_err(ApiError::GenericBadRequest)
+}
+
+#[guard(Authenticated)]
+async fn accept_terms(
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+    scope_and_user: ScopeAndUser,
+    acceptance: Option<web::Json<TermsAcceptance>>,
+) -> Result<HttpResponse, ApiError> {
+    let version = acceptance.and_then(|a| a.into_inner().version);
+    let version = operations::terms::accept_terms(&pool, &scope_and_user, &group_name, version)?;
+    Ok(HttpResponse::Ok().json(AcceptedTerms { version }))
+}
+
+#[guard(Ndaed)]
+async fn pending_acceptances(
+    pool: web::Data<Pool>,
+    group_name: web::Path<String>,
+    scope_and_user: ScopeAndUser,
+) -> Result<HttpResponse, ApiError> {
+    operations::terms::pending_acceptances(&pool, &scope_and_user, &group_name)
+        .map(|pending| HttpResponse::Ok().json(pending))
+        .map_err(Into::into)
+}
+
 pub fn terms_app() -> impl HttpServiceFactory {
-    web::scope("/terms").service(
-        web::resource("/{group_name}")
-            .route(web::get().to(view_terms))
-            .route(web::put().to(update_terms))
-            .route(web::delete().to(delete_terms))
-            .route(web::post().to(update_terms)),
-    )
+    web::scope("/terms")
+        .service(web::resource("/{group_name}/versions").route(web::get().to(terms_versions)))
+        .service(web::resource("/{group_name}/accept").route(web::post().to(accept_terms)))
+        .service(web::resource("/{group_name}/pending").route(web::get().to(pending_acceptances)))
+        .service(
+            web::resource("/{group_name}")
+                .route(web::get().to(view_terms))
+                .route(web::put().to(update_terms))
+                .route(web::delete().to(delete_terms))
+                .route(web::post().to(update_terms)),
+        )
 }
@@ -1,6 +1,5 @@
 use crate::api::error::ApiError;
 use crate::db::operations;
-use crate::db::types::RoleType;
 use crate::db::types::TrustType;
 use crate::db::Pool;
 use actix_web::dev::HttpServiceFactory;
@@ -46,7 +45,7 @@ async fn search_users(
                 .into_iter()
                 .filter(|u| {
                     if curators {
-                        u.role.is_none() || u.role == Some(RoleType::Member)
+                        !u.role.map(|r| r.is_curator()).unwrap_or_default()
                     } else {
                         !u.invited && u.role.is_none()
                     }
@@ -0,0 +1,62 @@
+use crate::capabilities::CapabilityProvider;
+use crate::capabilities::MembershipEvent;
+use crate::capabilities::Providers;
+use crate::db::types::CapabilityType;
+use failure::Error;
+use log::info;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Records every event instead of talking to an external system.
+#[derive(Clone)]
+pub struct LocalProvider {
+    capability: CapabilityType,
+    events: Arc<Mutex<Vec<MembershipEvent>>>,
+}
+
+impl LocalProvider {
+    pub fn new(capability: CapabilityType) -> Self {
+        LocalProvider {
+            capability,
+            events: Default::default(),
+        }
+    }
+
+    pub fn events(&self) -> Vec<MembershipEvent> {
+        self.events
+            .lock()
+            .map(|events| events.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl CapabilityProvider for LocalProvider {
+    fn capability(&self) -> CapabilityType {
+        self.capability
+    }
+
+    fn sync(
+        &self,
+        event: MembershipEvent,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> {
+        info!(
+            "{:?}: {:?} {} in {}",
+            self.capability, event.change, event.user_uuid, event.group_name
+        );
+        if let Ok(mut events) = self.events.lock() {
+            events.push(event);
+        }
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// A `LocalProvider` for every capability, used when running locally.
+pub fn providers() -> Providers {
+    let mut providers = Providers::default();
+    for capability in [CapabilityType::Gdrive, CapabilityType::Discourse] {
+        providers.register(Arc::new(LocalProvider::new(capability)));
+    }
+    providers
+}
@@ -0,0 +1,142 @@
+pub mod local;
+
+use crate::db::internal;
+use crate::db::model::Group;
+use crate::db::types::CapabilityType;
+use crate::db::Pool;
+use failure::Error;
+use futures::future::join_all;
+use log::error;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MembershipChange {
+    Added,
+    Removed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MembershipEvent {
+    pub group_name: String,
+    pub user_uuid: Uuid,
+    pub email: String,
+    pub change: MembershipChange,
+}
+
+/// Syncs group membership into the external system backing a `CapabilityType`.
+pub trait CapabilityProvider: Send + Sync + 'static {
+    fn capability(&self) -> CapabilityType;
+    fn sync(
+        &self,
+        event: MembershipEvent,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+}
+
+/// The providers membership changes are handed to, shared with the handlers as app data.
+#[derive(Clone, Default)]
+pub struct Providers {
+    providers: Vec<Arc<dyn CapabilityProvider>>,
+}
+
+impl Providers {
+    pub fn register(&mut self, provider: Arc<dyn CapabilityProvider>) {
+        self.providers.push(provider);
+    }
+
+    fn providers_for(&self, capabilities: &[CapabilityType]) -> Vec<Arc<dyn CapabilityProvider>> {
+        self.providers
+            .iter()
+            .filter(|p| capabilities.contains(&p.capability()))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Hands a membership change to every provider registered for one of the group's capabilities.
+/// Provider failures are logged and never fail the membership change itself.
+pub async fn sync_membership(
+    pool: &Pool,
+    providers: &Providers,
+    group_name: &str,
+    user_uuid: &Uuid,
+    change: MembershipChange,
+) {
+    if let Err(e) = _sync_membership(pool, providers, group_name, user_uuid, change).await {
+        error!(
+            "failed to sync capabilities of {} for {}: {}",
+            group_name, user_uuid, e
+        );
+    }
+}
+
+/// Hands a change of all given memberships to the providers of a group at once, e.g. when the
+/// group is archived or reactivated. Unlike `sync_membership` this works for inactive groups.
+pub async fn sync_group_memberships(
+    pool: &Pool,
+    providers: &Providers,
+    group: &Group,
+    user_uuids: &[Uuid],
+    change: MembershipChange,
+) {
+    for user_uuid in user_uuids {
+        if let Err(e) = sync_for_group(pool, providers, group, user_uuid, change).await {
+            error!(
+                "failed to sync capabilities of {} for {}: {}",
+                group.name, user_uuid, e
+            );
+        }
+    }
+}
+
+async fn _sync_membership(
+    pool: &Pool,
+    providers: &Providers,
+    group_name: &str,
+    user_uuid: &Uuid,
+    change: MembershipChange,
+) -> Result<(), Error> {
+    let connection = pool.get()?;
+    let group = internal::group::get_group(&connection, group_name)?;
+    drop(connection);
+    sync_for_group(pool, providers, &group, user_uuid, change).await
+}
+
+async fn sync_for_group(
+    pool: &Pool,
+    providers: &Providers,
+    group: &Group,
+    user_uuid: &Uuid,
+    change: MembershipChange,
+) -> Result<(), Error> {
+    let providers = providers.providers_for(&group.capabilities);
+    if providers.is_empty() {
+        return Ok(());
+    }
+    let connection = pool.get()?;
+    let user_profile = internal::user::slim_user_profile_by_uuid(&connection, user_uuid)?;
+    drop(connection);
+    let event = MembershipEvent {
+        group_name: group.name.clone(),
+        user_uuid: *user_uuid,
+        email: user_profile.email,
+        change,
+    };
+    for (provider, result) in providers
+        .iter()
+        .zip(join_all(providers.iter().map(|p| p.sync(event.clone()))).await)
+    {
+        if let Err(e) = result {
+            error!(
+                "{:?} provider failed to sync {} for {}: {}",
+                provider.capability(),
+                event.group_name,
+                event.user_uuid,
+                e
+            );
+        }
+    }
+    Ok(())
+}
@@ -12,6 +12,7 @@ use cis_profile::schema::Profile;
 use cis_profile::schema::PublisherAuthority;
 use failure::format_err;
 use failure::Error;
+use log::error;
 use std::collections::BTreeMap;
 use std::sync::Arc;
 use uuid::Uuid;
@@ -72,7 +73,31 @@ pub async fn send_groups_to_cis(
 ) -> Result<(), Error> {
     let connection = pool.get()?;
     let user_profile = internal::user::user_profile_by_uuid(&connection, user_uuid)?;
-    let groups = internal::member::group_names_for_user(&connection, user_uuid)?;
+    let groups = internal::include::effective_group_names_for_user(&connection, user_uuid)?;
     drop(connection);
     _send_groups_to_cis(cis_client, groups, user_profile.profile).await
 }
+
+/// Publishes the groups of every given user, carrying on past failures which are logged and
+/// reported together at the end.
+pub async fn send_groups_to_cis_for_all(
+    pool: &Pool,
+    cis_client: Arc<impl AsyncCisClientTrait>,
+    user_uuids: &[Uuid],
+) -> Result<(), Error> {
+    let mut failed = 0;
+    for user_uuid in user_uuids {
+        if let Err(e) = send_groups_to_cis(pool, Arc::clone(&cis_client), user_uuid).await {
+            error!("failed to publish groups for {}: {}", user_uuid, e);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(format_err!(
+            "failed to publish groups for {} of {} users",
+            failed,
+            user_uuids.len()
+        ));
+    }
+    Ok(())
+}
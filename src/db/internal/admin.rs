@@ -21,7 +21,8 @@ pub fn add_admin_role(
         group_id,
         typ: RoleType::Admin,
         name: ROLE_ADMIN.to_owned(),
-        permissions: vec![],
+        permissions: PermissionType::all(),
+        position: 0,
     };
     diesel::insert_into(schema::roles::table)
         .values(admin)
@@ -0,0 +1,83 @@
+use crate::db::model::*;
+use crate::db::operations::models::DisplayApproval;
+use crate::db::schema;
+use crate::db::types::PermissionType;
+use diesel::dsl::count;
+use diesel::prelude::*;
+use failure::Error;
+use uuid::Uuid;
+
+/// Records an approval of `approver_uuid` for `user_uuid` joining the group. Approvals are
+/// kept until the user becomes a member or the request or invitation goes away.
+pub fn add(
+    connection: &PgConnection,
+    group_id: i32,
+    user_uuid: &Uuid,
+    approver_uuid: &Uuid,
+) -> Result<(), Error> {
+    let approval = InsertApproval {
+        group_id,
+        user_uuid: *user_uuid,
+        approver_uuid: *approver_uuid,
+    };
+    diesel::insert_into(schema::approvals::table)
+        .values(&approval)
+        .on_conflict_do_nothing()
+        .execute(connection)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+/// Counts the approvals for `user_uuid` given by members whose current role may still invite,
+/// which are exactly the hosts allowed to approve. Approvals of hosts who were demoted or removed
+/// since do not count.
+pub fn count_for(connection: &PgConnection, group_id: i32, user_uuid: &Uuid) -> Result<i64, Error> {
+    use schema::approvals as a;
+    use schema::memberships as m;
+    use schema::roles as r;
+    a::table
+        .filter(a::group_id.eq(group_id))
+        .filter(a::user_uuid.eq(user_uuid))
+        .inner_join(
+            m::table.on(m::group_id
+                .eq(a::group_id)
+                .and(m::user_uuid.eq(a::approver_uuid))),
+        )
+        .inner_join(r::table.on(r::role_id.eq(m::role_id)))
+        .filter(r::permissions.contains(vec![PermissionType::InviteMember]))
+        .select(count(a::approver_uuid))
+        .first(connection)
+        .map_err(Into::into)
+}
+
+pub fn approvals(
+    connection: &PgConnection,
+    group_name: &str,
+) -> Result<Vec<DisplayApproval>, Error> {
+    use schema::approvals as a;
+    use schema::groups as g;
+    a::table
+        .inner_join(g::table)
+        .filter(g::name.eq(group_name))
+        .select((a::user_uuid, a::approver_uuid, a::approved_ts))
+        .order((a::user_uuid, a::approved_ts))
+        .get_results::<DisplayApproval>(connection)
+        .map_err(Into::into)
+}
+
+pub fn delete_for(connection: &PgConnection, group_id: i32, user_uuid: &Uuid) -> Result<(), Error> {
+    diesel::delete(schema::approvals::table)
+        .filter(schema::approvals::group_id.eq(group_id))
+        .filter(schema::approvals::user_uuid.eq(user_uuid))
+        .execute(connection)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+pub fn delete_approvals(connection: &PgConnection, group_id: i32) -> Result<(), Error> {
+    diesel::delete(schema::approvals::table)
+        .filter(schema::approvals::group_id.eq(group_id))
+        .execute(connection)
+        .map(|_| ())
+        .map_err(Into::into)
+}
@@ -10,11 +10,15 @@ use crate::db::operations::models::SortGroupsBy;
 use crate::db::schema;
 use crate::db::types::*;
 use crate::db::views;
+use crate::utils::child_path;
+use crate::utils::ROOT_GROUP_PATH;
+use chrono::NaiveDateTime;
 use diesel::dsl::exists;
 use diesel::dsl::select;
 use diesel::prelude::*;
 use failure::Error;
 use serde_json::Value;
+use std::collections::BTreeSet;
 use std::convert::TryFrom;
 use uuid::Uuid;
 
@@ -66,10 +70,17 @@ pub fn add_group(
     connection: &PgConnection,
     new_group: NewGroup,
 ) -> Result<Group, Error> {
+    let path = match new_group.parent {
+        Some(ref parent) => {
+            let parent = get_group(connection, parent)?;
+            child_path(&parent.path, &parent.name)
+        }
+        None => String::from(ROOT_GROUP_PATH),
+    };
     let group = InsertGroup {
         name: new_group.name,
         active: true,
-        path: String::from("/access_information/mozillians/"),
+        path,
         description: new_group.description,
         capabilities: new_group.capabilities,
         typ: new_group.typ,
@@ -138,6 +149,18 @@ pub fn update_group(
                 .group_expiration
                 .map(|e| e.and_then(|i| if i < 1 { None } else { Some(i) }))
                 .map(|e| schema::groups::group_expiration.eq(e)),
+            group_update
+                .self_renewal_days
+                .map(|d| d.and_then(|i| if i < 1 { None } else { Some(i) }))
+                .map(|d| schema::groups::self_renewal_days.eq(d)),
+            group_update
+                .max_membership_days
+                .map(|d| d.and_then(|i| if i < 1 { None } else { Some(i) }))
+                .map(|d| schema::groups::max_membership_days.eq(d)),
+            group_update
+                .required_approvals
+                .map(|a| a.and_then(|i| if i < 2 { None } else { Some(i) }))
+                .map(|a| schema::groups::required_approvals.eq(a)),
         ))
         .get_result::<Group>(connection)
         .map_err(Into::into)
@@ -205,6 +228,12 @@ pub fn delete_group(host_uuid: &Uuid, connection: &PgConnection, name: &str) ->
         .execute(connection)
         .optional()
         .map(|_| log_delete(connection, &log_ctx, LogTargetType::Terms, None))?;
+    internal::rule::delete_rules(connection, group.id)?;
+    internal::include::delete_includes(connection, group.id)?;
+    internal::pending::delete_pending(connection, group.id)?;
+    internal::invitation::delete_email_invitations(connection, group.id)?;
+    internal::approval::delete_approvals(connection, group.id)?;
+    reparent_children(connection, &group)?;
     diesel::update(schema::groups::table)
         .filter(schema::groups::name.eq(name))
         .set((
@@ -216,6 +245,24 @@ pub fn delete_group(host_uuid: &Uuid, connection: &PgConnection, name: &str) ->
         .map_err(Into::into)
 }
 
+/// Moves all groups below `group` one level up.
+fn reparent_children(connection: &PgConnection, group: &Group) -> Result<(), Error> {
+    use schema::groups as g;
+    let prefix = child_path(&group.path, &group.name);
+    let children = g::table
+        .filter(g::path.like(format!("{}%", prefix)))
+        .select((g::group_id, g::path))
+        .get_results::<(i32, String)>(connection)?;
+    for (group_id, path) in children {
+        if let Some(rest) = path.strip_prefix(prefix.as_str()) {
+            diesel::update(g::table.filter(g::group_id.eq(group_id)))
+                .set(g::path.eq(format!("{}{}", group.path, rest)))
+                .execute(connection)?;
+        }
+    }
+    Ok(())
+}
+
 pub fn groups_for_user(connection: &PgConnection, user_uuid: &Uuid) -> Result<Vec<Group>, Error> {
     schema::memberships::table
         .filter(schema::memberships::user_uuid.eq(user_uuid))
@@ -230,7 +277,7 @@ pub fn reserve_group(connection: &PgConnection, host_uuid: &Uuid, name: &str) ->
     let group = InsertGroup {
         name: name.into(),
         active: false,
-        path: String::from("/access_information/mozillians/"),
+        path: String::from(ROOT_GROUP_PATH),
         description: Default::default(),
         capabilities: Default::default(),
         typ: GroupType::Closed,
@@ -257,14 +304,34 @@ pub fn reserve_group(connection: &PgConnection, host_uuid: &Uuid, name: &str) ->
 pub fn list_groups(
     connection: &PgConnection,
     filter: Option<String>,
+    parent: Option<String>,
     sort_by: SortGroupsBy,
     limit: i64,
     offset: i64,
 ) -> Result<PaginatedGroupsLists, Error> {
     let mut query = views::groups_list::table.into_boxed();
-    if let Some(filter) = filter {
+    if let Some(ref filter) = filter {
         query = query.filter(views::groups_list::name.ilike(format!("%{filter}%")))
     };
+    let path = match parent {
+        Some(parent) => {
+            let parent = get_group(connection, &parent)?;
+            let path = child_path(&parent.path, &parent.name);
+            query = query.filter(views::groups_list::path.eq(path.clone()));
+            Some(path)
+        }
+        None => None,
+    };
+    let including = schema::group_includes::table
+        .select(schema::group_includes::parent_id)
+        .distinct()
+        .get_results::<i32>(connection)?;
+    if !including.is_empty() {
+        let groups = query.get_results(connection)?;
+        return list_effective_groups(
+            connection, groups, including, filter, path, sort_by, limit, offset,
+        );
+    }
     query = match sort_by {
         SortGroupsBy::MemberCountAsc => query.order((
             views::groups_list::members_count.asc(),
@@ -291,6 +358,70 @@ pub fn list_groups(
     Ok(PaginatedGroupsLists { groups, next })
 }
 
+/// `groups_list` only counts direct members. Admission rules decide which members of included
+/// groups count as well, so groups including others are recounted here and the list is sorted
+/// and paginated in memory.
+#[allow(clippy::too_many_arguments)]
+fn list_effective_groups(
+    connection: &PgConnection,
+    mut groups: Vec<GroupsList>,
+    including: Vec<i32>,
+    filter: Option<String>,
+    path: Option<String>,
+    sort_by: SortGroupsBy,
+    limit: i64,
+    offset: i64,
+) -> Result<PaginatedGroupsLists, Error> {
+    let filter = filter.map(|filter| filter.to_lowercase());
+    for group in get_groups_by_ids(connection, &including)? {
+        if filter
+            .as_ref()
+            .map(|filter| !group.name.to_lowercase().contains(filter))
+            .unwrap_or_default()
+            || path
+                .as_ref()
+                .map(|path| path != &group.path)
+                .unwrap_or_default()
+        {
+            continue;
+        }
+        let member_count =
+            i64::try_from(internal::include::effective_member_uuids(connection, &group)?.len())?;
+        groups.retain(|listed| listed.name != group.name);
+        if member_count > 0 {
+            groups.push(GroupsList {
+                name: group.name,
+                typ: group.typ,
+                trust: group.trust,
+                path: group.path,
+                member_count,
+            });
+        }
+    }
+    match sort_by {
+        SortGroupsBy::MemberCountAsc => {
+            groups.sort_by(|a, b| (a.member_count, &a.name).cmp(&(b.member_count, &b.name)))
+        }
+        SortGroupsBy::MemberCountDesc => groups.sort_by(|a, b| {
+            b.member_count
+                .cmp(&a.member_count)
+                .then_with(|| a.name.cmp(&b.name))
+        }),
+        SortGroupsBy::NameAsc => groups.sort_by(|a, b| a.name.cmp(&b.name)),
+        SortGroupsBy::NameDesc => groups.sort_by(|a, b| b.name.cmp(&a.name)),
+    };
+    let groups: Vec<GroupsList> = groups
+        .into_iter()
+        .skip(usize::try_from(offset)?)
+        .take(usize::try_from(limit)?)
+        .collect();
+    let next = match i64::try_from(groups.len()) {
+        Ok(x) if x == limit => Some(offset + x),
+        _ => None,
+    };
+    Ok(PaginatedGroupsLists { groups, next })
+}
+
 pub fn inactive_groups(
     connection: &PgConnection,
     limit: i64,
@@ -304,6 +435,109 @@ pub fn inactive_groups(
         .map_err(Into::into)
 }
 
+fn group_ids_with_curators(connection: &PgConnection) -> Result<BTreeSet<i32>, Error> {
+    use schema::memberships as m;
+    use schema::roles as r;
+    let group_ids = m::table
+        .inner_join(r::table.on(r::role_id.eq(m::role_id)))
+        .filter(r::typ.eq_any(vec![RoleType::Admin, RoleType::Curator]))
+        .select(m::group_id)
+        .distinct()
+        .get_results::<i32>(connection)?;
+    Ok(group_ids.into_iter().collect())
+}
+
+/// Flags active groups without curators as inactive since `now` and clears the flag of groups
+/// which regained one. Curators are members too, so this covers groups without any members.
+/// Returns the newly flagged groups.
+pub fn flag_unattended_groups(
+    connection: &PgConnection,
+    now: NaiveDateTime,
+) -> Result<Vec<Group>, Error> {
+    use schema::groups as g;
+    let curated = group_ids_with_curators(connection)?;
+    let (unattended, attended): (Vec<i32>, Vec<i32>) = g::table
+        .filter(g::active.eq(true))
+        .select(g::group_id)
+        .get_results::<i32>(connection)?
+        .into_iter()
+        .partition(|id| !curated.contains(id));
+    diesel::update(
+        g::table
+            .filter(g::group_id.eq_any(attended))
+            .filter(g::inactive_since.is_not_null()),
+    )
+    .set(g::inactive_since.eq(None::<NaiveDateTime>))
+    .execute(connection)?;
+    diesel::update(
+        g::table
+            .filter(g::group_id.eq_any(unattended))
+            .filter(g::inactive_since.is_null()),
+    )
+    .set(g::inactive_since.eq(now))
+    .get_results(connection)
+    .map_err(Into::into)
+}
+
+/// Archives all active groups flagged as inactive before `before`. Scheduled memberships of
+/// those groups are dropped, current memberships are kept for a reactivation.
+pub fn archive_groups_inactive_before(
+    connection: &PgConnection,
+    before: NaiveDateTime,
+    now: NaiveDateTime,
+) -> Result<Vec<Group>, Error> {
+    use schema::groups as g;
+    let groups = diesel::update(
+        g::table
+            .filter(g::active.eq(true))
+            .filter(g::inactive_since.lt(before)),
+    )
+    .set((g::active.eq(false), g::archived_at.eq(now)))
+    .get_results::<Group>(connection)?;
+    for group in &groups {
+        internal::pending::delete_pending(connection, group.id)?;
+        let log_ctx = LogContext::with(group.id, Uuid::nil());
+        internal::log::db_log(
+            connection,
+            &log_ctx,
+            LogTargetType::Group,
+            LogOperationType::Updated,
+            log_comment_body("archived"),
+        )
+    }
+    Ok(groups)
+}
+
+pub fn reactivate_group(
+    host_uuid: &Uuid,
+    connection: &PgConnection,
+    group_name: &str,
+) -> Result<Group, Error> {
+    use schema::groups as g;
+    diesel::update(
+        g::table
+            .filter(g::name.eq(group_name))
+            .filter(g::archived_at.is_not_null()),
+    )
+    .set((
+        g::active.eq(true),
+        g::inactive_since.eq(None::<NaiveDateTime>),
+        g::archived_at.eq(None::<NaiveDateTime>),
+    ))
+    .get_result::<Group>(connection)
+    .map_err(Into::into)
+    .inspect(|group| {
+        let log_ctx = LogContext::with(group.id, *host_uuid);
+        internal::log::db_log(
+            connection,
+            &log_ctx,
+            LogTargetType::Group,
+            LogOperationType::Updated,
+            log_comment_body("reactivated"),
+        )
+    })
+}
+
 pub fn delete_inactive_group(connection: &PgConnection, group_name: &str) -> Result<(), Error> {
     use schema::groups as g;
     use schema::logs as l;
@@ -315,6 +549,7 @@ pub fn delete_inactive_group(connection: &PgConnection, group_name: &str) -> Res
     diesel::delete(l::table)
         .filter(l::group_id.eq(id))
         .execute(connection)?;
+    internal::terms::delete_terms_history(connection, id)?;
     diesel::delete(g::table)
         .filter(g::name.eq(group_name))
         .filter(g::active.eq(false))
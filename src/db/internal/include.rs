@@ -0,0 +1,352 @@
+use crate::db::error::DBError;
+use crate::db::internal;
+use crate::db::logs::add_to_comment_body;
+use crate::db::logs::log_comment_body;
+use crate::db::logs::LogContext;
+use crate::db::model::*;
+use crate::db::schema;
+use crate::db::types::*;
+use diesel::dsl::sql;
+use diesel::expression::SqlLiteral;
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use failure::Error;
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+/// Breadth first walk along `group_includes`, either towards the included groups or towards the
+/// including ones. The start groups are only part of the result if they are reachable.
+fn walk(connection: &PgConnection, start: Vec<i32>, upwards: bool) -> Result<Vec<i32>, Error> {
+    use schema::group_includes as gi;
+    let mut seen = BTreeSet::new();
+    let mut next = start;
+    while !next.is_empty() {
+        let found: Vec<i32> = if upwards {
+            gi::table
+                .filter(gi::child_id.eq_any(&next))
+                .select(gi::parent_id)
+                .get_results(connection)?
+        } else {
+            gi::table
+                .filter(gi::parent_id.eq_any(&next))
+                .select(gi::child_id)
+                .get_results(connection)?
+        };
+        next = found.into_iter().filter(|id| seen.insert(*id)).collect();
+    }
+    Ok(seen.into_iter().collect())
+}
+
+/// All groups included by the given group, directly or transitively.
+pub fn descendant_ids(connection: &PgConnection, group_id: i32) -> Result<Vec<i32>, Error> {
+    walk(connection, vec![group_id], false)
+}
+
+/// All groups including the given groups, directly or transitively.
+pub fn ancestor_ids(connection: &PgConnection, group_ids: Vec<i32>) -> Result<Vec<i32>, Error> {
+    walk(connection, group_ids, true)
+}
+
+pub fn included_group_names(
+    connection: &PgConnection,
+    group_name: &str,
+) -> Result<Vec<String>, Error> {
+    use schema::group_includes as gi;
+    use schema::groups as g;
+    let group = internal::group::get_group(connection, group_name)?;
+    gi::table
+        .filter(gi::parent_id.eq(group.id))
+        .inner_join(g::table.on(g::group_id.eq(gi::child_id)))
+        .select(g::name)
+        .order_by(g::name)
+        .get_results(connection)
+        .map_err(Into::into)
+}
+
+pub fn add_include(
+    host_uuid: &Uuid,
+    connection: &PgConnection,
+    parent: &Group,
+    child: &Group,
+) -> Result<(), Error> {
+    let include = InsertGroupInclude {
+        parent_id: parent.id,
+        child_id: child.id,
+        added_by: *host_uuid,
+    };
+    let log_ctx = LogContext::with(parent.id, *host_uuid);
+    diesel::insert_into(schema::group_includes::table)
+        .values(&include)
+        .on_conflict_do_nothing()
+        .execute(connection)
+        .map(|_| {
+            internal::log::db_log(
+                connection,
+                &log_ctx,
+                LogTargetType::Group,
+                LogOperationType::Updated,
+                add_to_comment_body("group", &child.name, log_comment_body("group included")),
+            )
+        })
+        .map_err(Into::into)
+}
+
+pub fn remove_include(
+    host_uuid: &Uuid,
+    connection: &PgConnection,
+    parent: &Group,
+    child: &Group,
+) -> Result<(), Error> {
+    use schema::group_includes as gi;
+    let log_ctx = LogContext::with(parent.id, *host_uuid);
+    let deleted = diesel::delete(gi::table)
+        .filter(gi::parent_id.eq(parent.id))
+        .filter(gi::child_id.eq(child.id))
+        .execute(connection)?;
+    if deleted == 0 {
+        return Err(DBError::NotApplicable.into());
+    }
+    internal::log::db_log(
+        connection,
+        &log_ctx,
+        LogTargetType::Group,
+        LogOperationType::Updated,
+        add_to_comment_body("group", &child.name, log_comment_body("group excluded")),
+    );
+    Ok(())
+}
+
+pub fn delete_includes(connection: &PgConnection, group_id: i32) -> Result<(), Error> {
+    use schema::group_includes as gi;
+    diesel::delete(gi::table)
+        .filter(gi::parent_id.eq(group_id).or(gi::child_id.eq(group_id)))
+        .execute(connection)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+/// The group followed by all groups it includes, whose members may be effective members.
+pub fn effective_group_ids(connection: &PgConnection, group_id: i32) -> Result<Vec<i32>, Error> {
+    let mut group_ids = vec![group_id];
+    group_ids.extend(descendant_ids(connection, group_id)?);
+    Ok(group_ids)
+}
+
+/// Users who are members of the group or of any group it includes, whether admitted or not.
+/// Their effective groups may change along with the group.
+pub fn reachable_member_uuids(
+    connection: &PgConnection,
+    group_id: i32,
+) -> Result<Vec<Uuid>, Error> {
+    use schema::memberships as m;
+    let group_ids = effective_group_ids(connection, group_id)?;
+    m::table
+        .filter(m::group_id.eq_any(group_ids))
+        .select(m::user_uuid)
+        .distinct()
+        .get_results(connection)
+        .map_err(Into::into)
+}
+
+/// A member of an included group only counts as a member of `group` if they could join it
+/// directly: they need the group's trust level and must meet its admission rules. Checks all
+/// `candidates` at once.
+fn admitted_inherited(
+    connection: &PgConnection,
+    group: &Group,
+    candidates: &[Uuid],
+    visiting: &mut BTreeSet<i32>,
+) -> Result<BTreeSet<Uuid>, Error> {
+    if candidates.is_empty() {
+        return Ok(BTreeSet::new());
+    }
+    let candidates = internal::user::user_trusts(connection, candidates)?
+        .into_iter()
+        .filter(|(_, trust)| *trust >= group.trust)
+        .collect();
+    internal::rule::admitted_by_rules(connection, group, candidates, visiting)
+}
+
+/// Users who are not members of the group themselves but are admitted to it as members of a
+/// group it includes.
+pub fn inherited_member_uuids(
+    connection: &PgConnection,
+    group: &Group,
+) -> Result<Vec<Uuid>, Error> {
+    use schema::memberships as m;
+    let descendants = descendant_ids(connection, group.id)?;
+    if descendants.is_empty() {
+        return Ok(vec![]);
+    }
+    let direct = m::table
+        .filter(m::group_id.eq(group.id))
+        .select(m::user_uuid)
+        .get_results::<Uuid>(connection)?;
+    let candidates = m::table
+        .filter(m::group_id.eq_any(descendants))
+        .filter(m::user_uuid.ne_all(direct))
+        .select(m::user_uuid)
+        .distinct()
+        .get_results::<Uuid>(connection)?;
+    admitted_inherited(connection, group, &candidates, &mut BTreeSet::new())
+        .map(|inherited| inherited.into_iter().collect())
+}
+
+/// The groups whose memberships show up when listing the effective members of a group, along
+/// with the users admitted through the included ones.
+pub fn effective_listing(
+    connection: &PgConnection,
+    group_id: i32,
+) -> Result<(Vec<i32>, Vec<Uuid>), Error> {
+    let group_ids = effective_group_ids(connection, group_id)?;
+    let inherited = match internal::group::get_group_by_id(connection, group_id)? {
+        Some(ref group) if group_ids.len() > 1 => inherited_member_uuids(connection, group)?,
+        _ => vec![],
+    };
+    Ok((group_ids, inherited))
+}
+
+/// Users who are members of the group or admitted members of any group it includes.
+pub fn effective_member_uuids(
+    connection: &PgConnection,
+    group: &Group,
+) -> Result<Vec<Uuid>, Error> {
+    use schema::memberships as m;
+    let mut members = m::table
+        .filter(m::group_id.eq(group.id))
+        .select(m::user_uuid)
+        .get_results::<Uuid>(connection)?;
+    members.extend(inherited_member_uuids(connection, group)?);
+    Ok(members)
+}
+
+/// Whether the user is a member of the group or an admitted member of any group it includes.
+/// `visiting` is handed on to the admission rules, see `rule::admitted_by_rules`.
+pub fn is_effective_member_of(
+    connection: &PgConnection,
+    user_uuid: &Uuid,
+    group: &Group,
+    visiting: &mut BTreeSet<i32>,
+) -> Result<bool, Error> {
+    effective_members_among(connection, group, &[*user_uuid], visiting)
+        .map(|members| members.contains(user_uuid))
+}
+
+/// Those of `user_uuids` who are members of the group or admitted members of any group it
+/// includes.
+pub fn effective_members_among(
+    connection: &PgConnection,
+    group: &Group,
+    user_uuids: &[Uuid],
+    visiting: &mut BTreeSet<i32>,
+) -> Result<BTreeSet<Uuid>, Error> {
+    use schema::memberships as m;
+    let mut members = m::table
+        .filter(m::group_id.eq(group.id))
+        .filter(m::user_uuid.eq_any(user_uuids))
+        .select(m::user_uuid)
+        .get_results::<Uuid>(connection)?
+        .into_iter()
+        .collect::<BTreeSet<_>>();
+    let rest = user_uuids
+        .iter()
+        .filter(|user_uuid| !members.contains(*user_uuid))
+        .copied()
+        .collect::<Vec<_>>();
+    if rest.is_empty() {
+        return Ok(members);
+    }
+    let descendants = descendant_ids(connection, group.id)?;
+    if descendants.is_empty() {
+        return Ok(members);
+    }
+    let candidates = m::table
+        .filter(m::group_id.eq_any(descendants))
+        .filter(m::user_uuid.eq_any(rest))
+        .select(m::user_uuid)
+        .distinct()
+        .get_results::<Uuid>(connection)?;
+    members.extend(admitted_inherited(
+        connection,
+        group,
+        &candidates,
+        visiting,
+    )?);
+    Ok(members)
+}
+
+/// Filter on `memberships` keeping a single membership per user out of `group_ids`, the one of
+/// the earliest group in the list. Members of several included groups are listed once this way.
+/// Only the integer ids end up in the SQL.
+pub fn first_membership_among(group_ids: &[i32]) -> SqlLiteral<Bool> {
+    let ids = group_ids
+        .iter()
+        .map(i32::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    sql(&format!(
+        "memberships.group_id = (SELECT first.group_id FROM memberships first \
+         WHERE first.user_uuid = memberships.user_uuid AND first.group_id IN ({ids}) \
+         ORDER BY array_position(ARRAY[{ids}], first.group_id) LIMIT 1)",
+        ids = ids
+    ))
+}
+
+/// Role of a listed membership as seen from the group: members of included groups are plain
+/// members of it, whatever role they hold in their own group.
+pub fn effective_role(group_id: i32) -> SqlLiteral<Role_type> {
+    sql(&format!(
+        "CASE WHEN memberships.group_id = {} THEN roles.typ ELSE 'member' END",
+        group_id
+    ))
+}
+
+/// Names of the groups a user is a member of, including all groups that include those
+/// transitively and admit the user.
+pub fn effective_group_names_for_user(
+    connection: &PgConnection,
+    user_uuid: &Uuid,
+) -> Result<Vec<String>, Error> {
+    use schema::groups as g;
+    use schema::memberships as m;
+    let group_ids = m::table
+        .filter(m::user_uuid.eq(user_uuid))
+        .select(m::group_id)
+        .get_results::<i32>(connection)?;
+    let mut names = g::table
+        .filter(g::group_id.eq_any(&group_ids))
+        .filter(g::active.eq(true))
+        .select(g::name)
+        .get_results::<String>(connection)?;
+    let ancestors = ancestor_ids(connection, group_ids.clone())?
+        .into_iter()
+        .filter(|id| !group_ids.contains(id))
+        .collect::<Vec<_>>();
+    if ancestors.is_empty() {
+        return Ok(names);
+    }
+    // one lookup for the trust and the groups with rules, only those get evaluated one by one
+    let trust = internal::user::user_trust(connection, user_uuid)?;
+    let with_rules = schema::group_rules::table
+        .filter(schema::group_rules::group_id.eq_any(&ancestors))
+        .select(schema::group_rules::group_id)
+        .distinct()
+        .get_results::<i32>(connection)?;
+    for group in internal::group::get_groups_by_ids(connection, &ancestors)? {
+        if trust < group.trust {
+            continue;
+        }
+        if !with_rules.contains(&group.id)
+            || internal::rule::admission_rules_met(
+                connection,
+                &group,
+                trust,
+                user_uuid,
+                &mut BTreeSet::new(),
+            )?
+        {
+            names.push(group.name);
+        }
+    }
+    Ok(names)
+}
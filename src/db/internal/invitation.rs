@@ -1,4 +1,5 @@
 use crate::db::internal;
+use crate::db::logs::add_to_comment_body;
 use crate::db::logs::log_comment_body;
 use crate::db::logs::LogContext;
 use crate::db::model::*;
@@ -12,8 +13,11 @@ use crate::db::types::LogOperationType;
 use crate::db::types::LogTargetType;
 use crate::db::types::TrustType;
 use crate::db::views;
+use crate::error::PacksError;
+use crate::tokens::InvitationToken;
 use crate::user::User;
 use chrono::NaiveDateTime;
+use chrono::Utc;
 use diesel::dsl::count;
 use diesel::prelude::*;
 use failure::Error;
@@ -40,6 +44,7 @@ macro_rules! scoped_invitations_for_user {
                     i::user_uuid,
                     i::invitation_expiration,
                     i::group_expiration,
+                    i::starts_at,
                     g::name,
                     t::text.is_not_null(),
                     i::added_by,
@@ -84,6 +89,7 @@ macro_rules! scoped_invitations_for {
                     u::trust.eq(TrustType::Staff),
                     i::invitation_expiration,
                     i::group_expiration,
+                    i::starts_at,
                     g::name,
                     t::text.is_not_null(),
                     i::added_by,
@@ -179,7 +185,8 @@ pub fn delete(
                 comment,
             );
         })
-        .map_err(Error::from)
+        .map_err(Error::from)?;
+    internal::approval::delete_for(connection, group.id, &member.user_uuid)
 }
 
 pub fn invite(
@@ -189,7 +196,8 @@ pub fn invite(
     member: User,
     invitation_expiration: Option<NaiveDateTime>,
     group_expiration: Option<i32>,
-) -> Result<(), Error> {
+    starts_at: Option<NaiveDateTime>,
+) -> Result<Invitation, Error> {
     let group = internal::group::get_group(connection, group_name)?;
     let invitation = Invitation {
         user_uuid: member.user_uuid,
@@ -197,12 +205,14 @@ pub fn invite(
         invitation_expiration,
         group_expiration,
         added_by: host.user_uuid,
+        starts_at,
+        token_nonce: Some(Uuid::new_v4()),
     };
     let log_ctx = LogContext::with(group.id, host.user_uuid).with_user(member.user_uuid);
     diesel::insert_into(schema::invitations::table)
         .values(&invitation)
-        .execute(connection)
-        .map(|_| {
+        .get_result(connection)
+        .map(|invitation| {
             internal::log::db_log(
                 connection,
                 &log_ctx,
@@ -210,8 +220,43 @@ pub fn invite(
                 LogOperationType::Created,
                 None,
             );
+            invitation
         })
         .map_err(Error::from)
+        .and_then(|invitation| {
+            internal::approval::add(connection, group.id, &member.user_uuid, &host.user_uuid)
+                .map(|_| invitation)
+        })
+}
+
+/// Adds the approval of `host` to the pending invitation of `member`.
+pub fn add_approval(
+    connection: &PgConnection,
+    group_name: &str,
+    host: &User,
+    member: &User,
+) -> Result<(), Error> {
+    let group = internal::group::get_group(connection, group_name)?;
+    schema::invitations::table
+        .filter(schema::invitations::user_uuid.eq(member.user_uuid))
+        .filter(schema::invitations::group_id.eq(group.id))
+        .first::<Invitation>(connection)?;
+    internal::approval::add(connection, group.id, &member.user_uuid, &host.user_uuid)
+}
+
+/// Looks up the invitation a token was issued for. Deleted or accepted invitations take their
+/// nonce with them which revokes all tokens issued for them.
+pub fn invitation_by_token(
+    connection: &PgConnection,
+    token: &InvitationToken,
+) -> Result<Option<Invitation>, Error> {
+    schema::invitations::table
+        .filter(schema::invitations::group_id.eq(token.group_id))
+        .filter(schema::invitations::user_uuid.eq(token.user_uuid))
+        .filter(schema::invitations::token_nonce.eq(token.nonce))
+        .first(connection)
+        .optional()
+        .map_err(Into::into)
 }
 
 pub fn pending_count(connection: &PgConnection, group_name: &str) -> Result<i64, Error> {
@@ -223,7 +268,15 @@ pub fn pending_count(connection: &PgConnection, group_name: &str) -> Result<i64,
     Ok(count)
 }
 
-pub fn accept(connection: &PgConnection, group_name: &str, member: &User) -> Result<(), Error> {
+/// Accepts an invitation and returns whether the membership is active right away, which is not
+/// the case for invitations with a `starts_at` in the future. Groups with terms are only joined
+/// with `accepted_terms`, which is recorded along with the membership.
+pub fn accept(
+    connection: &PgConnection,
+    group_name: &str,
+    member: &User,
+    accepted_terms: bool,
+) -> Result<bool, Error> {
     let group = internal::group::get_group(connection, group_name)?;
     let invitation = schema::invitations::table
         .filter(
@@ -232,6 +285,36 @@ pub fn accept(connection: &PgConnection, group_name: &str, member: &User) -> Res
                 .and(schema::invitations::group_id.eq(group.id)),
         )
         .first::<Invitation>(connection)?;
+    if let Some(terms) = internal::terms::current_terms(connection, &group)? {
+        if !accepted_terms {
+            return Err(PacksError::TermsNotAccepted.into());
+        }
+        internal::terms::accept_terms(connection, group.id, &member.user_uuid, terms.version)?;
+    }
+    let delete_invitation = || -> Result<(), Error> {
+        diesel::delete(schema::invitations::table)
+            .filter(
+                schema::invitations::user_uuid
+                    .eq(member.user_uuid)
+                    .and(schema::invitations::group_id.eq(group.id)),
+            )
+            .execute(connection)?;
+        internal::approval::delete_for(connection, group.id, &member.user_uuid)
+    };
+    if let Some(starts_at) = invitation.starts_at.filter(|s| *s > Utc::now().naive_utc()) {
+        internal::pending::add_pending(
+            connection,
+            &group,
+            &User {
+                user_uuid: invitation.added_by,
+            },
+            member,
+            invitation.group_expiration,
+            starts_at,
+        )?;
+        delete_invitation()?;
+        return Ok(false);
+    }
     let expiration =
         internal::expiration::map_expiration(invitation.group_expiration, group.group_expiration);
     let role = internal::member::member_role(connection, group_name)?;
@@ -261,14 +344,8 @@ pub fn accept(connection: &PgConnection, group_name: &str, member: &User) -> Res
                 log_comment_body("accepted invitation"),
             );
         })?;
-    diesel::delete(schema::invitations::table)
-        .filter(
-            schema::invitations::user_uuid
-                .eq(member.user_uuid)
-                .and(schema::invitations::group_id.eq(group.id)),
-        )
-        .execute(connection)?;
-    Ok(())
+    delete_invitation()?;
+    Ok(true)
 }
 
 pub fn expire_before(connection: &PgConnection, before: NaiveDateTime) -> Result<(), Error> {
@@ -286,6 +363,174 @@ pub fn expire_before(connection: &PgConnection, before: NaiveDateTime) -> Result
             LogOperationType::Deleted,
             log_comment_body("expired"),
         );
+        internal::approval::delete_for(connection, invitation.group_id, &invitation.user_uuid)?;
+    }
+    Ok(())
+}
+
+/// Stores an invitation for an email address without a profile. It gets converted into a
+/// regular invitation once a profile with that primary email shows up.
+pub fn invite_email(
+    connection: &PgConnection,
+    group_name: &str,
+    host: User,
+    email: &str,
+    invitation_expiration: Option<NaiveDateTime>,
+    group_expiration: Option<i32>,
+) -> Result<(), Error> {
+    let group = internal::group::get_group(connection, group_name)?;
+    let invitation = InsertEmailInvitation {
+        email: email.to_lowercase(),
+        group_id: group.id,
+        invitation_expiration,
+        group_expiration,
+        added_by: host.user_uuid,
+    };
+    let log_ctx = LogContext::with(group.id, host.user_uuid);
+    diesel::insert_into(schema::email_invitations::table)
+        .values(&invitation)
+        .on_conflict((
+            schema::email_invitations::email,
+            schema::email_invitations::group_id,
+        ))
+        .do_update()
+        .set(&invitation)
+        .execute(connection)
+        .map(|_| {
+            internal::log::db_log(
+                connection,
+                &log_ctx,
+                LogTargetType::Invitation,
+                LogOperationType::Created,
+                add_to_comment_body("email", &invitation.email, None),
+            );
+        })
+        .map_err(Error::from)
+}
+
+pub fn email_invitations(
+    connection: &PgConnection,
+    group_name: &str,
+) -> Result<Vec<EmailInvitation>, Error> {
+    let group = internal::group::get_group(connection, group_name)?;
+    schema::email_invitations::table
+        .filter(schema::email_invitations::group_id.eq(group.id))
+        .order(schema::email_invitations::invitation_expiration.asc())
+        .get_results(connection)
+        .map_err(Into::into)
+}
+
+pub fn delete_email_invitation(
+    connection: &PgConnection,
+    group_name: &str,
+    host: User,
+    email: &str,
+) -> Result<(), Error> {
+    let group = internal::group::get_group(connection, group_name)?;
+    let email = email.to_lowercase();
+    let log_ctx = LogContext::with(group.id, host.user_uuid);
+    diesel::delete(schema::email_invitations::table)
+        .filter(schema::email_invitations::email.eq(&email))
+        .filter(schema::email_invitations::group_id.eq(group.id))
+        .execute(connection)
+        .map(|_| {
+            internal::log::db_log(
+                connection,
+                &log_ctx,
+                LogTargetType::Invitation,
+                LogOperationType::Deleted,
+                add_to_comment_body("email", &email, None),
+            );
+        })
+        .map_err(Error::from)
+}
+
+pub fn delete_email_invitations(connection: &PgConnection, group_id: i32) -> Result<(), Error> {
+    diesel::delete(schema::email_invitations::table)
+        .filter(schema::email_invitations::group_id.eq(group_id))
+        .execute(connection)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+/// Turns all invitations for `email` into regular invitations for `member`. Groups `member`
+/// already belongs to are skipped.
+pub fn convert_email_invitations(
+    connection: &PgConnection,
+    email: &str,
+    member: &User,
+) -> Result<usize, Error> {
+    use schema::email_invitations as e;
+    let email = email.to_lowercase();
+    let email_invitations = e::table
+        .filter(e::email.eq(&email))
+        .get_results::<EmailInvitation>(connection)?;
+    let mut converted = 0;
+    for email_invitation in email_invitations {
+        if internal::member::get_membership(
+            connection,
+            email_invitation.group_id,
+            &member.user_uuid,
+        )?
+        .is_none()
+        {
+            let invitation = Invitation {
+                user_uuid: member.user_uuid,
+                group_id: email_invitation.group_id,
+                invitation_expiration: email_invitation.invitation_expiration,
+                group_expiration: email_invitation.group_expiration,
+                added_by: email_invitation.added_by,
+                starts_at: None,
+                token_nonce: Some(Uuid::new_v4()),
+            };
+            let log_ctx = LogContext::with(email_invitation.group_id, email_invitation.added_by)
+                .with_user(member.user_uuid);
+            diesel::insert_into(schema::invitations::table)
+                .values(&invitation)
+                .on_conflict_do_nothing()
+                .execute(connection)
+                .map(|_| {
+                    internal::log::db_log(
+                        connection,
+                        &log_ctx,
+                        LogTargetType::Invitation,
+                        LogOperationType::Created,
+                        add_to_comment_body("email", &email, log_comment_body("email invitation")),
+                    );
+                })?;
+            internal::approval::add(
+                connection,
+                email_invitation.group_id,
+                &member.user_uuid,
+                &email_invitation.added_by,
+            )?;
+            converted += 1;
+        }
+        diesel::delete(e::table)
+            .filter(e::email.eq(&email))
+            .filter(e::group_id.eq(email_invitation.group_id))
+            .execute(connection)?;
+    }
+    Ok(converted)
+}
+
+pub fn expire_email_invitations_before(
+    connection: &PgConnection,
+    before: NaiveDateTime,
+) -> Result<(), Error> {
+    let deleted = diesel::delete(schema::email_invitations::table)
+        .filter(schema::email_invitations::invitation_expiration.le(before))
+        .get_results::<EmailInvitation>(connection)?;
+
+    for invitation in deleted {
+        let log_ctx = LogContext::with(invitation.group_id, Uuid::default());
+        internal::log::db_log(
+            connection,
+            &log_ctx,
+            LogTargetType::Invitation,
+            LogOperationType::Deleted,
+            add_to_comment_body("email", &invitation.email, log_comment_body("expired")),
+        );
     }
     Ok(())
 }
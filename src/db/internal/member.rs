@@ -31,11 +31,17 @@ macro_rules! scoped_members_for {
             let offset = options.offset.unwrap_or_default();
             let limit = options.limit;
             let q = format!("{}%", options.query.unwrap_or_default());
-            let (members, next) = m::table
-                .filter(m::group_id.eq(group_id))
+            let (group_ids, inherited) = if options.effective {
+                internal::include::effective_listing(connection, group_id)?
+            } else {
+                (vec![group_id], vec![])
+            };
+            let role = internal::include::effective_role(group_id);
+            let mut query = m::table
+                .filter(m::group_id.eq_any(group_ids.clone()))
                 .inner_join(u::table.on(m::user_uuid.eq(u::user_uuid)))
                 .inner_join(r::table)
-                .filter(r::typ.eq_any(options.roles))
+                .filter(role.clone().eq_any(options.roles))
                 .filter(
                     u::first_name
                         .concat(" ")
@@ -46,7 +52,6 @@ macro_rules! scoped_members_for {
                         .or(u::username.ilike(&q))
                         .or(u::email.ilike(&q)),
                 )
-                .then_order_by(u::username)
                 .select((
                     m::user_uuid,
                     u::picture,
@@ -55,9 +60,17 @@ macro_rules! scoped_members_for {
                     u::username,
                     u::email,
                     u::trust.eq(TrustType::Staff),
-                    r::typ,
+                    role.clone(),
                     m::added_ts,
                 ))
+                .into_boxed();
+            if group_ids.len() > 1 {
+                query = query
+                    .filter(m::group_id.eq(group_id).or(m::user_uuid.eq_any(inherited)))
+                    .filter(internal::include::first_membership_among(&group_ids));
+            }
+            let (members, next) = query
+                .then_order_by(u::username)
                 .offset(offset)
                 .limit(limit + 1)
                 .get_results::<Member>(connection)
@@ -92,12 +105,18 @@ macro_rules! scoped_members_and_host_for {
             let offset = options.offset.unwrap_or_default();
             let limit = options.limit;
             let q = format!("{}%", options.query.unwrap_or_default());
+            let (group_ids, inherited) = if options.effective {
+                internal::include::effective_listing(connection, group_id)?
+            } else {
+                (vec![group_id], vec![])
+            };
+            let role = internal::include::effective_role(group_id);
             let mut query = m::table
-                .filter(m::group_id.eq(group_id))
+                .filter(m::group_id.eq_any(group_ids.clone()))
                 .inner_join(u::table.on(m::user_uuid.eq(u::user_uuid)))
                 .left_outer_join(h::table.on(m::added_by.eq(h::user_uuid)))
                 .inner_join(r::table)
-                .filter(r::typ.eq_any(options.roles))
+                .filter(role.clone().eq_any(options.roles))
                 .filter(
                     u::first_name
                         .concat(" ")
@@ -118,7 +137,7 @@ macro_rules! scoped_members_and_host_for {
                     u::trust.eq(TrustType::Staff),
                     m::added_ts,
                     m::expiration,
-                    r::typ,
+                    role.clone(),
                     m::added_by,
                     h::first_name.nullable(),
                     h::last_name.nullable(),
@@ -126,12 +145,19 @@ macro_rules! scoped_members_and_host_for {
                     h::email.nullable(),
                 ))
                 .into_boxed();
+            if group_ids.len() > 1 {
+                query = query
+                    .filter(m::group_id.eq(group_id).or(m::user_uuid.eq_any(inherited)))
+                    .filter(internal::include::first_membership_among(&group_ids));
+            }
             query = match options.order {
                 SortMembersBy::None => query,
-                SortMembersBy::ExpirationAsc => query.order_by((m::expiration.asc(), r::typ)),
-                SortMembersBy::ExpirationDesc => query.order_by((m::expiration.desc(), r::typ)),
-                SortMembersBy::RoleAsc => query.order_by((r::typ.asc(), m::expiration)),
-                SortMembersBy::RoleDesc => query.order_by((r::typ.desc(), m::expiration)),
+                SortMembersBy::ExpirationAsc => query.order_by((m::expiration.asc(), role.clone())),
+                SortMembersBy::ExpirationDesc => {
+                    query.order_by((m::expiration.desc(), role.clone()))
+                }
+                SortMembersBy::RoleAsc => query.order_by((role.clone().asc(), m::expiration)),
+                SortMembersBy::RoleDesc => query.order_by((role.clone().desc(), m::expiration)),
             };
 
             query = query
@@ -173,13 +199,19 @@ macro_rules! privileged_scoped_members_and_host_for {
             let offset = options.offset.unwrap_or_default();
             let limit = options.limit;
             let q = format!("{}%", options.query.unwrap_or_default());
+            let (group_ids, inherited) = if options.effective {
+                internal::include::effective_listing(connection, group_id)?
+            } else {
+                (vec![group_id], vec![])
+            };
+            let role = internal::include::effective_role(group_id);
             let mut query = m::table
-                .filter(m::group_id.eq(group_id))
+                .filter(m::group_id.eq_any(group_ids.clone()))
                 .inner_join(u::table.on(m::user_uuid.eq(u::user_uuid)))
                 .left_outer_join(l::table.on(m::user_uuid.eq(l::user_uuid)))
                 .left_outer_join(h::table.on(m::added_by.eq(h::user_uuid)))
                 .inner_join(r::table)
-                .filter(r::typ.eq_any(options.roles))
+                .filter(role.clone().eq_any(options.roles))
                 .filter(
                     u::first_name
                         .concat(" ")
@@ -204,7 +236,7 @@ macro_rules! privileged_scoped_members_and_host_for {
                     u::trust.eq(TrustType::Staff),
                     m::added_ts,
                     m::expiration,
-                    r::typ,
+                    role.clone(),
                     m::added_by,
                     h::first_name.nullable(),
                     h::last_name.nullable(),
@@ -212,12 +244,19 @@ macro_rules! privileged_scoped_members_and_host_for {
                     h::email.nullable(),
                 ))
                 .into_boxed();
+            if group_ids.len() > 1 {
+                query = query
+                    .filter(m::group_id.eq(group_id).or(m::user_uuid.eq_any(inherited)))
+                    .filter(internal::include::first_membership_among(&group_ids));
+            }
             query = match options.order {
                 SortMembersBy::None => query,
-                SortMembersBy::ExpirationAsc => query.order_by((m::expiration.asc(), r::typ)),
-                SortMembersBy::ExpirationDesc => query.order_by((m::expiration.desc(), r::typ)),
-                SortMembersBy::RoleAsc => query.order_by((r::typ.asc(), m::expiration)),
-                SortMembersBy::RoleDesc => query.order_by((r::typ.desc(), m::expiration)),
+                SortMembersBy::ExpirationAsc => query.order_by((m::expiration.asc(), role.clone())),
+                SortMembersBy::ExpirationDesc => {
+                    query.order_by((m::expiration.desc(), role.clone()))
+                }
+                SortMembersBy::RoleAsc => query.order_by((role.clone().asc(), m::expiration)),
+                SortMembersBy::RoleDesc => query.order_by((role.clone().desc(), m::expiration)),
             };
 
             query = query
@@ -264,6 +303,7 @@ macro_rules! membership_and_scoped_host_for {
                     m::added_ts,
                     m::expiration,
                     r::typ,
+                    r::name,
                     m::added_by,
                     h::first_name.nullable(),
                     h::last_name.nullable(),
@@ -324,6 +364,7 @@ pub fn add_member_role(
         typ: RoleType::Member,
         name: ROLE_MEMBER.to_owned(),
         permissions: vec![],
+        position: 0,
     };
     let log_ctx = LogContext::with(group_id, *host_uuid);
     diesel::insert_into(schema::roles::table)
@@ -367,6 +408,42 @@ pub fn member_role(connection: &PgConnection, group_name: &str) -> Result<Role,
         .map_err(Into::into)
 }
 
+/// Moves a member to the `Member` role or to one of the group's `Custom` roles.
+pub fn assign_role(
+    host_uuid: &Uuid,
+    connection: &PgConnection,
+    group_name: &str,
+    member: &User,
+    role_name: &str,
+) -> Result<Role, Error> {
+    let group = internal::group::get_group(connection, group_name)?;
+    let role = schema::roles::table
+        .filter(schema::roles::group_id.eq(group.id))
+        .filter(schema::roles::name.eq(role_name))
+        .filter(schema::roles::typ.eq_any(&[RoleType::Custom, RoleType::Member]))
+        .first::<Role>(connection)?;
+    let log_ctx = LogContext::with(group.id, *host_uuid).with_user(member.user_uuid);
+    diesel::update(
+        schema::memberships::table.filter(
+            schema::memberships::group_id
+                .eq(group.id)
+                .and(schema::memberships::user_uuid.eq(member.user_uuid)),
+        ),
+    )
+    .set(schema::memberships::role_id.eq(role.id))
+    .execute(connection)
+    .map(|_| {
+        internal::log::db_log(
+            connection,
+            &log_ctx,
+            LogTargetType::Membership,
+            LogOperationType::Updated,
+            log_comment_body(&format!("assigned role {}", role.name)),
+        );
+    })?;
+    Ok(role)
+}
+
 pub fn remove_from_group(
     host_uuid: &Uuid,
     connection: &PgConnection,
@@ -500,6 +577,59 @@ pub fn renew(
     .map_err(Into::into)
 }
 
+pub fn get_membership(
+    connection: &PgConnection,
+    group_id: i32,
+    user_uuid: &Uuid,
+) -> Result<Option<Membership>, Error> {
+    schema::memberships::table
+        .filter(schema::memberships::group_id.eq(group_id))
+        .filter(schema::memberships::user_uuid.eq(user_uuid))
+        .first(connection)
+        .optional()
+        .map_err(Into::into)
+}
+
+pub fn self_renew(
+    connection: &PgConnection,
+    group: &Group,
+    member: &User,
+    expiration: NaiveDateTime,
+    now: NaiveDateTime,
+) -> Result<(), Error> {
+    let log_ctx = LogContext::with(group.id, member.user_uuid).with_user(member.user_uuid);
+    diesel::update(
+        schema::memberships::table.filter(
+            schema::memberships::group_id
+                .eq(group.id)
+                .and(schema::memberships::user_uuid.eq(member.user_uuid)),
+        ),
+    )
+    .set((
+        schema::memberships::expiration.eq(expiration),
+        schema::memberships::self_renewed_at.eq(now),
+    ))
+    .execute(connection)
+    .map(|_| {
+        internal::log::db_log(
+            connection,
+            &log_ctx,
+            LogTargetType::Membership,
+            LogOperationType::Updated,
+            log_comment_body("self-renewed"),
+        );
+    })
+    .map_err(Into::into)
+}
+
+pub fn member_uuids(connection: &PgConnection, group_id: i32) -> Result<Vec<Uuid>, Error> {
+    schema::memberships::table
+        .filter(schema::memberships::group_id.eq(group_id))
+        .select(schema::memberships::user_uuid)
+        .get_results(connection)
+        .map_err(Into::into)
+}
+
 pub fn get_members_not_current(
     connection: &PgConnection,
     group_name: &str,
@@ -623,18 +753,3 @@ pub fn get_anonymous_member_emails(connection: &PgConnection) -> Result<Vec<Stri
         .get_results::<String>(connection)
         .map_err(Into::into)
 }
-
-pub fn group_names_for_user(
-    connection: &PgConnection,
-    user_uuid: &Uuid,
-) -> Result<Vec<String>, Error> {
-    use schema::groups as g;
-    use schema::memberships as m;
-
-    m::table
-        .filter(m::user_uuid.eq(user_uuid))
-        .inner_join(g::table)
-        .select(g::name)
-        .get_results::<String>(connection)
-        .map_err(Into::into)
-}
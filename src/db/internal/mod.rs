@@ -1,10 +1,17 @@
 pub mod admin;
+pub mod approval;
 pub mod expiration;
 pub mod group;
+pub mod include;
 pub mod invitation;
 pub mod log;
 pub mod member;
+pub mod notification;
+pub mod outbox;
+pub mod pending;
 pub mod raw;
 pub mod request;
+pub mod role;
+pub mod rule;
 pub mod terms;
 pub mod user;
@@ -0,0 +1,67 @@
+use crate::db::model::*;
+use crate::db::schema;
+use crate::db::types::NotificationCategoryType;
+use crate::user::User;
+use chrono::Utc;
+use diesel::prelude::*;
+use failure::Error;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// Returns all explicitly set preferences of `user`. Categories without an entry are enabled.
+pub fn preferences(
+    connection: &PgConnection,
+    user: &User,
+) -> Result<HashMap<NotificationCategoryType, bool>, Error> {
+    use schema::notification_preferences as n;
+    n::table
+        .filter(n::user_uuid.eq(user.user_uuid))
+        .select((n::category, n::enabled))
+        .get_results::<(NotificationCategoryType, bool)>(connection)
+        .map(|preferences| preferences.into_iter().collect())
+        .map_err(Into::into)
+}
+
+pub fn set_preference(
+    connection: &PgConnection,
+    user: &User,
+    category: NotificationCategoryType,
+    enabled: bool,
+) -> Result<(), Error> {
+    let preference = InsertNotificationPreference {
+        user_uuid: user.user_uuid,
+        category,
+        enabled,
+        updated: Utc::now().naive_utc(),
+    };
+    diesel::insert_into(schema::notification_preferences::table)
+        .values(&preference)
+        .on_conflict((
+            schema::notification_preferences::user_uuid,
+            schema::notification_preferences::category,
+        ))
+        .do_update()
+        .set(&preference)
+        .execute(connection)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+/// Returns the emails in `emails` whose users disabled `category`.
+pub fn opted_out(
+    connection: &PgConnection,
+    emails: &[String],
+    category: NotificationCategoryType,
+) -> Result<HashSet<String>, Error> {
+    use schema::notification_preferences as n;
+    use schema::profiles as p;
+    p::table
+        .inner_join(n::table.on(n::user_uuid.eq(p::user_uuid)))
+        .filter(p::email.eq_any(emails))
+        .filter(n::category.eq(category))
+        .filter(n::enabled.eq(false))
+        .select(p::email)
+        .get_results::<String>(connection)
+        .map(|emails| emails.into_iter().collect())
+        .map_err(Into::into)
+}
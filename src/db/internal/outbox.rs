@@ -0,0 +1,145 @@
+use crate::db::model::*;
+use crate::db::schema;
+use crate::db::types::EmailStatusType;
+use crate::mail::Email;
+use chrono::NaiveDateTime;
+use chrono::Utc;
+use diesel::prelude::*;
+use failure::Error;
+
+pub fn enqueue(connection: &PgConnection, email: &Email) -> Result<OutboxEmail, Error> {
+    let outbox_email = InsertOutboxEmail {
+        to_address: email.to.clone(),
+        bcc: email.bcc.clone(),
+        from_address: email.from.clone(),
+        subject: email.message.subject.clone(),
+        body: email.message.body.clone(),
+        html_body: email.message.html.clone(),
+        template: email.template.clone(),
+    };
+    diesel::insert_into(schema::email_outbox::table)
+        .values(&outbox_email)
+        .get_result(connection)
+        .map_err(Into::into)
+}
+
+/// Leases up to `limit` pending emails which are due by moving their next attempt to
+/// `lease_until`. Emails of a crashed delivery run are picked up again once the lease is over.
+pub fn claim_due(
+    connection: &PgConnection,
+    limit: i64,
+    lease_until: NaiveDateTime,
+) -> Result<Vec<OutboxEmail>, Error> {
+    use schema::email_outbox as o;
+    connection.transaction::<_, Error, _>(|| {
+        let due = o::table
+            .filter(o::status.eq(EmailStatusType::Pending))
+            .filter(o::next_attempt.le(Utc::now().naive_utc()))
+            .select(o::email_id)
+            .order(o::next_attempt.asc())
+            .limit(limit)
+            .for_update()
+            .skip_locked()
+            .get_results::<i32>(connection)?;
+        diesel::update(o::table.filter(o::email_id.eq_any(due)))
+            .set(o::next_attempt.eq(lease_until))
+            .get_results(connection)
+            .map_err(Into::into)
+    })
+}
+
+/// Leases a single pending email regardless of its next attempt.
+pub fn claim(
+    connection: &PgConnection,
+    email_id: i32,
+    lease_until: NaiveDateTime,
+) -> Result<Option<OutboxEmail>, Error> {
+    use schema::email_outbox as o;
+    diesel::update(
+        o::table
+            .filter(o::email_id.eq(email_id))
+            .filter(o::status.eq(EmailStatusType::Pending)),
+    )
+    .set(o::next_attempt.eq(lease_until))
+    .get_result(connection)
+    .optional()
+    .map_err(Into::into)
+}
+
+pub fn mark_sent(connection: &PgConnection, email_id: i32) -> Result<(), Error> {
+    use schema::email_outbox as o;
+    diesel::update(o::table.filter(o::email_id.eq(email_id)))
+        .set((
+            o::status.eq(EmailStatusType::Sent),
+            o::attempts.eq(o::attempts + 1),
+            o::last_error.eq(None::<String>),
+            o::sent_at.eq(Utc::now().naive_utc()),
+        ))
+        .execute(connection)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+/// Records a failed attempt. Without a `next_attempt` the email is given up on and moved to
+/// the failed state.
+pub fn mark_attempt_failed(
+    connection: &PgConnection,
+    email_id: i32,
+    error: &str,
+    next_attempt: Option<NaiveDateTime>,
+) -> Result<(), Error> {
+    use schema::email_outbox as o;
+    let (status, next_attempt) = match next_attempt {
+        Some(next_attempt) => (EmailStatusType::Pending, next_attempt),
+        None => (EmailStatusType::Failed, Utc::now().naive_utc()),
+    };
+    diesel::update(o::table.filter(o::email_id.eq(email_id)))
+        .set((
+            o::status.eq(status),
+            o::attempts.eq(o::attempts + 1),
+            o::last_error.eq(error),
+            o::next_attempt.eq(next_attempt),
+        ))
+        .execute(connection)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+/// Deletes emails sent before `sent_before` and returns how many were deleted.
+pub fn purge_sent(connection: &PgConnection, sent_before: NaiveDateTime) -> Result<usize, Error> {
+    use schema::email_outbox as o;
+    diesel::delete(
+        o::table
+            .filter(o::status.eq(EmailStatusType::Sent))
+            .filter(o::sent_at.lt(sent_before)),
+    )
+    .execute(connection)
+    .map_err(Into::into)
+}
+
+pub fn failed(connection: &PgConnection) -> Result<Vec<OutboxEmail>, Error> {
+    use schema::email_outbox as o;
+    o::table
+        .filter(o::status.eq(EmailStatusType::Failed))
+        .order(o::created.desc())
+        .get_results(connection)
+        .map_err(Into::into)
+}
+
+/// Moves a failed email back to pending with a fresh set of attempts.
+pub fn reset(connection: &PgConnection, email_id: i32) -> Result<Option<OutboxEmail>, Error> {
+    use schema::email_outbox as o;
+    diesel::update(
+        o::table
+            .filter(o::email_id.eq(email_id))
+            .filter(o::status.eq(EmailStatusType::Failed)),
+    )
+    .set((
+        o::status.eq(EmailStatusType::Pending),
+        o::attempts.eq(0),
+        o::next_attempt.eq(Utc::now().naive_utc()),
+    ))
+    .get_result(connection)
+    .optional()
+    .map_err(Into::into)
+}
@@ -0,0 +1,156 @@
+use crate::db::internal;
+use crate::db::logs::add_to_comment_body;
+use crate::db::logs::log_comment_body;
+use crate::db::logs::LogContext;
+use crate::db::model::*;
+use crate::db::schema;
+use crate::db::types::*;
+use crate::user::User;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use failure::Error;
+use uuid::Uuid;
+
+/// Schedules a membership starting at `starts_at`. The membership expiration is computed upon
+/// activation.
+pub fn add_pending(
+    connection: &PgConnection,
+    group: &Group,
+    host: &User,
+    member: &User,
+    group_expiration: Option<i32>,
+    starts_at: NaiveDateTime,
+) -> Result<(), Error> {
+    let pending = InsertPendingMembership {
+        user_uuid: member.user_uuid,
+        group_id: group.id,
+        group_expiration,
+        starts_at,
+        added_by: host.user_uuid,
+    };
+    let log_ctx = LogContext::with(group.id, host.user_uuid).with_user(member.user_uuid);
+    diesel::insert_into(schema::pending_memberships::table)
+        .values(&pending)
+        .on_conflict((
+            schema::pending_memberships::user_uuid,
+            schema::pending_memberships::group_id,
+        ))
+        .do_update()
+        .set(&pending)
+        .execute(connection)
+        .map(|_| {
+            internal::log::db_log(
+                connection,
+                &log_ctx,
+                LogTargetType::Membership,
+                LogOperationType::Created,
+                add_to_comment_body(
+                    "starts_at",
+                    &starts_at.to_string(),
+                    log_comment_body("scheduled"),
+                ),
+            );
+        })
+        .map_err(Into::into)
+}
+
+pub fn pending_starting_before(
+    connection: &PgConnection,
+    before: NaiveDateTime,
+) -> Result<Vec<PendingMembership>, Error> {
+    schema::pending_memberships::table
+        .filter(schema::pending_memberships::starts_at.le(before))
+        .get_results(connection)
+        .map_err(Into::into)
+}
+
+/// Turns a pending membership into a regular membership of `group` with the group's member
+/// role. Users who became members in the meantime keep their membership and role. The
+/// membership is added and the pending one removed in a single transaction.
+pub fn activate(
+    connection: &PgConnection,
+    pending: &PendingMembership,
+    group: &Group,
+) -> Result<(), Error> {
+    connection.transaction::<_, Error, _>(|| {
+        let role = internal::member::member_role(connection, &group.name)?;
+        let membership = InsertMembership {
+            group_id: group.id,
+            user_uuid: pending.user_uuid,
+            role_id: role.id,
+            expiration: internal::expiration::map_expiration(
+                pending.group_expiration,
+                group.group_expiration,
+            ),
+            added_by: pending.added_by,
+        };
+        let inserted = diesel::insert_into(schema::memberships::table)
+            .values(&membership)
+            .on_conflict_do_nothing()
+            .execute(connection)?;
+        discard(connection, pending)?;
+        if inserted > 0 {
+            let log_ctx = LogContext::with(group.id, pending.added_by).with_user(pending.user_uuid);
+            internal::log::db_log(
+                connection,
+                &log_ctx,
+                LogTargetType::Membership,
+                LogOperationType::Created,
+                log_comment_body("activated"),
+            );
+        }
+        Ok(())
+    })
+}
+
+/// Drops a pending membership without activating it.
+pub fn discard(connection: &PgConnection, pending: &PendingMembership) -> Result<(), Error> {
+    use schema::pending_memberships as p;
+    diesel::delete(p::table)
+        .filter(p::user_uuid.eq(pending.user_uuid))
+        .filter(p::group_id.eq(pending.group_id))
+        .execute(connection)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+/// Drops the pending memberships of a user for groups requiring more than `trust`.
+pub fn delete_pending_above_trust(
+    connection: &PgConnection,
+    user_uuid: &Uuid,
+    trust: TrustType,
+) -> Result<(), Error> {
+    use schema::groups as g;
+    use schema::pending_memberships as p;
+    let group_ids = p::table
+        .filter(p::user_uuid.eq(user_uuid))
+        .inner_join(g::table.on(g::group_id.eq(p::group_id)))
+        .select((g::group_id, g::trust))
+        .get_results::<(i32, TrustType)>(connection)?
+        .into_iter()
+        .filter(|(_, group_trust)| trust < *group_trust)
+        .map(|(group_id, _)| group_id)
+        .collect::<Vec<_>>();
+    diesel::delete(p::table)
+        .filter(p::user_uuid.eq(user_uuid))
+        .filter(p::group_id.eq_any(group_ids))
+        .execute(connection)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+pub fn delete_pending_for_user(connection: &PgConnection, user_uuid: &Uuid) -> Result<(), Error> {
+    diesel::delete(schema::pending_memberships::table)
+        .filter(schema::pending_memberships::user_uuid.eq(user_uuid))
+        .execute(connection)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+pub fn delete_pending(connection: &PgConnection, group_id: i32) -> Result<(), Error> {
+    diesel::delete(schema::pending_memberships::table)
+        .filter(schema::pending_memberships::group_id.eq(group_id))
+        .execute(connection)
+        .map(|_| ())
+        .map_err(Into::into)
+}
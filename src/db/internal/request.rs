@@ -1,5 +1,6 @@
 use crate::db::error::DBError;
 use crate::db::internal;
+use crate::db::logs::add_to_comment_body;
 use crate::db::logs::log_comment_body;
 use crate::db::logs::LogContext;
 use crate::db::model::*;
@@ -36,6 +37,7 @@ pub fn requests_for_user(
             r::user_uuid,
             r::created,
             r::request_expiration,
+            r::justification,
             g::name,
             t::text.nullable().is_not_null(),
         ))
@@ -70,6 +72,7 @@ macro_rules! scoped_requests_for {
                     u::trust.eq(TrustType::Staff),
                     r::created,
                     r::request_expiration,
+                    r::justification,
                     g::name,
                     t::text.is_not_null(),
                 ))
@@ -91,12 +94,17 @@ pub fn request(
     group_name: &str,
     member: User,
     request_expiration: Option<NaiveDateTime>,
+    justification: Option<String>,
 ) -> Result<(), Error> {
     let group = internal::group::get_group(connection, group_name)?;
+    let comment = justification
+        .as_deref()
+        .and_then(|j| add_to_comment_body("justification", j, None));
     let req = InsertRequest {
         user_uuid: member.user_uuid,
         group_id: group.id,
         request_expiration,
+        justification,
     };
     let log_ctx = LogContext::with(group.id, member.user_uuid);
     let rows = diesel::insert_into(schema::requests::table)
@@ -109,7 +117,7 @@ pub fn request(
                 &log_ctx,
                 LogTargetType::Request,
                 LogOperationType::Created,
-                None,
+                comment,
             )
         })
         .map_err(Error::from)?;
@@ -156,7 +164,9 @@ pub fn cancel(connection: &PgConnection, group_name: &str, user: &User) -> Resul
         None,
         user,
         log_comment_body("canceled"),
-    )
+    )?;
+    let group = internal::group::get_group(connection, group_name)?;
+    internal::approval::delete_for(connection, group.id, &user.user_uuid)
 }
 
 pub fn reject(
@@ -164,14 +174,107 @@ pub fn reject(
     group_name: &str,
     host: &User,
     member: &User,
+    reason: Option<&str>,
 ) -> Result<(), Error> {
-    delete(
-        connection,
-        group_name,
-        Some(*host),
-        member,
-        log_comment_body("rejected"),
-    )
+    let comment = match reason {
+        Some(reason) => add_to_comment_body("reason", reason, log_comment_body("rejected")),
+        None => log_comment_body("rejected"),
+    };
+    delete(connection, group_name, Some(*host), member, comment)?;
+    let group = internal::group::get_group(connection, group_name)?;
+    internal::approval::delete_for(connection, group.id, &member.user_uuid)
+}
+
+/// Adds the approval of `host` to the pending request of `member`.
+pub fn add_approval(
+    connection: &PgConnection,
+    group_name: &str,
+    host: &User,
+    member: &User,
+) -> Result<(), Error> {
+    use schema::requests as r;
+    let group = internal::group::get_group(connection, group_name)?;
+    r::table
+        .filter(r::user_uuid.eq(member.user_uuid))
+        .filter(r::group_id.eq(group.id))
+        .first::<Request>(connection)?;
+    internal::approval::add(connection, group.id, &member.user_uuid, &host.user_uuid)
+}
+
+/// Approves a request and returns whether the requester is a member right away. For groups with
+/// terms the request is turned into an invitation, so the terms still have to be accepted.
+pub fn approve(
+    connection: &PgConnection,
+    group_name: &str,
+    host: &User,
+    member: &User,
+    group_expiration: Option<i32>,
+) -> Result<bool, Error> {
+    use schema::requests as r;
+    let group = internal::group::get_group(connection, group_name)?;
+    r::table
+        .filter(r::user_uuid.eq(member.user_uuid))
+        .filter(r::group_id.eq(group.id))
+        .first::<Request>(connection)?;
+    let has_terms = internal::terms::get_terms(connection, group_name)?.is_some();
+    if has_terms {
+        internal::invitation::invite(
+            connection,
+            group_name,
+            *host,
+            *member,
+            None,
+            group_expiration,
+            None,
+        )?;
+    } else {
+        let role = internal::member::member_role(connection, group_name)?;
+        let membership = InsertMembership {
+            group_id: group.id,
+            user_uuid: member.user_uuid,
+            role_id: role.id,
+            expiration: internal::expiration::map_expiration(
+                group_expiration,
+                group.group_expiration,
+            ),
+            added_by: host.user_uuid,
+        };
+        let log_ctx = LogContext::with(group.id, host.user_uuid).with_user(member.user_uuid);
+        diesel::insert_into(schema::memberships::table)
+            .values(&membership)
+            .on_conflict((
+                schema::memberships::user_uuid,
+                schema::memberships::group_id,
+            ))
+            .do_update()
+            .set(&membership)
+            .execute(connection)
+            .map(|_| {
+                internal::log::db_log(
+                    connection,
+                    &log_ctx,
+                    LogTargetType::Membership,
+                    LogOperationType::Created,
+                    log_comment_body("approved request"),
+                );
+            })?;
+        internal::approval::delete_for(connection, group.id, &member.user_uuid)?;
+    }
+    let log_ctx = LogContext::with(group.id, host.user_uuid).with_user(member.user_uuid);
+    diesel::delete(r::table)
+        .filter(r::user_uuid.eq(member.user_uuid))
+        .filter(r::group_id.eq(group.id))
+        .execute(connection)
+        .map(|_| {
+            internal::log::db_log(
+                connection,
+                &log_ctx,
+                LogTargetType::Request,
+                LogOperationType::Updated,
+                log_comment_body("approved"),
+            );
+        })?;
+    Ok(!has_terms)
 }
 
 pub fn count(connection: &PgConnection, group_name: &str) -> Result<i64, Error> {
@@ -232,6 +335,7 @@ pub fn expire_before(connection: &PgConnection, before: NaiveDateTime) -> Result
             LogOperationType::Deleted,
             log_comment_body("expired"),
         );
+        internal::approval::delete_for(connection, request.group_id, &request.user_uuid)?;
     }
     Ok(())
 }
@@ -0,0 +1,172 @@
+use crate::db::error::DBError;
+use crate::db::internal;
+use crate::db::logs::LogContext;
+use crate::db::model::*;
+use crate::db::schema;
+use crate::db::types::*;
+use diesel::prelude::*;
+use failure::Error;
+use serde_json::json;
+use uuid::Uuid;
+
+const ROLE_ADMIN: &str = "admin";
+const ROLE_CURATOR: &str = "curator";
+const ROLE_MEMBER: &str = "member";
+
+pub fn get_roles(connection: &PgConnection, group_name: &str) -> Result<Vec<Role>, Error> {
+    let group = internal::group::get_group(connection, group_name)?;
+    schema::roles::table
+        .filter(schema::roles::group_id.eq(group.id))
+        .order_by((schema::roles::typ, schema::roles::position))
+        .get_results(connection)
+        .map_err(Into::into)
+}
+
+/// Sets the permissions of the role with the given type, creating the `Curator` role if the
+/// group does not have one yet.
+pub fn update_role_permissions(
+    host_uuid: &Uuid,
+    connection: &PgConnection,
+    group_name: &str,
+    typ: RoleType,
+    permissions: Vec<PermissionType>,
+) -> Result<Role, Error> {
+    let group = internal::group::get_group(connection, group_name)?;
+    let log_ctx = LogContext::with(group.id, *host_uuid);
+    let existing = schema::roles::table
+        .filter(schema::roles::group_id.eq(group.id))
+        .filter(schema::roles::typ.eq(typ))
+        .first::<Role>(connection)
+        .optional()?;
+    let (role, operation) = match existing {
+        Some(role) => (
+            diesel::update(schema::roles::table.filter(schema::roles::role_id.eq(role.id)))
+                .set(schema::roles::permissions.eq(&permissions))
+                .get_result::<Role>(connection)?,
+            LogOperationType::Updated,
+        ),
+        None if typ == RoleType::Curator => {
+            let curator = InsertRole {
+                group_id: group.id,
+                typ,
+                name: ROLE_CURATOR.to_owned(),
+                permissions: permissions.clone(),
+                position: 0,
+            };
+            (
+                diesel::insert_into(schema::roles::table)
+                    .values(curator)
+                    .get_result::<Role>(connection)?,
+                LogOperationType::Created,
+            )
+        }
+        None => return Err(diesel::result::Error::NotFound.into()),
+    };
+    internal::log::db_log(
+        connection,
+        &log_ctx,
+        LogTargetType::Role,
+        operation,
+        Some(json!({ "comment": "permissions", "role": role.name, "permissions": permissions })),
+    );
+    Ok(role)
+}
+
+pub fn get_custom_role(
+    connection: &PgConnection,
+    group_id: i32,
+    name: &str,
+) -> Result<Option<Role>, Error> {
+    schema::roles::table
+        .filter(schema::roles::group_id.eq(group_id))
+        .filter(schema::roles::typ.eq(RoleType::Custom))
+        .filter(schema::roles::name.eq(name))
+        .first(connection)
+        .optional()
+        .map_err(Into::into)
+}
+
+/// Creates or updates the `Custom` role with the given name.
+pub fn upsert_custom_role(
+    host_uuid: &Uuid,
+    connection: &PgConnection,
+    group_name: &str,
+    name: &str,
+    permissions: Vec<PermissionType>,
+    position: i32,
+) -> Result<Role, Error> {
+    let group = internal::group::get_group(connection, group_name)?;
+    let log_ctx = LogContext::with(group.id, *host_uuid);
+    let (role, operation) = match get_custom_role(connection, group.id, name)? {
+        Some(role) => (
+            diesel::update(schema::roles::table.filter(schema::roles::role_id.eq(role.id)))
+                .set((
+                    schema::roles::permissions.eq(&permissions),
+                    schema::roles::position.eq(position),
+                ))
+                .get_result::<Role>(connection)?,
+            LogOperationType::Updated,
+        ),
+        None => {
+            let custom = InsertRole {
+                group_id: group.id,
+                typ: RoleType::Custom,
+                name: name.to_owned(),
+                permissions: permissions.clone(),
+                position,
+            };
+            (
+                diesel::insert_into(schema::roles::table)
+                    .values(custom)
+                    .get_result::<Role>(connection)?,
+                LogOperationType::Created,
+            )
+        }
+    };
+    internal::log::db_log(
+        connection,
+        &log_ctx,
+        LogTargetType::Role,
+        operation,
+        Some(json!({ "comment": "custom role", "role": role.name, "permissions": permissions })),
+    );
+    Ok(role)
+}
+
+/// Deletes a `Custom` role. Members holding it fall back to the `Member` role so their
+/// membership (and what gets published to CIS) stays untouched.
+pub fn delete_custom_role(
+    host_uuid: &Uuid,
+    connection: &PgConnection,
+    group_name: &str,
+    name: &str,
+) -> Result<(), Error> {
+    let group = internal::group::get_group(connection, group_name)?;
+    let role = get_custom_role(connection, group.id, name)?.ok_or(DBError::NotApplicable)?;
+    let member_role = internal::member::member_role(connection, group_name)?;
+    let log_ctx = LogContext::with(group.id, *host_uuid);
+    let moved =
+        diesel::update(schema::memberships::table.filter(schema::memberships::role_id.eq(role.id)))
+            .set(schema::memberships::role_id.eq(member_role.id))
+            .execute(connection)?;
+    diesel::delete(schema::roles::table.filter(schema::roles::role_id.eq(role.id)))
+        .execute(connection)?;
+    internal::log::db_log(
+        connection,
+        &log_ctx,
+        LogTargetType::Role,
+        LogOperationType::Deleted,
+        Some(json!({ "comment": "custom role", "role": role.name, "moved": moved })),
+    );
+    Ok(())
+}
+
+/// Whether `name` can be used for a `Custom` role.
+pub fn valid_custom_role_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.trim() == name
+        && name.len() <= 64
+        && ![ROLE_ADMIN, ROLE_CURATOR, ROLE_MEMBER]
+            .iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(name))
+}
@@ -0,0 +1,252 @@
+use crate::db::error::DBError;
+use crate::db::internal;
+use crate::db::logs::add_to_comment_body;
+use crate::db::logs::log_comment_body;
+use crate::db::logs::LogContext;
+use crate::db::model::*;
+use crate::db::schema;
+use crate::db::types::*;
+use diesel::prelude::*;
+use failure::Error;
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+pub fn rules_for_group(connection: &PgConnection, group_id: i32) -> Result<Vec<Rule>, Error> {
+    schema::group_rules::table
+        .filter(schema::group_rules::group_id.eq(group_id))
+        .inner_join(schema::rules::table)
+        .select(schema::rules::all_columns)
+        .order_by(schema::rules::rule_id)
+        .get_results(connection)
+        .map_err(Into::into)
+}
+
+/// The group and all groups whose `Group` rules depend on it, directly or through other groups.
+/// Changing the rules of the group may change who these groups admit.
+pub fn dependent_group_ids(connection: &PgConnection, group: &Group) -> Result<Vec<i32>, Error> {
+    use schema::group_rules as gr;
+    use schema::groups as g;
+    use schema::rules as r;
+    let mut seen = BTreeSet::new();
+    seen.insert(group.id);
+    let mut next = vec![group.name.clone()];
+    while !next.is_empty() {
+        let found: Vec<(i32, String)> = gr::table
+            .inner_join(r::table)
+            .inner_join(g::table)
+            .filter(r::typ.eq(RuleType::Group))
+            .filter(r::payload.eq_any(&next))
+            .select((g::group_id, g::name))
+            .get_results(connection)?;
+        next = found
+            .into_iter()
+            .filter(|(id, _)| seen.insert(*id))
+            .map(|(_, name)| name)
+            .collect();
+    }
+    Ok(seen.into_iter().collect())
+}
+
+pub fn get_rules(connection: &PgConnection, group_name: &str) -> Result<Vec<Rule>, Error> {
+    let group = internal::group::get_group(connection, group_name)?;
+    rules_for_group(connection, group.id)
+}
+
+/// Evaluate the admission rules stored for a group for a user with the given trust, see
+/// `admitted_by_rules`.
+pub fn admission_rules_met(
+    connection: &PgConnection,
+    group: &Group,
+    trust: TrustType,
+    user_uuid: &Uuid,
+    visiting: &mut BTreeSet<i32>,
+) -> Result<bool, Error> {
+    admitted_by_rules(connection, group, vec![(*user_uuid, trust)], visiting)
+        .map(|admitted| admitted.contains(user_uuid))
+}
+
+/// Evaluate the admission rules stored for a group for several users with their trust at once.
+/// `Group` rules count admitted members of included groups, `Custom` rules have no evaluator and
+/// never admit anyone. `visiting` holds the groups whose admission is already being evaluated, a
+/// rule referring back to one of them is not met.
+pub fn admitted_by_rules(
+    connection: &PgConnection,
+    group: &Group,
+    candidates: Vec<(Uuid, TrustType)>,
+    visiting: &mut BTreeSet<i32>,
+) -> Result<BTreeSet<Uuid>, Error> {
+    if !visiting.insert(group.id) {
+        return Ok(BTreeSet::new());
+    }
+    let mut admitted = candidates;
+    for rule in rules_for_group(connection, group.id)? {
+        if admitted.is_empty() {
+            break;
+        }
+        admitted = match rule.typ {
+            RuleType::Staff => admitted
+                .into_iter()
+                .filter(|(_, trust)| *trust >= TrustType::Staff)
+                .collect(),
+            RuleType::Nda => admitted
+                .into_iter()
+                .filter(|(_, trust)| *trust >= TrustType::Ndaed)
+                .collect(),
+            RuleType::Group => match rule.payload {
+                Some(ref group_name) => {
+                    let other = internal::group::get_group(connection, group_name)?;
+                    let user_uuids = admitted.iter().map(|(u, _)| *u).collect::<Vec<_>>();
+                    let members = internal::include::effective_members_among(
+                        connection,
+                        &other,
+                        &user_uuids,
+                        visiting,
+                    )?;
+                    admitted
+                        .into_iter()
+                        .filter(|(user_uuid, _)| members.contains(user_uuid))
+                        .collect()
+                }
+                None => vec![],
+            },
+            RuleType::Custom => vec![],
+        };
+    }
+    visiting.remove(&group.id);
+    Ok(admitted
+        .into_iter()
+        .map(|(user_uuid, _)| user_uuid)
+        .collect())
+}
+
+/// `Staff` and `Nda` rules are shared rows seeded by the initial migration. `Group` and `Custom`
+/// rules carry a payload and get a row per attachment.
+fn rule_for(
+    connection: &PgConnection,
+    typ: RuleType,
+    payload: Option<String>,
+) -> Result<Rule, Error> {
+    match typ {
+        RuleType::Staff | RuleType::Nda => schema::rules::table
+            .filter(schema::rules::typ.eq(typ))
+            .filter(schema::rules::payload.is_null())
+            .order_by(schema::rules::rule_id)
+            .first(connection)
+            .map_err(Into::into),
+        RuleType::Group | RuleType::Custom => {
+            let name = match typ {
+                RuleType::Group => format!("member of {}", payload.as_deref().unwrap_or_default()),
+                _ => String::from("custom"),
+            };
+            let rule = InsertRule { typ, name, payload };
+            diesel::insert_into(schema::rules::table)
+                .values(&rule)
+                .get_result(connection)
+                .map_err(Into::into)
+        }
+    }
+}
+
+pub fn add_rule(
+    host_uuid: &Uuid,
+    connection: &PgConnection,
+    group_name: &str,
+    typ: RuleType,
+    payload: Option<String>,
+) -> Result<Rule, Error> {
+    let group = internal::group::get_group(connection, group_name)?;
+    connection.transaction::<_, Error, _>(|| {
+        if let Some(existing) = attached_rule(connection, group.id, typ, payload.as_deref())? {
+            return Ok(existing);
+        }
+        let rule = rule_for(connection, typ, payload)?;
+        let group_rule = InsertGroupRule {
+            rule_id: rule.id,
+            group_id: group.id,
+        };
+        let log_ctx = LogContext::with(group.id, *host_uuid);
+        diesel::insert_into(schema::group_rules::table)
+            .values(&group_rule)
+            .on_conflict_do_nothing()
+            .execute(connection)
+            .map(|_| {
+                internal::log::db_log(
+                    connection,
+                    &log_ctx,
+                    LogTargetType::Group,
+                    LogOperationType::Updated,
+                    add_to_comment_body("rule", &rule.name, log_comment_body("rule added")),
+                )
+            })?;
+        Ok(rule)
+    })
+}
+
+/// Adding a rule a group already has is a no-op returning the attached rule.
+fn attached_rule(
+    connection: &PgConnection,
+    group_id: i32,
+    typ: RuleType,
+    payload: Option<&str>,
+) -> Result<Option<Rule>, Error> {
+    let query = schema::group_rules::table
+        .filter(schema::group_rules::group_id.eq(group_id))
+        .inner_join(schema::rules::table)
+        .filter(schema::rules::typ.eq(typ))
+        .select(schema::rules::all_columns)
+        .into_boxed();
+    let query = match payload {
+        Some(payload) => query.filter(schema::rules::payload.eq(payload)),
+        None => query.filter(schema::rules::payload.is_null()),
+    };
+    query.first(connection).optional().map_err(Into::into)
+}
+
+pub fn remove_rule(
+    host_uuid: &Uuid,
+    connection: &PgConnection,
+    group_name: &str,
+    rule_id: i32,
+) -> Result<(), Error> {
+    let group = internal::group::get_group(connection, group_name)?;
+    let rule = schema::rules::table
+        .filter(schema::rules::rule_id.eq(rule_id))
+        .first::<Rule>(connection)?;
+    let log_ctx = LogContext::with(group.id, *host_uuid);
+    let deleted = diesel::delete(schema::group_rules::table)
+        .filter(schema::group_rules::group_id.eq(group.id))
+        .filter(schema::group_rules::rule_id.eq(rule.id))
+        .execute(connection)?;
+    if deleted == 0 {
+        return Err(DBError::NotApplicable.into());
+    }
+    internal::log::db_log(
+        connection,
+        &log_ctx,
+        LogTargetType::Group,
+        LogOperationType::Updated,
+        add_to_comment_body("rule", &rule.name, log_comment_body("rule removed")),
+    );
+    if rule.payload.is_some() {
+        diesel::delete(schema::rules::table)
+            .filter(schema::rules::rule_id.eq(rule.id))
+            .execute(connection)?;
+    }
+    Ok(())
+}
+
+pub fn delete_rules(connection: &PgConnection, group_id: i32) -> Result<(), Error> {
+    let rule_ids = schema::group_rules::table
+        .filter(schema::group_rules::group_id.eq(group_id))
+        .select(schema::group_rules::rule_id)
+        .get_results::<i32>(connection)?;
+    diesel::delete(schema::group_rules::table)
+        .filter(schema::group_rules::group_id.eq(group_id))
+        .execute(connection)?;
+    diesel::delete(schema::rules::table)
+        .filter(schema::rules::rule_id.eq_any(rule_ids))
+        .filter(schema::rules::payload.is_not_null())
+        .execute(connection)
+        .map(|_| ())
+        .map_err(Into::into)
+}
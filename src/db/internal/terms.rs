@@ -1,10 +1,15 @@
 use crate::db::internal;
+use crate::db::logs::add_to_comment_body;
+use crate::db::logs::log_comment_body;
 use crate::db::logs::LogContext;
 use crate::db::model::*;
+use crate::db::operations::models::DisplayTermsVersion;
+use crate::db::operations::models::PendingTermsAcceptance;
 use crate::db::schema;
 use crate::db::types::LogOperationType;
 use crate::db::types::LogTargetType;
 
+use diesel::dsl;
 use diesel::prelude::*;
 use failure::Error;
 use uuid::Uuid;
@@ -18,6 +23,13 @@ pub fn get_terms(connection: &PgConnection, group_name: &str) -> Result<Option<S
         .map_err(Into::into)
 }
 
+pub fn current_terms(connection: &PgConnection, group: &Group) -> Result<Option<Terms>, Error> {
+    Terms::belonging_to(group)
+        .first(connection)
+        .optional()
+        .map_err(Into::into)
+}
+
 pub fn delete_terms(
     host_uuid: &Uuid,
     connection: &PgConnection,
@@ -40,16 +52,44 @@ pub fn delete_terms(
         .map_err(Into::into)
 }
 
+/// Sets the terms of a group. Changing the text creates a new version, previous versions and
+/// acceptances are kept.
 pub fn set_terms(
     host_uuid: &Uuid,
     connection: &PgConnection,
     group_name: &str,
     text: String,
+    require_reacceptance: Option<bool>,
 ) -> Result<(), Error> {
+    use schema::terms_versions as v;
     let group = internal::group::get_group(connection, group_name)?;
+    let current = current_terms(connection, &group)?;
+    let version = match current {
+        Some(ref terms) if terms.text == text => terms.version,
+        _ => {
+            let latest = v::table
+                .filter(v::group_id.eq(group.id))
+                .select(dsl::max(v::version))
+                .first::<Option<i32>>(connection)?;
+            let version = latest.unwrap_or_default() + 1;
+            diesel::insert_into(v::table)
+                .values(&InsertTermsVersion {
+                    group_id: group.id,
+                    version,
+                    text: text.clone(),
+                    added_by: *host_uuid,
+                })
+                .execute(connection)?;
+            version
+        }
+    };
     let terms = Terms {
         group_id: group.id,
         text,
+        version,
+        require_reacceptance: require_reacceptance
+            .or_else(|| current.map(|t| t.require_reacceptance))
+            .unwrap_or_default(),
     };
     let log_ctx = LogContext::with(group.id, *host_uuid);
     diesel::insert_into(schema::terms::table)
@@ -64,8 +104,112 @@ pub fn set_terms(
                 &log_ctx,
                 LogTargetType::Terms,
                 LogOperationType::Updated,
-                None,
+                add_to_comment_body("version", &version.to_string(), None),
             );
         })
         .map_err(Into::into)
 }
+
+pub fn terms_versions(
+    connection: &PgConnection,
+    group_name: &str,
+) -> Result<Vec<DisplayTermsVersion>, Error> {
+    use schema::groups as g;
+    use schema::terms_versions as v;
+    v::table
+        .inner_join(g::table)
+        .filter(g::name.eq(group_name))
+        .select((v::version, v::text, v::added_ts))
+        .order(v::version.desc())
+        .get_results(connection)
+        .map_err(Into::into)
+}
+
+/// Records that `user_uuid` accepted `version` of the group's terms.
+pub fn accept_terms(
+    connection: &PgConnection,
+    group_id: i32,
+    user_uuid: &Uuid,
+    version: i32,
+) -> Result<(), Error> {
+    let acceptance = InsertTermsAcceptance {
+        group_id,
+        user_uuid: *user_uuid,
+        version,
+    };
+    let log_ctx = LogContext::with(group_id, *user_uuid);
+    diesel::insert_into(schema::terms_acceptances::table)
+        .values(&acceptance)
+        .on_conflict_do_nothing()
+        .execute(connection)
+        .map(|rows| {
+            if rows > 0 {
+                internal::log::db_log(
+                    connection,
+                    &log_ctx,
+                    LogTargetType::Terms,
+                    LogOperationType::Created,
+                    add_to_comment_body(
+                        "version",
+                        &version.to_string(),
+                        log_comment_body("accepted"),
+                    ),
+                );
+            }
+        })
+        .map_err(Into::into)
+}
+
+pub fn has_accepted(
+    connection: &PgConnection,
+    group_id: i32,
+    user_uuid: &Uuid,
+    version: i32,
+) -> Result<bool, Error> {
+    use schema::terms_acceptances as a;
+    dsl::select(dsl::exists(
+        a::table
+            .filter(a::group_id.eq(group_id))
+            .filter(a::user_uuid.eq(user_uuid))
+            .filter(a::version.eq(version)),
+    ))
+    .get_result(connection)
+    .map_err(Into::into)
+}
+
+/// Members of `group` which have not accepted `version` of its terms.
+pub fn pending_acceptances(
+    connection: &PgConnection,
+    group: &Group,
+    version: i32,
+) -> Result<Vec<PendingTermsAcceptance>, Error> {
+    use schema::memberships as m;
+    use schema::profiles as p;
+    use schema::terms_acceptances as a;
+    m::table
+        .filter(m::group_id.eq(group.id))
+        .inner_join(p::table.on(p::user_uuid.eq(m::user_uuid)))
+        .left_outer_join(
+            a::table.on(a::group_id
+                .eq(m::group_id)
+                .and(a::user_uuid.eq(m::user_uuid))
+                .and(a::version.eq(version))),
+        )
+        .filter(a::version.nullable().is_null())
+        .select((m::user_uuid, p::username))
+        .order(p::username)
+        .get_results(connection)
+        .map_err(Into::into)
+}
+
+/// Removes all versions and acceptances, used when a group gets deleted for good.
+pub fn delete_terms_history(connection: &PgConnection, group_id: i32) -> Result<(), Error> {
+    diesel::delete(schema::terms_acceptances::table)
+        .filter(schema::terms_acceptances::group_id.eq(group_id))
+        .execute(connection)?;
+    diesel::delete(schema::terms_versions::table)
+        .filter(schema::terms_versions::group_id.eq(group_id))
+        .execute(connection)
+        .map(|_| ())
+        .map_err(Into::into)
+}
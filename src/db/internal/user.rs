@@ -11,6 +11,8 @@ use diesel::sql_types::Bool;
 use failure::Error;
 use log::error;
 use log::info;
+use serde_json::Value;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use uuid::Uuid;
 
@@ -28,6 +30,18 @@ pub fn user_trust(connection: &PgConnection, user_uuid: &Uuid) -> Result<TrustTy
         .map_err(Into::into)
 }
 
+/// Trust levels of the given users in a single query, users without a profile are left out.
+pub fn user_trusts(
+    connection: &PgConnection,
+    user_uuids: &[Uuid],
+) -> Result<Vec<(Uuid, TrustType)>, Error> {
+    schema::profiles::table
+        .filter(schema::profiles::user_uuid.eq_any(user_uuids))
+        .select((schema::profiles::user_uuid, schema::profiles::trust))
+        .get_results(connection)
+        .map_err(Into::into)
+}
+
 pub fn user_by_id(connection: &PgConnection, user_id: &str) -> Result<User, Error> {
     schema::user_ids::table
         .filter(schema::user_ids::user_id.eq(user_id))
@@ -99,6 +113,92 @@ pub fn user_profile_by_user_id(
         .map_err(|err| PacksError::ProfileNotFound(user_id.to_string(), err.to_string()).into())
 }
 
+/// Languages of a profile's `preferred_language` values, most preferred first. The keys come
+/// back sorted from JSONB (as from the CIS profile itself), so the order is taken from the
+/// position stored as value. Languages without a position go last.
+fn languages_by_preference(values: &Value) -> Vec<String> {
+    let mut languages = values
+        .as_object()
+        .map(|values| {
+            values
+                .iter()
+                .map(|(language, position)| {
+                    let position = position
+                        .as_str()
+                        .and_then(|p| p.trim().parse::<u32>().ok())
+                        .unwrap_or(u32::MAX);
+                    (position, language.clone())
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    languages.sort_by_key(|(position, _)| *position);
+    languages
+        .into_iter()
+        .map(|(_, language)| language)
+        .collect()
+}
+
+/// Preferred languages from the cached profiles of the users with the given emails.
+pub fn preferred_languages(
+    connection: &PgConnection,
+    emails: &[String],
+) -> Result<HashMap<String, Vec<String>>, Error> {
+    use schema::profiles as p;
+
+    p::table
+        .filter(p::email.eq_any(emails))
+        .select((p::email, p::profile))
+        .get_results::<(String, Value)>(connection)
+        .map(|profiles| {
+            profiles
+                .into_iter()
+                .map(|(email, profile)| {
+                    let languages =
+                        languages_by_preference(&profile["preferred_language"]["values"]);
+                    (email, languages)
+                })
+                .collect()
+        })
+        .map_err(Into::into)
+}
+
+/// Returns the user uuid of every email in `emails` which has a profile.
+pub fn user_uuids_by_email(
+    connection: &PgConnection,
+    emails: &[String],
+) -> Result<HashMap<String, Uuid>, Error> {
+    use schema::profiles as p;
+    p::table
+        .filter(p::email.eq_any(emails))
+        .select((p::email, p::user_uuid))
+        .get_results::<(String, Uuid)>(connection)
+        .map(|profiles| profiles.into_iter().collect())
+        .map_err(Into::into)
+}
+
+sql_function!(fn lower(x: diesel::sql_types::Text) -> diesel::sql_types::Text);
+
+/// Looks up a user by username or by email, the latter ignoring case like email invitations do.
+pub fn user_by_username_or_email(
+    connection: &PgConnection,
+    username_or_email: &str,
+) -> Result<Option<User>, Error> {
+    use schema::profiles as p;
+
+    p::table
+        .filter(
+            p::username
+                .eq(username_or_email)
+                .or(lower(p::email).eq(username_or_email.to_lowercase())),
+        )
+        .select(p::user_uuid)
+        .first(connection)
+        .optional()
+        .map(|user_uuid| user_uuid.map(|user_uuid| User { user_uuid }))
+        .map_err(Into::into)
+}
+
 pub fn delete_user(connection: &PgConnection, user: &User) -> Result<(), Error> {
     diesel::delete(schema::requests::table)
         .filter(schema::requests::user_uuid.eq(user.user_uuid))
@@ -109,6 +209,10 @@ pub fn delete_user(connection: &PgConnection, user: &User) -> Result<(), Error>
     diesel::delete(schema::memberships::table)
         .filter(schema::memberships::user_uuid.eq(user.user_uuid))
         .execute(connection)?;
+    internal::pending::delete_pending_for_user(connection, &user.user_uuid)?;
+    diesel::delete(schema::notification_preferences::table)
+        .filter(schema::notification_preferences::user_uuid.eq(user.user_uuid))
+        .execute(connection)?;
     diesel::delete(schema::users_staff::table)
         .filter(schema::users_staff::user_uuid.eq(user.user_uuid))
         .execute(connection)?;
@@ -213,6 +317,20 @@ pub fn update_user_cache(connection: &PgConnection, profile: &Profile) -> Result
         .do_update()
         .set(&public_profile)
         .execute(connection)?;
+
+    // converted in a transaction of its own so a broken email invitation can not keep the
+    // profile from being updated
+    let member = User {
+        user_uuid: user_profile.user_uuid,
+    };
+    if let Err(e) = connection.transaction(|| {
+        internal::invitation::convert_email_invitations(connection, &user_profile.email, &member)
+    }) {
+        error!(
+            "failed to convert email invitations for {}: {}",
+            user_profile.user_uuid, e
+        );
+    }
     Ok(())
 }
 
@@ -490,3 +608,18 @@ pub fn all_with_groups(connection: &PgConnection) -> Result<Vec<Uuid>, Error> {
         .get_results::<Uuid>(connection)
         .map_err(Into::into)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_languages_by_preference() {
+        let values = json!({ "de": "1", "en": "0" });
+        assert_eq!(languages_by_preference(&values), vec!["en", "de"]);
+        let values = json!({ "de": null, "en": "0" });
+        assert_eq!(languages_by_preference(&values), vec!["en", "de"]);
+        assert!(languages_by_preference(&Value::Null).is_empty());
+    }
+}
@@ -15,9 +15,13 @@ use diesel::r2d2::ConnectionManager;
 pub type Pool = r2d2::Pool<ConnectionManager<PgConnection>>;
 
 pub fn establish_connection(database_url: &str) -> Pool {
+    establish_connection_with_size(database_url, 25)
+}
+
+pub fn establish_connection_with_size(database_url: &str, size: u32) -> Pool {
     let manager = ConnectionManager::<PgConnection>::new(database_url);
     r2d2::Pool::builder()
-        .max_size(25)
+        .max_size(size)
         .build(manager)
         .expect("Failed to create pool.")
 }
@@ -17,6 +17,11 @@ pub struct Group {
     pub trust: TrustType,
     pub group_expiration: Option<i32>,
     pub created: NaiveDateTime,
+    pub inactive_since: Option<NaiveDateTime>,
+    pub archived_at: Option<NaiveDateTime>,
+    pub self_renewal_days: Option<i32>,
+    pub max_membership_days: Option<i32>,
+    pub required_approvals: Option<i32>,
 }
 
 #[derive(Identifiable, Associations, Queryable, PartialEq, Eq, Debug, Insertable, AsChangeset)]
@@ -26,15 +31,37 @@ pub struct Group {
 pub struct Terms {
     pub group_id: i32,
     pub text: String,
+    pub version: i32,
+    pub require_reacceptance: bool,
 }
 
-#[derive(Identifiable, Queryable, Associations, PartialEq, Eq, Debug)]
+#[derive(Queryable, Associations, PartialEq, Eq, Debug)]
+#[belongs_to(Group)]
+#[table_name = "terms_versions"]
+pub struct TermsVersion {
+    pub group_id: i32,
+    pub version: i32,
+    pub text: String,
+    pub added_by: Uuid,
+    pub added_ts: NaiveDateTime,
+}
+
+#[derive(Identifiable, Queryable, Associations, PartialEq, Eq, Debug, Serialize)]
 pub struct Role {
     pub id: i32,
     pub group_id: i32,
     pub typ: RoleType,
     pub name: String,
     pub permissions: Vec<PermissionType>,
+    pub position: i32,
+}
+
+#[derive(Identifiable, Queryable, PartialEq, Eq, Debug, Serialize)]
+pub struct Rule {
+    pub id: i32,
+    pub typ: RuleType,
+    pub name: String,
+    pub payload: Option<String>,
 }
 
 #[derive(Serialize, Queryable, Associations, PartialEq, Eq, Debug, Insertable, AsChangeset)]
@@ -47,6 +74,7 @@ pub struct Membership {
     pub expiration: Option<NaiveDateTime>,
     pub added_by: Uuid,
     pub added_ts: NaiveDateTime,
+    pub self_renewed_at: Option<NaiveDateTime>,
 }
 
 #[derive(
@@ -60,6 +88,58 @@ pub struct Invitation {
     pub invitation_expiration: Option<NaiveDateTime>,
     pub group_expiration: Option<i32>,
     pub added_by: Uuid,
+    pub starts_at: Option<NaiveDateTime>,
+    #[serde(skip_serializing)]
+    pub token_nonce: Option<Uuid>,
+}
+
+#[derive(Queryable, Associations, PartialEq, Eq, Debug)]
+#[belongs_to(Group)]
+pub struct PendingMembership {
+    pub user_uuid: Uuid,
+    pub group_id: i32,
+    pub group_expiration: Option<i32>,
+    pub starts_at: NaiveDateTime,
+    pub added_by: Uuid,
+    pub added_ts: NaiveDateTime,
+}
+
+#[derive(Queryable, Associations, PartialEq, Eq, Debug)]
+#[belongs_to(Group)]
+pub struct EmailInvitation {
+    pub email: String,
+    pub group_id: i32,
+    pub invitation_expiration: Option<NaiveDateTime>,
+    pub group_expiration: Option<i32>,
+    pub added_by: Uuid,
+    pub added_ts: NaiveDateTime,
+}
+
+#[derive(Queryable, Associations, PartialEq, Eq, Debug)]
+#[belongs_to(Group)]
+pub struct Approval {
+    pub group_id: i32,
+    pub user_uuid: Uuid,
+    pub approver_uuid: Uuid,
+    pub approved_ts: NaiveDateTime,
+}
+
+#[derive(Queryable, PartialEq, Eq, Debug, Serialize)]
+pub struct OutboxEmail {
+    pub id: i32,
+    pub to_address: Option<String>,
+    pub bcc: Option<Vec<String>>,
+    pub from_address: String,
+    pub subject: String,
+    pub body: String,
+    pub status: EmailStatusType,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt: NaiveDateTime,
+    pub created: NaiveDateTime,
+    pub sent_at: Option<NaiveDateTime>,
+    pub html_body: Option<String>,
+    pub template: Option<String>,
 }
 
 #[derive(Identifiable, Queryable, Associations, PartialEq, Eq, Debug, Insertable, AsChangeset)]
@@ -80,6 +160,7 @@ pub struct Request {
     pub user_uuid: Uuid,
     pub created: NaiveDateTime,
     pub request_expiration: Option<NaiveDateTime>,
+    pub justification: Option<String>,
 }
 
 #[derive(Queryable, Serialize)]
@@ -87,6 +168,7 @@ pub struct GroupsList {
     pub name: String,
     pub typ: GroupType,
     pub trust: TrustType,
+    pub path: String,
     pub member_count: i64,
 }
 
@@ -121,6 +203,98 @@ pub struct InsertRole {
     pub typ: RoleType,
     pub name: String,
     pub permissions: Vec<PermissionType>,
+    pub position: i32,
+}
+
+#[derive(Insertable)]
+#[table_name = "rules"]
+pub struct InsertRule {
+    pub typ: RuleType,
+    pub name: String,
+    pub payload: Option<String>,
+}
+
+#[derive(Insertable)]
+#[table_name = "group_rules"]
+pub struct InsertGroupRule {
+    pub rule_id: i32,
+    pub group_id: i32,
+}
+
+#[derive(Insertable, AsChangeset)]
+#[table_name = "pending_memberships"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct InsertPendingMembership {
+    pub user_uuid: Uuid,
+    pub group_id: i32,
+    pub group_expiration: Option<i32>,
+    pub starts_at: NaiveDateTime,
+    pub added_by: Uuid,
+}
+
+#[derive(Insertable, AsChangeset)]
+#[table_name = "email_invitations"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct InsertEmailInvitation {
+    pub email: String,
+    pub group_id: i32,
+    pub invitation_expiration: Option<NaiveDateTime>,
+    pub group_expiration: Option<i32>,
+    pub added_by: Uuid,
+}
+
+#[derive(Insertable, AsChangeset)]
+#[table_name = "notification_preferences"]
+pub struct InsertNotificationPreference {
+    pub user_uuid: Uuid,
+    pub category: NotificationCategoryType,
+    pub enabled: bool,
+    pub updated: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "email_outbox"]
+pub struct InsertOutboxEmail {
+    pub to_address: Option<String>,
+    pub bcc: Option<Vec<String>>,
+    pub from_address: String,
+    pub subject: String,
+    pub body: String,
+    pub html_body: Option<String>,
+    pub template: Option<String>,
+}
+
+#[derive(Insertable)]
+#[table_name = "terms_versions"]
+pub struct InsertTermsVersion {
+    pub group_id: i32,
+    pub version: i32,
+    pub text: String,
+    pub added_by: Uuid,
+}
+
+#[derive(Insertable)]
+#[table_name = "terms_acceptances"]
+pub struct InsertTermsAcceptance {
+    pub group_id: i32,
+    pub user_uuid: Uuid,
+    pub version: i32,
+}
+
+#[derive(Insertable)]
+#[table_name = "approvals"]
+pub struct InsertApproval {
+    pub group_id: i32,
+    pub user_uuid: Uuid,
+    pub approver_uuid: Uuid,
+}
+
+#[derive(Insertable)]
+#[table_name = "group_includes"]
+pub struct InsertGroupInclude {
+    pub parent_id: i32,
+    pub child_id: i32,
+    pub added_by: Uuid,
 }
 
 #[derive(Insertable)]
@@ -129,4 +303,5 @@ pub struct InsertRequest {
     pub group_id: i32,
     pub user_uuid: Uuid,
     pub request_expiration: Option<NaiveDateTime>,
+    pub justification: Option<String>,
 }
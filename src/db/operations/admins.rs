@@ -2,8 +2,7 @@ use crate::cis::operations::send_groups_to_cis;
 use crate::db::internal;
 use crate::db::Pool;
 use crate::error::PacksError;
-use crate::mail::manager::send_email;
-use crate::mail::manager::subscribe_nda;
+use crate::mail::manager::MailMan;
 use crate::mail::templates::Template;
 use crate::rules::engine::*;
 use crate::rules::RuleContext;
@@ -20,6 +19,7 @@ pub async fn add_admin(
     host: &User,
     user: &User,
     cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
 ) -> Result<(), Error> {
     CAN_ADD_CURATOR.run(&RuleContext::minimal_with_member_uuid(
         pool,
@@ -31,7 +31,7 @@ pub async fn add_admin(
     let connection = pool.get()?;
     let user_profile = internal::user::slim_user_profile_by_uuid(&connection, &user.user_uuid)?;
     if group_name == "nda" {
-        subscribe_nda(&user_profile.email)
+        mail_man.subscribe_nda(&user_profile.email)
     }
     internal::admin::add_admin(&connection, group_name, host, user)?;
     drop(connection);
@@ -45,8 +45,9 @@ pub fn demote(
     host: &User,
     user: &User,
     expiration: Option<i32>,
+    mail_man: &MailMan,
 ) -> Result<(), Error> {
-    HOST_IS_GROUP_ADMIN.run(&RuleContext::minimal(
+    REMOVE_CURATOR.run(&RuleContext::minimal(
         pool,
         scope_and_user,
         group_name,
@@ -63,7 +64,7 @@ pub fn demote(
         )
         .map(|_| ())?;
         let user = internal::user::slim_user_profile_by_uuid(&connection, &user.user_uuid)?;
-        send_email(user.email, &Template::DemoteCurator(group_name.to_owned()));
+        mail_man.send_email(user.email, &Template::DemoteCurator(group_name.to_owned()));
         Ok(())
     } else {
         Err(PacksError::LastAdmin.into())
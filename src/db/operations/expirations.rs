@@ -1,13 +1,12 @@
+use crate::capabilities::Providers;
 use crate::db::internal;
 use crate::db::logs::log_comment_body;
 use crate::db::model::Membership;
 use crate::db::operations::members::revoke_membership;
 use crate::db::operations::models::RemoveGroups;
-use crate::db::types::RoleType;
 use crate::db::Pool;
 use crate::error::PacksError;
-use crate::mail::manager::send_email;
-use crate::mail::manager::send_emails;
+use crate::mail::manager::MailMan;
 use crate::mail::templates::Template;
 use crate::user::User;
 use chrono::Duration;
@@ -26,6 +25,8 @@ async fn expire_membership(
     cis_client: Arc<impl AsyncCisClientTrait>,
     user: &User,
     memberships: Vec<Membership>,
+    mail_man: &MailMan,
+    providers: &Providers,
 ) -> Result<(), Error> {
     let connection = pool.get()?;
     let groups = internal::group::get_groups_by_ids(
@@ -45,6 +46,8 @@ async fn expire_membership(
         remove_groups,
         &host,
         cis_client,
+        mail_man,
+        providers,
         log_comment_body("expired"),
     )
     .await
@@ -53,6 +56,8 @@ async fn expire_membership(
 pub async fn expire_memberships(
     pool: &Pool,
     cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
 ) -> Result<(), Error> {
     let expires_before = Utc::now().naive_utc();
     let connection = pool.get()?;
@@ -75,14 +80,18 @@ pub async fn expire_memberships(
         let cis_client = Arc::clone(&cis_client);
         async move {
             let pool = pool.clone();
-            expire_membership(&pool, cis_client, &user, memberships).await
+            expire_membership(&pool, cis_client, &user, memberships, mail_man, providers).await
         }
     }))
     .map_ok(|_| ())
     .await
 }
 
-pub fn expiration_notification(pool: &Pool, first: bool) -> Result<usize, Error> {
+pub fn expiration_notification(
+    pool: &Pool,
+    first: bool,
+    mail_man: &MailMan,
+) -> Result<usize, Error> {
     let days = if first { 14 } else { 7 };
     let lower = Utc::now()
         .checked_add_signed(Duration::days(days))
@@ -118,37 +127,37 @@ pub fn expiration_notification(pool: &Pool, first: bool) -> Result<usize, Error>
         let host = internal::user::slim_user_profile_by_uuid(&connection, &membership.added_by)?;
         let host_valid =
             match internal::member::role_for(&connection, &host.user_uuid, &group.name)? {
-                Some(r) => r.typ != RoleType::Member,
+                Some(r) => r.typ.is_curator(),
                 None => false,
             } && !host.email.is_empty();
         let user = internal::user::slim_user_profile_by_uuid(&connection, &membership.user_uuid)?;
         if first {
             if host_valid {
-                send_email(
+                mail_man.send_email(
                     host.email,
                     &Template::FirstHostExpiration(group.name, user.username),
                 );
             } else {
                 let bcc = internal::member::get_curator_emails(&connection, group.id)?;
-                send_emails(
+                mail_man.send_emails(
                     bcc,
                     &Template::FirstHostExpiration(group.name, user.username),
                 );
             };
         } else {
             if host_valid {
-                send_email(
+                mail_man.send_email(
                     host.email,
                     &Template::SecondHostExpiration(group.name.clone(), user.username),
                 );
             } else {
                 let bcc = internal::member::get_curator_emails(&connection, group.id)?;
-                send_emails(
+                mail_man.send_emails(
                     bcc,
                     &Template::SecondHostExpiration(group.name.clone(), user.username),
                 );
             }
-            send_email(user.email, &Template::MemberExpiration(group.name));
+            mail_man.send_email(user.email, &Template::MemberExpiration(group.name));
         }
         count += 1;
     }
@@ -159,6 +168,7 @@ pub fn expire_invitations(pool: &Pool) -> Result<(), Error> {
     let connection = pool.get()?;
     let expires_before = Utc::now().naive_utc();
     internal::invitation::expire_before(&connection, expires_before)?;
+    internal::invitation::expire_email_invitations_before(&connection, expires_before)?;
     Ok(())
 }
 
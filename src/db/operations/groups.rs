@@ -1,8 +1,13 @@
+use crate::capabilities::sync_group_memberships;
+use crate::capabilities::MembershipChange;
+use crate::capabilities::Providers;
 use crate::cis::operations::send_groups_to_cis;
+use crate::cis::operations::send_groups_to_cis_for_all;
 use crate::db::internal;
 use crate::db::logs::LogContext;
 use crate::db::model::Group;
 use crate::db::operations;
+use crate::db::operations::models::DisplayApproval;
 use crate::db::operations::models::GroupUpdate;
 use crate::db::operations::models::GroupWithTermsFlag;
 use crate::db::operations::models::NewGroup;
@@ -11,17 +16,24 @@ use crate::db::operations::models::SortGroupsBy;
 use crate::db::types::TrustType;
 use crate::db::Pool;
 use crate::error::PacksError;
-use crate::mail::manager::send_emails;
+use crate::mail::manager::MailMan;
 use crate::mail::templates::Template;
 use crate::rules::engine::CREATE_GROUP;
+use crate::rules::engine::CREATE_SUBGROUP;
+use crate::rules::engine::DELETE_GROUP;
+use crate::rules::engine::HOST_IS_CURATOR;
 use crate::rules::engine::HOST_IS_GROUP_ADMIN;
 use crate::rules::engine::ONLY_ADMINS;
+use crate::rules::engine::UPDATE_GROUP;
 use crate::rules::RuleContext;
 use crate::user::User;
+use chrono::Duration;
+use chrono::Utc;
 use cis_client::AsyncCisClientTrait;
 use diesel::pg::PgConnection;
 use dino_park_gate::scope::ScopeAndUser;
 use failure::Error;
+use log::info;
 use std::sync::Arc;
 
 fn add_new_group_db(
@@ -51,6 +63,43 @@ pub async fn add_new_group(
         &new_group.name,
         &user.user_uuid,
     ))?;
+    if let Some(ref parent) = new_group.parent {
+        check_parent(pool, &connection, scope_and_user, parent, &user)?;
+    }
+    add_new_group_db(&connection, new_group, user).map_err(|_| PacksError::GroupNameExists)?;
+    drop(connection);
+    send_groups_to_cis(pool, cis_client, &user.user_uuid).await
+}
+
+fn check_parent(
+    pool: &Pool,
+    connection: &PgConnection,
+    scope_and_user: &ScopeAndUser,
+    parent: &str,
+    host: &User,
+) -> Result<(), Error> {
+    internal::group::get_group(connection, parent)?;
+    CREATE_SUBGROUP.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        parent,
+        &host.user_uuid,
+    ))?;
+    Ok(())
+}
+
+/// Curators of `parent` may create groups below it without being group creators.
+pub async fn add_new_subgroup(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    parent: &str,
+    mut new_group: NewGroup,
+    cis_client: Arc<impl AsyncCisClientTrait>,
+) -> Result<(), Error> {
+    let connection = pool.get()?;
+    let user = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    check_parent(pool, &connection, scope_and_user, parent, &user)?;
+    new_group.parent = Some(parent.to_owned());
     add_new_group_db(&connection, new_group, user).map_err(|_| PacksError::GroupNameExists)?;
     drop(connection);
     send_groups_to_cis(pool, cis_client, &user.user_uuid).await
@@ -61,10 +110,12 @@ pub async fn delete_group(
     scope_and_user: &ScopeAndUser,
     group_name: &str,
     cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
 ) -> Result<(), Error> {
     let connection = pool.get()?;
     let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
-    HOST_IS_GROUP_ADMIN.run(&RuleContext::minimal(
+    DELETE_GROUP.run(&RuleContext::minimal(
         pool,
         scope_and_user,
         group_name,
@@ -79,17 +130,33 @@ pub async fn delete_group(
         group_name,
         &members,
         Arc::clone(&cis_client),
+        mail_man,
+        providers,
+    )
+    .await?;
+    operations::members::remove(
+        pool,
+        scope_and_user,
+        group_name,
+        &host,
+        &host,
+        Arc::clone(&cis_client),
+        mail_man,
+        providers,
     )
     .await?;
-    operations::members::remove(pool, scope_and_user, group_name, &host, &host, cis_client).await?;
     let connection = pool.get()?;
+    let group = internal::group::get_group(&connection, group_name)?;
+    // members of included groups lose this group as well
+    let included_members = internal::include::reachable_member_uuids(&connection, group.id)?;
     internal::group::delete_group(&host.user_uuid, &connection, group_name)?;
     let host_profile = internal::user::slim_user_profile_by_uuid(&connection, &host.user_uuid)?;
-    send_emails(
+    drop(connection);
+    mail_man.send_emails(
         bcc,
         &Template::GroupDeleted(group_name.to_string(), host_profile.username),
     );
-    Ok(())
+    send_groups_to_cis_for_all(pool, cis_client, &included_members).await
 }
 
 pub async fn update_group_trust(
@@ -98,6 +165,8 @@ pub async fn update_group_trust(
     group_name: &str,
     trust: &TrustType,
     cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
 ) -> Result<(), Error> {
     let connection = pool.get()?;
     let to_delete =
@@ -109,6 +178,8 @@ pub async fn update_group_trust(
         group_name,
         &to_delete,
         cis_client,
+        mail_man,
+        providers,
     )
     .await?;
     let connection = pool.get()?;
@@ -125,14 +196,30 @@ pub fn update_group(
 ) -> Result<(), Error> {
     let connection = pool.get()?;
     let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
-    HOST_IS_GROUP_ADMIN.run(&RuleContext::minimal(
+    let ctx = RuleContext::minimal(pool, scope_and_user, &group_name, &host.user_uuid);
+    UPDATE_GROUP.run(&ctx)?;
+    if group_update.required_approvals.is_some() {
+        HOST_IS_GROUP_ADMIN.run(&ctx)?;
+    }
+    internal::group::update_group(&host.user_uuid, &connection, group_name, group_update)
+        .map(|_| ())
+}
+
+/// Lists the approvals collected for pending requests and invitations of a group.
+pub fn approvals(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+) -> Result<Vec<DisplayApproval>, Error> {
+    let connection = pool.get()?;
+    let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    HOST_IS_CURATOR.run(&RuleContext::minimal(
         pool,
         scope_and_user,
-        &group_name,
+        group_name,
         &host.user_uuid,
     ))?;
-    internal::group::update_group(&host.user_uuid, &connection, group_name, group_update)
-        .map(|_| ())
+    internal::approval::approvals(&connection, group_name)
 }
 
 pub fn get_group(pool: &Pool, group_name: &str) -> Result<Group, Error> {
@@ -151,12 +238,13 @@ pub fn get_group_with_terms_flag(
 pub fn list_groups(
     pool: &Pool,
     filter: Option<String>,
+    parent: Option<String>,
     sort_by: SortGroupsBy,
     limit: i64,
     offset: i64,
 ) -> Result<PaginatedGroupsLists, Error> {
     let connection = pool.get()?;
-    internal::group::list_groups(&connection, filter, sort_by, limit, offset)
+    internal::group::list_groups(&connection, filter, parent, sort_by, limit, offset)
 }
 
 pub fn list_inactive_groups(
@@ -194,6 +282,81 @@ pub fn delete_inactive_group(
     internal::group::delete_inactive_group(&connection, group_name)
 }
 
+/// Flags groups without curators and warns their remaining members that the group will be
+/// archived in `days` days.
+pub fn flag_unattended_groups(pool: &Pool, days: i64, mail_man: &MailMan) -> Result<usize, Error> {
+    let connection = pool.get()?;
+    let groups = internal::group::flag_unattended_groups(&connection, Utc::now().naive_utc())?;
+    info!("{} groups flagged as inactive", groups.len());
+    for group in &groups {
+        let bcc = internal::member::get_member_emails_by_group_name(&connection, &group.name)?;
+        if !bcc.is_empty() {
+            mail_man.send_emails(
+                bcc,
+                &Template::GroupArchivalWarning(group.name.clone(), days),
+            );
+        }
+    }
+    Ok(groups.len())
+}
+
+/// Archives groups which have been flagged as inactive for more than `days` days. Their members
+/// are removed from the capability providers, the memberships themselves are kept.
+pub async fn archive_unattended_groups(
+    pool: &Pool,
+    days: i64,
+    cis_client: Arc<impl AsyncCisClientTrait>,
+    providers: &Providers,
+) -> Result<usize, Error> {
+    let now = Utc::now().naive_utc();
+    let before = now - Duration::days(days);
+    let connection = pool.get()?;
+    let groups = internal::group::archive_groups_inactive_before(&connection, before, now)?;
+    info!("{} groups archived", groups.len());
+    let mut affected = Vec::new();
+    let mut members = Vec::new();
+    for group in &groups {
+        affected.extend(internal::include::reachable_member_uuids(
+            &connection,
+            group.id,
+        )?);
+        members.push(internal::member::member_uuids(&connection, group.id)?);
+    }
+    drop(connection);
+    for (group, members) in groups.iter().zip(members) {
+        sync_group_memberships(pool, providers, group, &members, MembershipChange::Removed).await;
+    }
+    affected.sort();
+    affected.dedup();
+    send_groups_to_cis_for_all(pool, cis_client, &affected).await?;
+    Ok(groups.len())
+}
+
+/// Reactivates an archived group. Only groups without curators get archived, so this is left to
+/// admins. The archival warning points members to the IAM team accordingly.
+pub async fn reactivate_group(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    cis_client: Arc<impl AsyncCisClientTrait>,
+    providers: &Providers,
+) -> Result<(), Error> {
+    let connection = pool.get()?;
+    let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    ONLY_ADMINS.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+    ))?;
+    let group = internal::group::reactivate_group(&host.user_uuid, &connection, group_name)?;
+    let members = internal::member::member_uuids(&connection, group.id)?;
+    let affected = internal::include::reachable_member_uuids(&connection, group.id)?;
+    drop(connection);
+    sync_group_memberships(pool, providers, &group, &members, MembershipChange::Added).await;
+    send_groups_to_cis_for_all(pool, cis_client, &affected).await
+}
+
 pub fn reserve_group(
     pool: &Pool,
     scope_and_user: &ScopeAndUser,
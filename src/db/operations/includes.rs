@@ -0,0 +1,77 @@
+use crate::cis::operations::send_groups_to_cis_for_all;
+use crate::db::internal;
+use crate::db::Pool;
+use crate::error::PacksError;
+use crate::rules::engine::HOST_IS_CURATOR;
+use crate::rules::engine::HOST_IS_GROUP_ADMIN;
+use crate::rules::RuleContext;
+use cis_client::AsyncCisClientTrait;
+use dino_park_gate::scope::ScopeAndUser;
+use failure::Error;
+use std::sync::Arc;
+
+pub fn get_included_groups(pool: &Pool, group_name: &str) -> Result<Vec<String>, Error> {
+    let connection = pool.get()?;
+    internal::include::included_group_names(&connection, group_name)
+}
+
+pub async fn add_include(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    included_group_name: &str,
+    cis_client: Arc<impl AsyncCisClientTrait>,
+) -> Result<(), Error> {
+    let connection = pool.get()?;
+    let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    HOST_IS_GROUP_ADMIN.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+    ))?;
+    HOST_IS_CURATOR.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        included_group_name,
+        &host.user_uuid,
+    ))?;
+    let parent = internal::group::get_group(&connection, group_name)?;
+    let child = internal::group::get_group(&connection, included_group_name)?;
+    if parent.id == child.id
+        || internal::include::descendant_ids(&connection, child.id)?.contains(&parent.id)
+    {
+        return Err(PacksError::GroupIncludeCycle.into());
+    }
+    // members of the included group must satisfy the trust level of the including group
+    if child.trust < parent.trust {
+        return Err(PacksError::IncludedGroupTrustTooLow.into());
+    }
+    internal::include::add_include(&host.user_uuid, &connection, &parent, &child)?;
+    let affected = internal::include::reachable_member_uuids(&connection, child.id)?;
+    drop(connection);
+    send_groups_to_cis_for_all(pool, cis_client, &affected).await
+}
+
+pub async fn remove_include(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    included_group_name: &str,
+    cis_client: Arc<impl AsyncCisClientTrait>,
+) -> Result<(), Error> {
+    let connection = pool.get()?;
+    let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    HOST_IS_GROUP_ADMIN.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+    ))?;
+    let parent = internal::group::get_group(&connection, group_name)?;
+    let child = internal::group::get_group(&connection, included_group_name)?;
+    internal::include::remove_include(&host.user_uuid, &connection, &parent, &child)?;
+    let affected = internal::include::reachable_member_uuids(&connection, child.id)?;
+    drop(connection);
+    send_groups_to_cis_for_all(pool, cis_client, &affected).await
+}
@@ -1,17 +1,24 @@
+use crate::capabilities::sync_membership;
+use crate::capabilities::MembershipChange;
+use crate::capabilities::Providers;
 use crate::cis::operations::send_groups_to_cis;
 use crate::db::internal;
 use crate::db::internal::invitation::*;
 use crate::db::logs::log_comment_body;
 use crate::db::operations::models::*;
 use crate::db::Pool;
-use crate::mail::manager::send_email;
-use crate::mail::manager::subscribe_nda;
+use crate::error::PacksError;
+use crate::mail::manager::MailMan;
 use crate::mail::templates::Template;
 use crate::rules::engine::*;
+use crate::rules::error::RuleError;
 use crate::rules::RuleContext;
+use crate::tokens::InvitationToken;
+use crate::tokens::TokenSecret;
 use crate::user::User;
 use chrono::NaiveDateTime;
 use cis_client::AsyncCisClientTrait;
+use diesel::pg::PgConnection;
 use dino_park_gate::scope::ScopeAndUser;
 use dino_park_trust::Trust;
 use failure::Error;
@@ -28,6 +35,7 @@ pub fn delete_invitation(
     group_name: &str,
     host: User,
     member: User,
+    mail_man: &MailMan,
 ) -> Result<(), Error> {
     DELETE_INVITATION.run(&RuleContext::minimal(
         pool,
@@ -38,7 +46,7 @@ pub fn delete_invitation(
     let connection = pool.get()?;
     delete(&connection, group_name, host, member, None)?;
     let p = internal::user::slim_user_profile_by_uuid(&connection, &member.user_uuid)?;
-    send_email(p.email, &Template::DeleteInvitation(group_name.to_owned()));
+    mail_man.send_email(p.email, &Template::DeleteInvitation(group_name.to_owned()));
     Ok(())
 }
 
@@ -52,6 +60,26 @@ pub fn reject_invitation(
     delete(&connection, group_name, User::default(), user, None)
 }
 
+/// Adds the approval of `host` to a pending invitation. Groups with `required_approvals` only
+/// let the invitation be accepted once enough hosts who may invite approved it.
+pub fn approve_invitation(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    host: User,
+    member: User,
+) -> Result<(), Error> {
+    INVITE_MEMBER.run(&RuleContext::minimal_with_member_uuid(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+        &member.user_uuid,
+    ))?;
+    let connection = pool.get()?;
+    add_approval(&connection, group_name, &host, &member)
+}
+
 pub fn update_invitation(
     pool: &Pool,
     scope_and_user: &ScopeAndUser,
@@ -87,8 +115,9 @@ pub fn invite_member(
     member: User,
     invitation_expiration: Option<NaiveDateTime>,
     group_expiration: Option<i32>,
+    starts_at: Option<NaiveDateTime>,
+    mail_man: &MailMan,
 ) -> Result<(), Error> {
-    // TODO: check db rules
     INVITE_MEMBER.run(&RuleContext::minimal_with_member_uuid(
         pool,
         scope_and_user,
@@ -97,36 +126,242 @@ pub fn invite_member(
         &member.user_uuid,
     ))?;
     let connection = pool.get()?;
+    send_invitation(
+        &connection,
+        group_name,
+        host,
+        member,
+        invitation_expiration,
+        group_expiration,
+        starts_at,
+        mail_man,
+    )
+}
+
+/// Invites `member` and mails them the invitation, any pending request of theirs is dropped.
+/// Callers are responsible for checking `INVITE_MEMBER`.
+fn send_invitation(
+    connection: &PgConnection,
+    group_name: &str,
+    host: User,
+    member: User,
+    invitation_expiration: Option<NaiveDateTime>,
+    group_expiration: Option<i32>,
+    starts_at: Option<NaiveDateTime>,
+    mail_man: &MailMan,
+) -> Result<(), Error> {
     // delete the pending request if it exists
     internal::request::delete(
-        &connection,
+        connection,
         group_name,
         Some(host),
         &member,
         log_comment_body("invited"),
     )?;
-    invite(
-        &connection,
+    let invitation = invite(
+        connection,
         group_name,
         host,
         member,
         invitation_expiration,
         group_expiration,
+        starts_at,
     )?;
-    let p = internal::user::slim_user_profile_by_uuid(&connection, &member.user_uuid)?;
+    let token = invitation.token_nonce.map(|nonce| {
+        InvitationToken {
+            group_id: invitation.group_id,
+            user_uuid: invitation.user_uuid,
+            nonce,
+        }
+        .sign(&mail_man.token_secret)
+    });
+    let p = internal::user::slim_user_profile_by_uuid(connection, &member.user_uuid)?;
     if let Ok(Some(invitation_text)) =
-        internal::invitation::get_invitation_text(&connection, group_name)
+        internal::invitation::get_invitation_text(connection, group_name)
     {
-        send_email(
+        mail_man.send_email(
             p.email,
-            &Template::CustomInvitation(group_name.to_owned(), invitation_text.body),
+            &Template::CustomInvitation(group_name.to_owned(), invitation_text.body, token),
         );
     } else {
-        send_email(p.email, &Template::Invitation(group_name.to_owned()));
+        mail_man.send_email(p.email, &Template::Invitation(group_name.to_owned(), token));
     }
     Ok(())
 }
 
+/// Invites users by username or email. The host has to be allowed to invite at all, every row
+/// is then checked against `INVITE_MEMBER` and reported individually. With `dry_run` nobody gets
+/// invited.
+pub fn bulk_invite(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    host: User,
+    rows: Vec<BulkInvitationRow>,
+    invitation_expiration: Option<NaiveDateTime>,
+    dry_run: bool,
+    mail_man: &MailMan,
+) -> Result<BulkInvitationReport, Error> {
+    BULK_INVITE.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+    ))?;
+    let connection = pool.get()?;
+    let mut results = Vec::with_capacity(rows.len());
+    for row in rows {
+        let user = row.user.trim().to_owned();
+        let member = match internal::user::user_by_username_or_email(&connection, &user)? {
+            Some(member) => member,
+            None => {
+                results.push(BulkInvitationResult {
+                    user,
+                    user_uuid: None,
+                    status: BulkInvitationStatus::NotFound,
+                    error: None,
+                });
+                continue;
+            }
+        };
+        let outcome = INVITE_MEMBER
+            .run(&RuleContext::minimal_with_member_uuid(
+                pool,
+                scope_and_user,
+                group_name,
+                &host.user_uuid,
+                &member.user_uuid,
+            ))
+            .map_err(Error::from)
+            .and_then(|_| {
+                if dry_run {
+                    Ok(())
+                } else {
+                    send_invitation(
+                        &connection,
+                        group_name,
+                        host,
+                        member,
+                        invitation_expiration,
+                        row.group_expiration,
+                        None,
+                        mail_man,
+                    )
+                }
+            });
+        let (status, error) = match outcome {
+            Ok(_) => (BulkInvitationStatus::Invited, None),
+            Err(e) => match e.downcast_ref::<RuleError>() {
+                Some(RuleError::AlreadyMember) => (BulkInvitationStatus::AlreadyMember, None),
+                Some(_) => (BulkInvitationStatus::NotAllowed, Some(e.to_string())),
+                None => (BulkInvitationStatus::Failed, Some(e.to_string())),
+            },
+        };
+        results.push(BulkInvitationResult {
+            user,
+            user_uuid: Some(member.user_uuid),
+            status,
+            error,
+        });
+    }
+    Ok(BulkInvitationReport { dry_run, results })
+}
+
+/// Invites someone by email. Users with a profile get a regular invitation, everybody else an
+/// email invitation which is converted once their profile shows up.
+pub fn invite_email(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    host: User,
+    email: &str,
+    invitation_expiration: Option<NaiveDateTime>,
+    group_expiration: Option<i32>,
+    mail_man: &MailMan,
+) -> Result<(), Error> {
+    let email = email.trim();
+    if !email.contains('@') {
+        return Err(PacksError::InvalidEmail.into());
+    }
+    let connection = pool.get()?;
+    if let Some(member) = internal::user::user_by_username_or_email(&connection, email)? {
+        drop(connection);
+        return invite_member(
+            pool,
+            scope_and_user,
+            group_name,
+            host,
+            member,
+            invitation_expiration,
+            group_expiration,
+            None,
+            mail_man,
+        );
+    }
+    INVITE_EMAIL.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+    ))?;
+    internal::invitation::invite_email(
+        &connection,
+        group_name,
+        host,
+        email,
+        invitation_expiration,
+        group_expiration,
+    )?;
+    if let Ok(Some(invitation_text)) =
+        internal::invitation::get_invitation_text(&connection, group_name)
+    {
+        mail_man.send_email(
+            email.to_owned(),
+            &Template::CustomInvitation(group_name.to_owned(), invitation_text.body, None),
+        );
+    } else {
+        mail_man.send_email(
+            email.to_owned(),
+            &Template::Invitation(group_name.to_owned(), None),
+        );
+    }
+    Ok(())
+}
+
+pub fn pending_email_invitations(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    host: &User,
+) -> Result<Vec<DisplayEmailInvitation>, Error> {
+    HOST_IS_CURATOR.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+    ))?;
+    let connection = pool.get()?;
+    internal::invitation::email_invitations(&connection, group_name)
+        .map(|invitations| invitations.into_iter().map(Into::into).collect())
+}
+
+pub fn delete_email_invitation(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    host: User,
+    email: &str,
+) -> Result<(), Error> {
+    DELETE_INVITATION.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+    ))?;
+    let connection = pool.get()?;
+    internal::invitation::delete_email_invitation(&connection, group_name, host, email)
+}
+
 pub fn pending_invitations_count(
     pool: &Pool,
     scope_and_user: &ScopeAndUser,
@@ -182,29 +417,85 @@ pub fn pending_invitations_for_user(
     }
 }
 
+/// Accepts an invitation of the current user, groups with terms require `accepted_terms`.
 pub async fn accept_invitation(
     pool: &Pool,
     scope_and_user: &ScopeAndUser,
     group_name: &str,
     user: &User,
+    accepted_terms: bool,
     cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
 ) -> Result<(), Error> {
-    CURRENT_USER_CAN_JOIN.run(&RuleContext::minimal(
+    let ctx = RuleContext::minimal_with_member_uuid(
         pool,
         scope_and_user,
         group_name,
         &Uuid::default(),
-    ))?;
+        &user.user_uuid,
+    );
+    CURRENT_USER_CAN_JOIN.run(&ctx)?;
+    MEMBER_IS_APPROVED.run_strict(&ctx)?;
     let connection = pool.get()?;
+    let active = accept(&connection, group_name, user, accepted_terms)?;
+    if !active {
+        // published once the membership gets activated
+        return Ok(());
+    }
     let user_profile = internal::user::slim_user_profile_by_uuid(&connection, &user.user_uuid)?;
     if group_name == "nda" {
-        subscribe_nda(&user_profile.email)
+        mail_man.subscribe_nda(&user_profile.email)
     }
-    accept(&connection, group_name, user)?;
     drop(connection);
+    sync_membership(
+        pool,
+        providers,
+        group_name,
+        &user.user_uuid,
+        MembershipChange::Added,
+    )
+    .await;
     send_groups_to_cis(pool, cis_client, &user.user_uuid).await
 }
 
+/// Accepts an invitation via the signed token from the invitation link. The token has to match
+/// the group and the current user and the group's terms must have been accepted.
+pub async fn accept_invitation_token(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    user: &User,
+    token: &str,
+    token_secret: &TokenSecret,
+    accepted_terms: bool,
+    cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
+) -> Result<(), Error> {
+    let token = InvitationToken::verify(token, token_secret)?;
+    {
+        let connection = pool.get()?;
+        let group = internal::group::get_group(&connection, group_name)?;
+        if token.group_id != group.id || token.user_uuid != user.user_uuid {
+            return Err(PacksError::InvalidInvitationToken.into());
+        }
+        internal::invitation::invitation_by_token(&connection, &token)?
+            .ok_or(PacksError::InvalidInvitationToken)?;
+    }
+    accept_invitation(
+        pool,
+        scope_and_user,
+        group_name,
+        user,
+        accepted_terms,
+        cis_client,
+        mail_man,
+        providers,
+    )
+    .await
+}
+
 pub fn set_invitation_email(
     pool: &Pool,
     scope_and_user: &ScopeAndUser,
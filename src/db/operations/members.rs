@@ -1,6 +1,11 @@
+use crate::capabilities::sync_membership;
+use crate::capabilities::MembershipChange;
+use crate::capabilities::Providers;
 use crate::cis::operations::send_groups_to_cis;
+use crate::cis::operations::send_groups_to_cis_for_all;
 use crate::db::internal;
 use crate::db::logs::add_to_comment_body;
+use crate::db::model::Role;
 use crate::db::operations;
 use crate::db::operations::models::*;
 use crate::db::schema;
@@ -9,15 +14,17 @@ use crate::db::types::*;
 use crate::db::Pool;
 use crate::error;
 use crate::error::PacksError;
-use crate::mail::manager::send_email;
-use crate::mail::manager::send_emails;
-use crate::mail::manager::subscribe_nda;
-use crate::mail::manager::unsubscribe_nda;
+use crate::mail::manager::MailMan;
 use crate::mail::templates::Template;
 use crate::rules::engine::ADMIN_CAN_ADD_MEMBER;
+use crate::rules::engine::ASSIGN_ROLE;
+use crate::rules::engine::BULK_RENEW;
 use crate::rules::engine::ONLY_ADMINS;
 use crate::rules::engine::REMOVE_MEMBER;
 use crate::rules::engine::RENEW_MEMBER;
+use crate::rules::engine::SELF_RENEW;
+use crate::rules::error::RuleError;
+use crate::rules::functions::member_can_join_group;
 use crate::rules::is_nda_group;
 use crate::rules::RuleContext;
 use crate::user::User;
@@ -32,6 +39,7 @@ use failure::Error;
 use futures::future::try_join_all;
 use futures::TryFutureExt;
 use log::error;
+use log::warn;
 use serde_json::Value;
 use std::sync::Arc;
 use uuid::Uuid;
@@ -76,7 +84,7 @@ pub fn scoped_members_and_host(
     let curator = if options.privileged {
         let user = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
         internal::member::role_for(&connection, &user.user_uuid, group_name)?
-            .map(|r| r.typ == RoleType::Admin || r.typ == RoleType::Curator)
+            .map(|r| r.typ.is_curator())
             .unwrap_or_default()
     } else {
         false
@@ -103,14 +111,31 @@ pub fn scoped_members_and_host(
     }
 }
 
+/// Counts the members of the group and of all groups it includes.
 pub fn member_count(pool: &Pool, group_name: &str) -> Result<i64, Error> {
     let connection = pool.get()?;
-    let count = schema::memberships::table
+    let group = internal::group::get_group(&connection, group_name)?;
+    let members = internal::include::effective_member_uuids(&connection, &group)?;
+    Ok(members.len() as i64)
+}
+
+fn renewal_deadline(expires_before: Option<NaiveDateTime>) -> NaiveDateTime {
+    expires_before
+        .unwrap_or_else(|| (Utc::now() + chrono::Duration::days(DEFAULT_RENEWAL_DAYS)).naive_utc())
+}
+
+fn renewal_uuids(
+    connection: &PgConnection,
+    group_name: &str,
+    expires_before: NaiveDateTime,
+) -> Result<Vec<Uuid>, Error> {
+    schema::memberships::table
         .inner_join(groups::groups)
         .filter(groups::name.eq(group_name))
-        .select(count(schema::memberships::user_uuid))
-        .first(&connection)?;
-    Ok(count)
+        .filter(schema::memberships::expiration.le(expires_before))
+        .select(schema::memberships::user_uuid)
+        .get_results(connection)
+        .map_err(Into::into)
 }
 
 pub fn renewal_count(
@@ -118,8 +143,7 @@ pub fn renewal_count(
     group_name: &str,
     expires_before: Option<NaiveDateTime>,
 ) -> Result<i64, Error> {
-    let expires_before = expires_before
-        .unwrap_or_else(|| (Utc::now() + chrono::Duration::days(DEFAULT_RENEWAL_DAYS)).naive_utc());
+    let expires_before = renewal_deadline(expires_before);
     let connection = pool.get()?;
     let count = schema::memberships::table
         .inner_join(groups::groups)
@@ -151,6 +175,7 @@ pub async fn transfer(
     old_user: &User,
     new_user: &User,
     cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
 ) -> Result<(), Error> {
     let connection = pool.get()?;
     let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
@@ -167,8 +192,8 @@ pub async fn transfer(
             internal::user::slim_user_profile_by_uuid(&connection, &old_user.user_uuid)?;
         let new_user_profile =
             internal::user::slim_user_profile_by_uuid(&connection, &old_user.user_uuid)?;
-        unsubscribe_nda(old_user_profile.email);
-        subscribe_nda(new_user_profile.email);
+        mail_man.unsubscribe_nda(old_user_profile.email);
+        mail_man.subscribe_nda(new_user_profile.email);
     }
     drop(connection);
     send_groups_to_cis(pool, Arc::clone(&cis_client), &old_user.user_uuid).await?;
@@ -182,7 +207,10 @@ pub async fn add(
     host: &User,
     user: &User,
     expiration: Option<i32>,
+    starts_at: Option<NaiveDateTime>,
     cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
 ) -> Result<(), Error> {
     ADMIN_CAN_ADD_MEMBER.run(&RuleContext::minimal_with_member_uuid(
         &pool.clone(),
@@ -192,26 +220,120 @@ pub async fn add(
         &user.user_uuid,
     ))?;
     let connection = pool.get()?;
+    let group = internal::group::get_group(&connection, group_name)?;
     let expiration = if expiration.is_none() {
-        internal::group::get_group(&connection, group_name)?.group_expiration
+        group.group_expiration
     } else {
         expiration
     };
+    if let Some(starts_at) = starts_at.filter(|s| *s > Utc::now().naive_utc()) {
+        // published once the membership gets activated
+        return internal::pending::add_pending(
+            &connection,
+            &group,
+            host,
+            user,
+            expiration,
+            starts_at,
+        );
+    }
     internal::member::add_to_group(&connection, group_name, host, user, expiration)?;
     let user_profile = internal::user::slim_user_profile_by_uuid(&connection, &user.user_uuid)?;
     if group_name == "nda" {
-        subscribe_nda(&user_profile.email)
+        mail_man.subscribe_nda(&user_profile.email)
     }
     drop(connection);
+    sync_membership(
+        pool,
+        providers,
+        group_name,
+        &user.user_uuid,
+        MembershipChange::Added,
+    )
+    .await;
     send_groups_to_cis(pool, cis_client, &user.user_uuid).await
 }
 
+/// Activates all pending memberships which have started and publishes them. Failing activations
+/// are logged and skipped, they are retried on the next run.
+pub async fn activate_pending_memberships(
+    pool: &Pool,
+    cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
+) -> Result<usize, Error> {
+    let connection = pool.get()?;
+    let pending = internal::pending::pending_starting_before(&connection, Utc::now().naive_utc())?;
+    let mut activated = Vec::new();
+    for p in &pending {
+        let activation = connection.transaction::<_, Error, _>(|| {
+            let group = match internal::group::get_group_by_id(&connection, p.group_id)? {
+                Some(group) => group,
+                None => {
+                    internal::pending::discard(&connection, p)?;
+                    return Ok(None);
+                }
+            };
+            // trust and admission rules may have changed since the membership was scheduled
+            match member_can_join_group(&connection, &group, &p.user_uuid) {
+                Ok(()) => {}
+                Err(RuleError::DBError) => return Err(RuleError::DBError.into()),
+                Err(e) => {
+                    warn!(
+                        "dropping scheduled membership of {} in group {}: {}",
+                        p.user_uuid, group.name, e
+                    );
+                    internal::pending::discard(&connection, p)?;
+                    return Ok(None);
+                }
+            }
+            internal::pending::activate(&connection, p, &group).map(|_| Some(group))
+        });
+        let group = match activation {
+            Ok(Some(group)) => group,
+            Ok(None) => continue,
+            Err(e) => {
+                error!(
+                    "failed to activate membership of {} in group {}: {}",
+                    p.user_uuid, p.group_id, e
+                );
+                continue;
+            }
+        };
+        if group.name == "nda" {
+            match internal::user::slim_user_profile_by_uuid(&connection, &p.user_uuid) {
+                Ok(user_profile) => mail_man.subscribe_nda(&user_profile.email),
+                Err(e) => error!("failed to subscribe {} to nda: {}", p.user_uuid, e),
+            }
+        }
+        activated.push((group.name, p.user_uuid));
+    }
+    drop(connection);
+    for (group_name, user_uuid) in &activated {
+        sync_membership(
+            pool,
+            providers,
+            group_name,
+            user_uuid,
+            MembershipChange::Added,
+        )
+        .await;
+    }
+    let mut user_uuids: Vec<Uuid> = activated.iter().map(|(_, user_uuid)| *user_uuid).collect();
+    user_uuids.sort();
+    user_uuids.dedup();
+    send_groups_to_cis_for_all(pool, cis_client, &user_uuids).await?;
+    Ok(activated.len())
+}
+
 pub async fn remove_members_silent(
     pool: &Pool,
     scope_and_user: &ScopeAndUser,
     group_name: &str,
     members: &[User],
     cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
 ) -> Result<(), Error> {
     let connection = pool.get()?;
     let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
@@ -234,6 +356,8 @@ pub async fn remove_members_silent(
                 &host,
                 user,
                 Arc::clone(&cis_client),
+                mail_man,
+                providers,
             )
             .map_ok(move |k| {
                 log::debug!("removed {} for {}", &group_name, user_uuid);
@@ -258,11 +382,18 @@ pub async fn revoke_memberships_by_trust<'a>(
     host: &User,
     trust: TrustType,
     cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
     comment: Option<Value>,
 ) -> Result<(), Error> {
     let connection = pool.get()?;
 
     let comment = add_to_comment_body("reason", "trust revoked", comment);
+    internal::pending::delete_pending_above_trust(
+        &connection,
+        &remove_groups.user.user_uuid,
+        trust,
+    )?;
     for invited in
         internal::invitation::invited_groups_for_user(&connection, &remove_groups.user.user_uuid)?
             .iter()
@@ -288,7 +419,16 @@ pub async fn revoke_memberships_by_trust<'a>(
     remove_groups.force = true;
 
     drop(connection);
-    _revoke_membership(pool, remove_groups, host, cis_client, comment).await
+    _revoke_membership(
+        pool,
+        remove_groups,
+        host,
+        cis_client,
+        mail_man,
+        providers,
+        comment,
+    )
+    .await
 }
 
 pub async fn revoke_membership<'a>(
@@ -296,6 +436,8 @@ pub async fn revoke_membership<'a>(
     remove_groups: RemoveGroups<'a>,
     host: &User,
     cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
     comment: Option<Value>,
 ) -> Result<(), Error> {
     let connection = pool.get()?;
@@ -305,7 +447,7 @@ pub async fn revoke_membership<'a>(
     if remove_groups.group_names.contains(&"nda") {
         let user_profile =
             internal::user::user_profile_by_uuid(&connection, &remove_groups.user.user_uuid)?;
-        unsubscribe_nda(user_profile.email);
+        mail_man.unsubscribe_nda(user_profile.email);
     }
     if remove_groups
         .group_names
@@ -321,12 +463,23 @@ pub async fn revoke_membership<'a>(
             host,
             TrustType::Authenticated,
             cis_client,
+            mail_man,
+            providers,
             comment,
         )
         .await
     } else {
         drop(connection);
-        _revoke_membership(pool, remove_groups, host, cis_client, comment).await
+        _revoke_membership(
+            pool,
+            remove_groups,
+            host,
+            cis_client,
+            mail_man,
+            providers,
+            comment,
+        )
+        .await
     }
 }
 async fn _revoke_membership<'a>(
@@ -334,6 +487,8 @@ async fn _revoke_membership<'a>(
     remove_groups: RemoveGroups<'a>,
     host: &User,
     cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
     comment: Option<Value>,
 ) -> Result<(), Error> {
     let RemoveGroups {
@@ -349,6 +504,7 @@ async fn _revoke_membership<'a>(
     let connection = pool.get()?;
     let user_profile_slim =
         internal::user::slim_user_profile_by_uuid(&connection, &user.user_uuid)?;
+    let mut removed = Vec::with_capacity(group_names.len());
     for group_name in group_names {
         if let Err(e) = db_leave(
             &host.user_uuid,
@@ -366,15 +522,27 @@ async fn _revoke_membership<'a>(
                     e, &group_name, user.user_uuid
                 );
             }
+        } else {
+            removed.push(group_name);
         }
         if notify {
-            send_email(
+            mail_man.send_email(
                 user_profile_slim.email.clone(),
                 &Template::DeleteMember(group_name.to_string()),
             );
         }
     }
     drop(connection);
+    for group_name in removed {
+        sync_membership(
+            pool,
+            providers,
+            group_name,
+            &user.user_uuid,
+            MembershipChange::Removed,
+        )
+        .await;
+    }
     log::debug!("removing group from profile");
     send_groups_to_cis(pool, cis_client, &user.user_uuid).await?;
     log::debug!("removed group from profile");
@@ -388,6 +556,8 @@ pub async fn remove_silent(
     host: &User,
     user: &User,
     cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
 ) -> Result<(), Error> {
     REMOVE_MEMBER.run(&RuleContext::minimal(
         pool,
@@ -401,7 +571,16 @@ pub async fn remove_silent(
         force: true,
         notify: false,
     };
-    revoke_membership(pool, remove_groups, host, cis_client, None).await
+    revoke_membership(
+        pool,
+        remove_groups,
+        host,
+        cis_client,
+        mail_man,
+        providers,
+        None,
+    )
+    .await
 }
 
 pub async fn remove(
@@ -411,6 +590,8 @@ pub async fn remove(
     host: &User,
     user: &User,
     cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
 ) -> Result<(), Error> {
     REMOVE_MEMBER.run(&RuleContext::minimal(
         pool,
@@ -424,7 +605,16 @@ pub async fn remove(
         force: true,
         notify: true,
     };
-    revoke_membership(pool, remove_groups, host, cis_client, None).await
+    revoke_membership(
+        pool,
+        remove_groups,
+        host,
+        cis_client,
+        mail_man,
+        providers,
+        None,
+    )
+    .await
 }
 
 pub async fn leave(
@@ -433,6 +623,8 @@ pub async fn leave(
     group_name: &str,
     force: bool,
     cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
 ) -> Result<(), Error> {
     let connection = pool.get()?;
     let user = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
@@ -443,7 +635,16 @@ pub async fn leave(
         force,
         notify: true,
     };
-    revoke_membership(pool, remove_groups, &host, cis_client, None).await
+    revoke_membership(
+        pool,
+        remove_groups,
+        &host,
+        cis_client,
+        mail_man,
+        providers,
+        None,
+    )
+    .await
 }
 
 pub fn renew(
@@ -466,6 +667,139 @@ pub fn renew(
     internal::member::renew(&host.user_uuid, &connection, group_name, user, expiration)
 }
 
+/// Renews either the given members or all members expiring before `expires_before`. The host
+/// must be allowed to renew members at all, asking for both is an error. Members for which
+/// `RENEW_MEMBER` fails are skipped, all others are renewed in a single transaction.
+pub fn bulk_renew(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    host: &User,
+    user_uuids: Option<Vec<Uuid>>,
+    expires_before: Option<NaiveDateTime>,
+    expiration: Option<i32>,
+) -> Result<Vec<RenewalResult>, Error> {
+    BULK_RENEW.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+    ))?;
+    let connection = pool.get()?;
+    let user_uuids = match (user_uuids, expires_before) {
+        (Some(_), Some(_)) => return Err(PacksError::AmbiguousBulkRenewal.into()),
+        (Some(user_uuids), None) => user_uuids,
+        (None, expires_before) => {
+            renewal_uuids(&connection, group_name, renewal_deadline(expires_before))?
+        }
+    };
+    let group = internal::group::get_group(&connection, group_name)?;
+    let mut results = Vec::with_capacity(user_uuids.len());
+    for user_uuid in user_uuids {
+        // renewing one member must not keep the others from being renewed
+        let outcome = RENEW_MEMBER
+            .run(&RuleContext::minimal_with_member_uuid(
+                pool,
+                scope_and_user,
+                group_name,
+                &host.user_uuid,
+                &user_uuid,
+            ))
+            .map_err(Error::from)
+            .and_then(|_| {
+                connection.transaction::<_, Error, _>(|| {
+                    // admins pass the rules for anyone, so check for an actual membership
+                    internal::member::get_membership(&connection, group.id, &user_uuid)?
+                        .ok_or(PacksError::NotAMember)?;
+                    internal::member::renew(
+                        &host.user_uuid,
+                        &connection,
+                        group_name,
+                        &User { user_uuid },
+                        expiration,
+                    )
+                })
+            });
+        results.push(RenewalResult {
+            user_uuid,
+            renewed: outcome.is_ok(),
+            error: outcome.err().map(|e| e.to_string()),
+        });
+    }
+    Ok(results)
+}
+
+/// Lets members of groups with a self-renewal policy extend their own membership by the group's
+/// expiration once it expires within `self_renewal_days`, capped by `max_membership_days`. A
+/// member may only renew once within `self_renewal_days`.
+pub fn self_renew(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    mail_man: &MailMan,
+) -> Result<NaiveDateTime, Error> {
+    let connection = pool.get()?;
+    let user = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    let group = internal::group::get_group(&connection, group_name)?;
+    let membership = internal::member::get_membership(&connection, group.id, &user.user_uuid)?
+        .ok_or(PacksError::NotAMember)?;
+    SELF_RENEW.run(&RuleContext::minimal_with_member_uuid(
+        pool,
+        scope_and_user,
+        group_name,
+        &user.user_uuid,
+        &user.user_uuid,
+    ))?;
+    let (window, group_expiration) = match (group.self_renewal_days, group.group_expiration) {
+        (Some(window), Some(group_expiration)) => (window, group_expiration),
+        _ => return Err(PacksError::SelfRenewalDisabled.into()),
+    };
+    let now = Utc::now().naive_utc();
+    let window = chrono::Duration::days(i64::from(window));
+    let current = match membership.expiration {
+        Some(current) if current <= now + window => current,
+        _ => return Err(PacksError::SelfRenewalNotYetPossible.into()),
+    };
+    if let Some(self_renewed_at) = membership.self_renewed_at {
+        if self_renewed_at > now - window {
+            return Err(PacksError::AlreadySelfRenewed.into());
+        }
+    }
+    let mut expiration = now + chrono::Duration::days(i64::from(group_expiration));
+    if let Some(max) = group.max_membership_days {
+        expiration = expiration.min(membership.added_ts + chrono::Duration::days(i64::from(max)));
+    }
+    if expiration <= current {
+        return Err(PacksError::MaxMembershipLengthReached.into());
+    }
+    internal::member::self_renew(&connection, &group, &user, expiration, now)?;
+    let user_profile = internal::user::slim_user_profile_by_uuid(&connection, &user.user_uuid)?;
+    mail_man.send_email(
+        user_profile.email,
+        &Template::SelfRenewal(group.name, expiration.date().to_string()),
+    );
+    Ok(expiration)
+}
+
+pub fn assign_role(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    host: &User,
+    user: &User,
+    role_name: &str,
+) -> Result<Role, Error> {
+    ASSIGN_ROLE.run(&RuleContext::minimal_with_member_uuid(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+        &user.user_uuid,
+    ))?;
+    let connection = pool.get()?;
+    internal::member::assign_role(&host.user_uuid, &connection, group_name, user, role_name)
+}
+
 pub fn role_for_current(
     pool: &Pool,
     scope_and_user: &ScopeAndUser,
@@ -528,11 +862,11 @@ pub fn get_anonymous_member_emails(
     internal::member::get_anonymous_member_emails(&connection)
 }
 
-pub fn notify_anonymous_members(pool: &Pool) -> Result<(), Error> {
+pub fn notify_anonymous_members(pool: &Pool, mail_man: &MailMan) -> Result<(), Error> {
     let connection = pool.get()?;
     let emails = internal::member::get_anonymous_member_emails(&connection)?;
 
-    send_emails(emails, &Template::AnonymousMember);
+    mail_man.send_emails(emails, &Template::AnonymousMember);
 
     Ok(())
 }
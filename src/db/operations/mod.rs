@@ -1,11 +1,17 @@
 pub mod admins;
 pub mod expirations;
 pub mod groups;
+pub mod includes;
 pub mod invitations;
 pub mod logs;
 pub mod members;
 pub mod models;
+pub mod notifications;
+pub mod outbox;
+pub mod permissions;
 pub mod raws;
 pub mod requests;
+pub mod roles;
+pub mod rules;
 pub mod terms;
 pub mod users;
@@ -1,4 +1,5 @@
 use crate::db::logs::Log;
+use crate::db::model::EmailInvitation;
 use crate::db::model::Group;
 use crate::db::model::GroupsList;
 use crate::db::model::Invitation;
@@ -15,6 +16,7 @@ use chrono::NaiveDateTime;
 use dino_park_trust::Trust;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::HashMap;
 use uuid::Uuid;
 
 const DESCRIPTION_MAX_LEN: usize = 1024;
@@ -52,6 +54,35 @@ pub enum SortMembersBy {
     ExpirationDesc,
 }
 
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupAction {
+    Invite,
+    Remove,
+    Renew,
+    EditTerms,
+    AddCurator,
+    RemoveCurator,
+    AssignRole,
+    EditDescription,
+    DeleteGroup,
+    Join,
+    Request,
+}
+
+impl GroupAction {
+    /// Actions whose rules check the member acted on, so they can only be explained for one.
+    pub fn needs_member(self) -> bool {
+        matches!(
+            self,
+            GroupAction::Invite
+                | GroupAction::Renew
+                | GroupAction::AddCurator
+                | GroupAction::AssignRole
+        )
+    }
+}
+
 pub struct MembersQueryOptions {
     pub query: Option<String>,
     pub roles: Vec<RoleType>,
@@ -59,17 +90,25 @@ pub struct MembersQueryOptions {
     pub offset: Option<i64>,
     pub order: SortMembersBy,
     pub privileged: bool,
+    /// Also list the members of included groups.
+    pub effective: bool,
 }
 
 impl Default for MembersQueryOptions {
     fn default() -> Self {
         MembersQueryOptions {
             query: None,
-            roles: vec![RoleType::Admin, RoleType::Curator, RoleType::Member],
+            roles: vec![
+                RoleType::Admin,
+                RoleType::Curator,
+                RoleType::Custom,
+                RoleType::Member,
+            ],
             limit: 20,
             offset: None,
             order: SortMembersBy::RoleAsc,
             privileged: false,
+            effective: false,
         }
     }
 }
@@ -82,6 +121,12 @@ pub struct GroupUpdate {
     pub capabilities: Option<Vec<CapabilityType>>,
     #[allow(clippy::option_option)]
     pub group_expiration: Option<Option<i32>>,
+    #[allow(clippy::option_option)]
+    pub self_renewal_days: Option<Option<i32>>,
+    #[allow(clippy::option_option)]
+    pub max_membership_days: Option<Option<i32>>,
+    #[allow(clippy::option_option)]
+    pub required_approvals: Option<Option<i32>>,
 }
 
 impl GroupUpdate {
@@ -91,6 +136,11 @@ impl GroupUpdate {
             self.typ.as_ref().map(|_| "typ"),
             self.capabilities.as_ref().map(|_| "capabilities"),
             self.group_expiration.as_ref().map(|_| "expiration"),
+            self.self_renewal_days.as_ref().map(|_| "self renewal"),
+            self.max_membership_days.as_ref().map(|_| "max membership"),
+            self.required_approvals
+                .as_ref()
+                .map(|_| "required approvals"),
         ]
         .iter()
         .filter_map(|s| *s)
@@ -124,6 +174,8 @@ pub struct NewGroup {
     pub trust: TrustType,
     #[serde(default)]
     pub group_expiration: Option<i32>,
+    #[serde(default)]
+    pub parent: Option<String>,
 }
 
 impl NewGroup {
@@ -131,18 +183,76 @@ impl NewGroup {
         if self.description.len() > DESCRIPTION_MAX_LEN || self.trust < TrustType::Authenticated {
             return Err(PacksError::InvalidGroupData);
         }
-        if !valid_group_name(&self.name) {
+        if !valid_group_name(&self.name)
+            || !self.parent.as_deref().map(valid_group_name).unwrap_or(true)
+        {
             return Err(PacksError::InvalidGroupName);
         }
         Ok(self)
     }
 }
 
+#[derive(Deserialize)]
+pub struct NewRule {
+    #[serde(rename = "type")]
+    pub typ: RuleType,
+    #[serde(default)]
+    pub payload: Option<String>,
+}
+
+impl NewRule {
+    pub fn checked(self) -> Result<Self, PacksError> {
+        match (self.typ, &self.payload) {
+            (RuleType::Staff, None) | (RuleType::Nda, None) => Ok(self),
+            (RuleType::Group, Some(group_name)) if valid_group_name(group_name) => Ok(self),
+            _ => Err(PacksError::InvalidRule),
+        }
+    }
+}
+
 pub struct GroupWithTermsFlag {
     pub group: Group,
     pub terms: bool,
 }
 
+#[derive(Serialize)]
+pub struct RenewalResult {
+    pub user_uuid: Uuid,
+    pub renewed: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BulkInvitationRow {
+    pub user: String,
+    #[serde(default)]
+    pub group_expiration: Option<i32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkInvitationStatus {
+    Invited,
+    AlreadyMember,
+    NotFound,
+    NotAllowed,
+    Failed,
+}
+
+#[derive(Serialize)]
+pub struct BulkInvitationResult {
+    pub user: String,
+    pub user_uuid: Option<Uuid>,
+    pub status: BulkInvitationStatus,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BulkInvitationReport {
+    pub dry_run: bool,
+    pub results: Vec<BulkInvitationResult>,
+}
+
 #[derive(Serialize)]
 pub struct DisplayInvitation {
     pub user_uuid: Uuid,
@@ -155,17 +265,76 @@ pub struct DisplayInvitation {
     #[serde(serialize_with = "maybe_to_utc")]
     pub invitation_expiration: Option<NaiveDateTime>,
     pub group_expiration: Option<i32>,
+    #[serde(serialize_with = "maybe_to_utc")]
+    pub starts_at: Option<NaiveDateTime>,
     pub group_name: String,
     pub terms: bool,
     pub added_by: DisplayHost,
 }
 
+#[derive(Queryable, Serialize)]
+pub struct DisplayTermsVersion {
+    pub version: i32,
+    pub text: String,
+    #[serde(serialize_with = "to_utc")]
+    pub added_ts: NaiveDateTime,
+}
+
+#[derive(Queryable, Serialize)]
+pub struct PendingTermsAcceptance {
+    pub user_uuid: Uuid,
+    pub username: String,
+}
+
+#[derive(Queryable, Serialize)]
+pub struct DisplayApproval {
+    pub user_uuid: Uuid,
+    pub approver_uuid: Uuid,
+    #[serde(serialize_with = "to_utc")]
+    pub approved_ts: NaiveDateTime,
+}
+
+/// Outcome of a curator approving a request.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+    Joined,
+    Invited,
+    /// The group requires more approvals before the request is turned into a membership.
+    Pending,
+}
+
+#[derive(Serialize)]
+pub struct DisplayEmailInvitation {
+    pub email: String,
+    #[serde(serialize_with = "maybe_to_utc")]
+    pub invitation_expiration: Option<NaiveDateTime>,
+    pub group_expiration: Option<i32>,
+    pub added_by: Uuid,
+    #[serde(serialize_with = "to_utc")]
+    pub added_ts: NaiveDateTime,
+}
+
+impl From<EmailInvitation> for DisplayEmailInvitation {
+    fn from(e: EmailInvitation) -> Self {
+        DisplayEmailInvitation {
+            email: e.email,
+            invitation_expiration: e.invitation_expiration,
+            group_expiration: e.group_expiration,
+            added_by: e.added_by,
+            added_ts: e.added_ts,
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub struct DisplayInvitationForUser {
     pub user_uuid: Uuid,
     #[serde(serialize_with = "maybe_to_utc")]
     pub invitation_expiration: Option<NaiveDateTime>,
     pub group_expiration: Option<i32>,
+    #[serde(serialize_with = "maybe_to_utc")]
+    pub starts_at: Option<NaiveDateTime>,
     pub group_name: String,
     pub terms: bool,
     pub added_by: DisplayHost,
@@ -178,6 +347,7 @@ pub struct DisplayRequestForUser {
     pub created: NaiveDateTime,
     #[serde(serialize_with = "maybe_to_utc")]
     pub request_expiration: Option<NaiveDateTime>,
+    pub justification: Option<String>,
     pub group_name: String,
     pub terms: bool,
 }
@@ -195,6 +365,7 @@ pub struct DisplayRequest {
     pub created: NaiveDateTime,
     #[serde(serialize_with = "maybe_to_utc")]
     pub request_expiration: Option<NaiveDateTime>,
+    pub justification: Option<String>,
     pub group_name: String,
     pub terms: bool,
 }
@@ -204,6 +375,7 @@ pub struct InvitationAndHostForUser {
     pub user_uuid: Uuid,
     pub invitation_expiration: Option<NaiveDateTime>,
     pub group_expiration: Option<i32>,
+    pub starts_at: Option<NaiveDateTime>,
     pub group_name: String,
     pub terms: bool,
     pub host_uuid: Uuid,
@@ -219,6 +391,7 @@ impl From<InvitationAndHostForUser> for DisplayInvitationForUser {
             user_uuid: m.user_uuid,
             invitation_expiration: m.invitation_expiration,
             group_expiration: m.group_expiration,
+            starts_at: m.starts_at,
             group_name: m.group_name,
             terms: m.terms,
             added_by: DisplayHost {
@@ -243,6 +416,7 @@ pub struct InvitationAndHost {
     pub is_staff: bool,
     pub invitation_expiration: Option<NaiveDateTime>,
     pub group_expiration: Option<i32>,
+    pub starts_at: Option<NaiveDateTime>,
     pub group_name: String,
     pub terms: bool,
     pub host_uuid: Uuid,
@@ -264,6 +438,7 @@ impl From<InvitationAndHost> for DisplayInvitation {
             is_staff: m.is_staff,
             invitation_expiration: m.invitation_expiration,
             group_expiration: m.group_expiration,
+            starts_at: m.starts_at,
             group_name: m.group_name,
             terms: m.terms,
             added_by: DisplayHost {
@@ -311,6 +486,7 @@ pub struct DisplayMembershipAndHost {
     #[serde(serialize_with = "maybe_to_utc")]
     pub expiration: Option<NaiveDateTime>,
     pub role: RoleType,
+    pub role_name: String,
     pub added_by: Option<DisplayHost>,
 }
 
@@ -373,6 +549,7 @@ pub struct MembershipAndHost {
     pub since: NaiveDateTime,
     pub expiration: Option<NaiveDateTime>,
     pub role: RoleType,
+    pub role_name: String,
     pub host_uuid: Uuid,
     pub host_first_name: Option<String>,
     pub host_last_name: Option<String>,
@@ -468,6 +645,7 @@ impl From<MembershipAndHost> for DisplayMembershipAndHost {
             since: Some(m.since),
             expiration: m.expiration,
             role: m.role,
+            role_name: m.role_name,
             added_by: Some(DisplayHost {
                 user_uuid: m.host_uuid,
                 first_name: m.host_first_name,
@@ -496,6 +674,44 @@ pub struct InvitationEmail {
     pub body: Option<String>,
 }
 
+#[derive(Serialize)]
+pub struct NotificationPreferences {
+    pub invitations: bool,
+    pub expirations: bool,
+    pub requests: bool,
+}
+
+impl From<HashMap<NotificationCategoryType, bool>> for NotificationPreferences {
+    fn from(preferences: HashMap<NotificationCategoryType, bool>) -> Self {
+        let enabled = |category| preferences.get(&category).copied().unwrap_or(true);
+        NotificationPreferences {
+            invitations: enabled(NotificationCategoryType::Invitations),
+            expirations: enabled(NotificationCategoryType::Expirations),
+            requests: enabled(NotificationCategoryType::Requests),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NotificationPreferencesUpdate {
+    pub invitations: Option<bool>,
+    pub expirations: Option<bool>,
+    pub requests: Option<bool>,
+}
+
+impl NotificationPreferencesUpdate {
+    pub fn changes(&self) -> Vec<(NotificationCategoryType, bool)> {
+        vec![
+            (NotificationCategoryType::Invitations, self.invitations),
+            (NotificationCategoryType::Expirations, self.expirations),
+            (NotificationCategoryType::Requests, self.requests),
+        ]
+        .into_iter()
+        .filter_map(|(category, enabled)| enabled.map(|enabled| (category, enabled)))
+        .collect()
+    }
+}
+
 #[derive(Serialize)]
 pub struct RawUserData {
     pub user_profile: UserProfile,
@@ -516,16 +732,22 @@ mod test {
             typ: None,
             capabilities: Some(vec![]),
             group_expiration: Some(None),
+            self_renewal_days: None,
+            max_membership_days: Some(Some(365)),
+            required_approvals: None,
         };
         assert_eq!(
             group_update.log_comment(),
-            "description, capabilities, expiration"
+            "description, capabilities, expiration, max membership"
         );
         let group_update = GroupUpdate {
             description: None,
             typ: None,
             capabilities: None,
             group_expiration: None,
+            self_renewal_days: None,
+            max_membership_days: None,
+            required_approvals: None,
         };
         assert_eq!(group_update.log_comment(), "");
     }
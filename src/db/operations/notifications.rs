@@ -0,0 +1,61 @@
+use crate::db::internal;
+use crate::db::operations::models::NotificationPreferences;
+use crate::db::operations::models::NotificationPreferencesUpdate;
+use crate::db::types::NotificationCategoryType;
+use crate::db::Pool;
+use crate::tokens::TokenSecret;
+use crate::tokens::UnsubscribeToken;
+use crate::user::User;
+use dino_park_gate::scope::ScopeAndUser;
+use failure::Error;
+
+pub fn preferences(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+) -> Result<NotificationPreferences, Error> {
+    let connection = pool.get()?;
+    let user = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    internal::notification::preferences(&connection, &user).map(Into::into)
+}
+
+pub fn update_preferences(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    update: NotificationPreferencesUpdate,
+) -> Result<NotificationPreferences, Error> {
+    let connection = pool.get()?;
+    let user = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    connection.transaction::<_, Error, _>(|| {
+        for (category, enabled) in update.changes() {
+            internal::notification::set_preference(&connection, &user, category, enabled)?;
+        }
+        Ok(())
+    })?;
+    internal::notification::preferences(&connection, &user).map(Into::into)
+}
+
+/// The category of a signed unsubscribe token, without changing any preferences.
+pub fn unsubscribe_category(
+    token: &str,
+    token_secret: &TokenSecret,
+) -> Result<NotificationCategoryType, Error> {
+    UnsubscribeToken::verify(token, token_secret)
+        .map(|token| token.category)
+        .map_err(Into::into)
+}
+
+/// Disables the category of a signed unsubscribe token. No login is required, the token is
+/// proof enough that the user received the email.
+pub fn unsubscribe(
+    pool: &Pool,
+    token: &str,
+    token_secret: &TokenSecret,
+) -> Result<NotificationCategoryType, Error> {
+    let token = UnsubscribeToken::verify(token, token_secret)?;
+    let connection = pool.get()?;
+    let user = User {
+        user_uuid: token.user_uuid,
+    };
+    internal::notification::set_preference(&connection, &user, token.category, false)?;
+    Ok(token.category)
+}
@@ -0,0 +1,47 @@
+use crate::db::internal;
+use crate::db::model::OutboxEmail;
+use crate::db::Pool;
+use crate::error::PacksError;
+use crate::mail::manager::MailMan;
+use crate::rules::engine::ONLY_ADMINS;
+use crate::rules::RuleContext;
+use dino_park_gate::scope::ScopeAndUser;
+use failure::Error;
+
+pub fn failed_emails(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+) -> Result<Vec<OutboxEmail>, Error> {
+    let connection = pool.get()?;
+    let user = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    ONLY_ADMINS.run(&RuleContext::minimal(
+        &pool.clone(),
+        scope_and_user,
+        "",
+        &user.user_uuid,
+    ))?;
+    internal::outbox::failed(&connection)
+}
+
+pub fn resend_email(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    email_id: i32,
+    mail_man: &MailMan,
+) -> Result<(), Error> {
+    let connection = pool.get()?;
+    let user = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    ONLY_ADMINS.run(&RuleContext::minimal(
+        &pool.clone(),
+        scope_and_user,
+        "",
+        &user.user_uuid,
+    ))?;
+    match internal::outbox::reset(&connection, email_id)? {
+        Some(email) => {
+            mail_man.deliver(email.id);
+            Ok(())
+        }
+        None => Err(PacksError::NoFailedEmail.into()),
+    }
+}
@@ -0,0 +1,97 @@
+use crate::db::internal;
+use crate::db::operations::models::GroupAction;
+use crate::db::Pool;
+use crate::error::PacksError;
+use crate::rules::engine::*;
+use crate::rules::RuleContext;
+use dino_park_gate::scope::ScopeAndUser;
+use failure::Error;
+use uuid::Uuid;
+
+/// Actions shown as controls on the group page.
+const GROUP_ACTIONS: [GroupAction; 9] = [
+    GroupAction::Invite,
+    GroupAction::Remove,
+    GroupAction::Renew,
+    GroupAction::EditTerms,
+    GroupAction::AddCurator,
+    GroupAction::RemoveCurator,
+    GroupAction::AssignRole,
+    GroupAction::EditDescription,
+    GroupAction::DeleteGroup,
+];
+
+/// The engine guarding each action, matching the ones used in `db::operations`.
+pub fn engine_for(action: GroupAction) -> Engine<'static> {
+    match action {
+        GroupAction::Invite => INVITE_MEMBER,
+        GroupAction::Remove => REMOVE_MEMBER,
+        GroupAction::Renew => RENEW_MEMBER,
+        GroupAction::EditTerms => EDIT_TERMS,
+        GroupAction::AddCurator => CAN_ADD_CURATOR,
+        GroupAction::RemoveCurator => REMOVE_CURATOR,
+        GroupAction::AssignRole => ASSIGN_ROLE,
+        GroupAction::EditDescription => UPDATE_GROUP,
+        GroupAction::DeleteGroup => DELETE_GROUP,
+        GroupAction::Join => CURRENT_USER_CAN_JOIN,
+        GroupAction::Request => CURRENT_USER_CAN_REQUEST,
+    }
+}
+
+/// Explains whether the current user may take `action`. Only curators may ask about actions on
+/// other members, everyone else can only explain actions on themselves.
+pub fn explain(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    action: GroupAction,
+    member_uuid: Option<Uuid>,
+) -> Result<Explanation, Error> {
+    if member_uuid.is_none() && action.needs_member() {
+        return Err(PacksError::MemberRequired.into());
+    }
+    let connection = pool.get()?;
+    let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    drop(connection);
+    if member_uuid.map_or(false, |member_uuid| member_uuid != host.user_uuid) {
+        HOST_IS_CURATOR.run(&RuleContext::minimal(
+            pool,
+            scope_and_user,
+            group_name,
+            &host.user_uuid,
+        ))?;
+    }
+    let engine = engine_for(action);
+    let explanation = match member_uuid {
+        Some(ref member_uuid) => engine.explain(&RuleContext::minimal_with_member_uuid(
+            pool,
+            scope_and_user,
+            group_name,
+            &host.user_uuid,
+            member_uuid,
+        )),
+        None => engine.explain(&RuleContext::minimal(
+            pool,
+            scope_and_user,
+            group_name,
+            &host.user_uuid,
+        )),
+    };
+    Ok(explanation)
+}
+
+pub fn effective_permissions(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+) -> Result<Vec<GroupAction>, Error> {
+    let connection = pool.get()?;
+    let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    drop(connection);
+    let ctx = RuleContext::minimal(pool, scope_and_user, group_name, &host.user_uuid);
+    Ok(GROUP_ACTIONS
+        .iter()
+        .filter(|action| engine_for(**action).explain(&ctx).allowed_without_member())
+        .copied()
+        .collect())
+}
@@ -1,9 +1,12 @@
+use crate::capabilities::sync_membership;
+use crate::capabilities::MembershipChange;
+use crate::capabilities::Providers;
+use crate::cis::operations::send_groups_to_cis;
 use crate::db::internal;
 use crate::db::internal::request::*;
 use crate::db::operations::models::*;
 use crate::db::Pool;
-use crate::mail::manager::send_email;
-use crate::mail::manager::send_emails;
+use crate::mail::manager::MailMan;
 use crate::mail::templates::Template;
 use crate::rules::engine::*;
 use crate::rules::RuleContext;
@@ -11,15 +14,18 @@ use crate::user::User;
 use chrono::Duration;
 use chrono::NaiveDateTime;
 use chrono::Utc;
+use cis_client::AsyncCisClientTrait;
 use dino_park_gate::scope::ScopeAndUser;
 use dino_park_trust::Trust;
 use failure::Error;
+use std::sync::Arc;
 
 pub fn request_membership(
     pool: &Pool,
     scope_and_user: &ScopeAndUser,
     group_name: &str,
     request_expiration: Option<NaiveDateTime>,
+    justification: Option<String>,
 ) -> Result<(), Error> {
     let connection = pool.get()?;
     let user = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
@@ -29,7 +35,13 @@ pub fn request_membership(
         group_name,
         &user.user_uuid,
     ))?;
-    request(&connection, group_name, user, request_expiration)
+    request(
+        &connection,
+        group_name,
+        user,
+        request_expiration,
+        justification,
+    )
 }
 
 pub fn reject_request(
@@ -37,6 +49,8 @@ pub fn reject_request(
     scope_and_user: &ScopeAndUser,
     group_name: &str,
     user: &User,
+    reason: Option<String>,
+    mail_man: &MailMan,
 ) -> Result<(), Error> {
     let connection = pool.get()?;
     let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
@@ -46,12 +60,66 @@ pub fn reject_request(
         group_name,
         &host.user_uuid,
     ))?;
-    reject(&connection, group_name, &host, user)?;
+    reject(&connection, group_name, &host, user, reason.as_deref())?;
     let p = internal::user::slim_user_profile_by_uuid(&connection, &user.user_uuid)?;
-    send_email(p.email, &Template::RejectRequest(group_name.to_owned()));
+    mail_man.send_email(
+        p.email,
+        &Template::RejectRequest(group_name.to_owned(), reason),
+    );
     Ok(())
 }
 
+/// Approves a request and returns whether the requester joined right away, got invited because
+/// the group has terms or is still waiting for approvals of other hosts.
+pub async fn approve_request(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    user: &User,
+    group_expiration: Option<i32>,
+    cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
+) -> Result<ApprovalStatus, Error> {
+    let connection = pool.get()?;
+    let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    let ctx = RuleContext::minimal_with_member_uuid(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+        &user.user_uuid,
+    );
+    INVITE_MEMBER.run(&ctx)?;
+    add_approval(&connection, group_name, &host, user)?;
+    if MEMBER_IS_APPROVED.run_strict(&ctx).is_err() {
+        return Ok(ApprovalStatus::Pending);
+    }
+    let joined = approve(&connection, group_name, &host, user, group_expiration)?;
+    let p = internal::user::slim_user_profile_by_uuid(&connection, &user.user_uuid)?;
+    if joined && group_name == "nda" {
+        mail_man.subscribe_nda(&p.email)
+    }
+    mail_man.send_email(
+        p.email,
+        &Template::ApproveRequest(group_name.to_owned(), joined),
+    );
+    drop(connection);
+    if joined {
+        sync_membership(
+            pool,
+            providers,
+            group_name,
+            &user.user_uuid,
+            MembershipChange::Added,
+        )
+        .await;
+        send_groups_to_cis(pool, cis_client, &user.user_uuid).await?;
+        return Ok(ApprovalStatus::Joined);
+    }
+    Ok(ApprovalStatus::Invited)
+}
+
 pub fn cancel_request(
     pool: &Pool,
     scope_and_user: &ScopeAndUser,
@@ -109,7 +177,7 @@ pub fn pending_requests(
     }
 }
 
-pub fn pending_requests_notification(pool: &Pool) -> Result<(), Error> {
+pub fn pending_requests_notification(pool: &Pool, mail_man: &MailMan) -> Result<(), Error> {
     let lower = Utc::now()
         .checked_sub_signed(Duration::days(1))
         .unwrap()
@@ -132,7 +200,7 @@ pub fn pending_requests_notification(pool: &Pool) -> Result<(), Error> {
     let pending = internal::request::new_pending(&connection, lower, upper)?;
     for (group_id, npr) in pending {
         let bcc = internal::member::get_curator_emails(&connection, group_id)?;
-        send_emails(bcc, &Template::PendingRequest(npr.group_name, npr.count));
+        mail_man.send_emails(bcc, &Template::PendingRequest(npr.group_name, npr.count));
     }
     Ok(())
 }
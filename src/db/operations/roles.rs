@@ -0,0 +1,109 @@
+use crate::db::internal;
+use crate::db::model::Role;
+use crate::db::types::PermissionType;
+use crate::db::types::RoleType;
+use crate::db::Pool;
+use crate::error::PacksError;
+use crate::rules::engine::HOST_IS_CURATOR;
+use crate::rules::engine::HOST_IS_GROUP_ADMIN;
+use crate::rules::RuleContext;
+use dino_park_gate::scope::ScopeAndUser;
+use failure::Error;
+
+pub fn get_roles(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+) -> Result<Vec<Role>, Error> {
+    let connection = pool.get()?;
+    let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    HOST_IS_CURATOR.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+    ))?;
+    internal::role::get_roles(&connection, group_name)
+}
+
+pub fn update_role_permissions(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    typ: RoleType,
+    mut permissions: Vec<PermissionType>,
+) -> Result<Role, Error> {
+    let connection = pool.get()?;
+    let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    HOST_IS_GROUP_ADMIN.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+    ))?;
+    if typ == RoleType::Custom {
+        return Err(PacksError::InvalidRoleType.into());
+    }
+    permissions.sort_by_key(|p| *p as u8);
+    permissions.dedup();
+    // Admins must keep all their rights, otherwise a group could end up without anybody being
+    // able to manage it.
+    if typ == RoleType::Admin && permissions != PermissionType::all() {
+        return Err(PacksError::AdminRoleRequiresAllPermissions.into());
+    }
+    internal::role::update_role_permissions(
+        &host.user_uuid,
+        &connection,
+        group_name,
+        typ,
+        permissions,
+    )
+}
+
+pub fn upsert_custom_role(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    name: &str,
+    mut permissions: Vec<PermissionType>,
+    position: i32,
+) -> Result<Role, Error> {
+    let connection = pool.get()?;
+    let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    HOST_IS_GROUP_ADMIN.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+    ))?;
+    if !internal::role::valid_custom_role_name(name) {
+        return Err(PacksError::InvalidRoleName.into());
+    }
+    permissions.sort_by_key(|p| *p as u8);
+    permissions.dedup();
+    internal::role::upsert_custom_role(
+        &host.user_uuid,
+        &connection,
+        group_name,
+        name,
+        permissions,
+        position,
+    )
+}
+
+pub fn delete_custom_role(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    name: &str,
+) -> Result<(), Error> {
+    let connection = pool.get()?;
+    let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    HOST_IS_GROUP_ADMIN.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+    ))?;
+    internal::role::delete_custom_role(&host.user_uuid, &connection, group_name, name)
+}
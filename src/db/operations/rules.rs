@@ -0,0 +1,90 @@
+use crate::cis::operations::send_groups_to_cis_for_all;
+use crate::db::internal;
+use crate::db::model::Rule;
+use crate::db::operations::models::NewRule;
+use crate::db::Pool;
+use crate::error::PacksError;
+use crate::rules::engine::HOST_IS_CURATOR;
+use crate::rules::RuleContext;
+use cis_client::AsyncCisClientTrait;
+use diesel::PgConnection;
+use dino_park_gate::scope::ScopeAndUser;
+use failure::Error;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub fn get_rules(pool: &Pool, group_name: &str) -> Result<Vec<Rule>, Error> {
+    let connection = pool.get()?;
+    internal::rule::get_rules(&connection, group_name)
+}
+
+/// Users whose effective groups may change along with the rules of the group: the members of
+/// every group depending on it, including the groups they include.
+fn affected_by_rules(connection: &PgConnection, group_name: &str) -> Result<Vec<Uuid>, Error> {
+    let group = internal::group::get_group(connection, group_name)?;
+    let mut affected = Vec::new();
+    for group_id in internal::rule::dependent_group_ids(connection, &group)? {
+        affected.extend(internal::include::reachable_member_uuids(
+            connection, group_id,
+        )?);
+    }
+    affected.sort();
+    affected.dedup();
+    Ok(affected)
+}
+
+pub async fn add_rule(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    new_rule: NewRule,
+    cis_client: Arc<impl AsyncCisClientTrait>,
+) -> Result<Rule, Error> {
+    let connection = pool.get()?;
+    let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    HOST_IS_CURATOR.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+    ))?;
+    if let Some(ref required_group) = new_rule.payload {
+        if required_group == group_name
+            || internal::group::get_group(&connection, required_group).is_err()
+        {
+            return Err(PacksError::InvalidRule.into());
+        }
+    }
+    let rule = internal::rule::add_rule(
+        &host.user_uuid,
+        &connection,
+        group_name,
+        new_rule.typ,
+        new_rule.payload,
+    )?;
+    let affected = affected_by_rules(&connection, group_name)?;
+    drop(connection);
+    send_groups_to_cis_for_all(pool, cis_client, &affected).await?;
+    Ok(rule)
+}
+
+pub async fn remove_rule(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    rule_id: i32,
+    cis_client: Arc<impl AsyncCisClientTrait>,
+) -> Result<(), Error> {
+    let connection = pool.get()?;
+    let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    HOST_IS_CURATOR.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+    ))?;
+    internal::rule::remove_rule(&host.user_uuid, &connection, group_name, rule_id)?;
+    let affected = affected_by_rules(&connection, group_name)?;
+    drop(connection);
+    send_groups_to_cis_for_all(pool, cis_client, &affected).await
+}
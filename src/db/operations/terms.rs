@@ -1,5 +1,8 @@
 use crate::db::internal;
+use crate::db::operations::models::DisplayTermsVersion;
+use crate::db::operations::models::PendingTermsAcceptance;
 use crate::db::Pool;
+use crate::error::PacksError;
 use crate::rules::engine::*;
 use crate::rules::RuleContext;
 use dino_park_gate::scope::ScopeAndUser;
@@ -10,6 +13,7 @@ pub fn update_terms(
     scope_and_user: &ScopeAndUser,
     group_name: &str,
     text: String,
+    require_reacceptance: Option<bool>,
 ) -> Result<(), Error> {
     let connection = pool.get()?;
     let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
@@ -19,7 +23,13 @@ pub fn update_terms(
         group_name,
         &host.user_uuid,
     ))?;
-    internal::terms::set_terms(&host.user_uuid, &connection, group_name, text)
+    internal::terms::set_terms(
+        &host.user_uuid,
+        &connection,
+        group_name,
+        text,
+        require_reacceptance,
+    )
 }
 
 pub fn delete_terms(
@@ -42,3 +52,50 @@ pub fn get_terms(pool: &Pool, group_name: &str) -> Result<Option<String>, Error>
     let connection = pool.get()?;
     internal::terms::get_terms(&connection, group_name)
 }
+
+pub fn terms_versions(pool: &Pool, group_name: &str) -> Result<Vec<DisplayTermsVersion>, Error> {
+    let connection = pool.get()?;
+    internal::terms::terms_versions(&connection, group_name)
+}
+
+/// Lets a member accept the current terms. If a `version` is given it has to be the current one,
+/// so nobody accepts a text that changed while reading it.
+pub fn accept_terms(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+    version: Option<i32>,
+) -> Result<i32, Error> {
+    let connection = pool.get()?;
+    let user = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    let group = internal::group::get_group(&connection, group_name)?;
+    internal::member::get_membership(&connection, group.id, &user.user_uuid)?
+        .ok_or(PacksError::NotAMember)?;
+    let terms = internal::terms::current_terms(&connection, &group)?.ok_or(PacksError::NoTerms)?;
+    if version.map(|v| v != terms.version).unwrap_or_default() {
+        return Err(PacksError::OutdatedTermsVersion.into());
+    }
+    internal::terms::accept_terms(&connection, group.id, &user.user_uuid, terms.version)?;
+    Ok(terms.version)
+}
+
+/// Lists the members which have not accepted the current version of the terms.
+pub fn pending_acceptances(
+    pool: &Pool,
+    scope_and_user: &ScopeAndUser,
+    group_name: &str,
+) -> Result<Vec<PendingTermsAcceptance>, Error> {
+    let connection = pool.get()?;
+    let host = internal::user::user_by_id(&connection, &scope_and_user.user_id)?;
+    HOST_IS_CURATOR.run(&RuleContext::minimal(
+        pool,
+        scope_and_user,
+        group_name,
+        &host.user_uuid,
+    ))?;
+    let group = internal::group::get_group(&connection, group_name)?;
+    match internal::terms::current_terms(&connection, &group)? {
+        Some(terms) => internal::terms::pending_acceptances(&connection, &group, terms.version),
+        None => Ok(vec![]),
+    }
+}
@@ -1,3 +1,4 @@
+use crate::capabilities::Providers;
 use crate::cis::operations::send_groups_to_cis;
 use crate::db::internal;
 use crate::db::logs::log_comment_body;
@@ -10,6 +11,7 @@ use crate::db::users::UserForGroup;
 use crate::db::users::UserProfile;
 use crate::db::Pool;
 use crate::error::PacksError;
+use crate::mail::manager::MailMan;
 use crate::rules::engine::ONLY_ADMINS;
 use crate::rules::engine::SEARCH_USERS;
 use crate::rules::RuleContext;
@@ -119,6 +121,8 @@ pub async fn update_user_cache(
     pool: &Pool,
     profile: &Profile,
     cis_client: Arc<impl AsyncCisClientTrait>,
+    mail_man: &MailMan,
+    providers: &Providers,
 ) -> Result<(), Error> {
     let user_uuid = Uuid::parse_str(&profile.uuid.value.clone().ok_or(PacksError::NoUuid)?)?;
     if profile.active.value == Some(false) {
@@ -145,6 +149,8 @@ pub async fn update_user_cache(
                 &User::default(),
                 new_trust,
                 cis_client,
+                mail_man,
+                providers,
                 log_comment_body("trust revoked by CIS update"),
             )
             .await?;
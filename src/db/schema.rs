@@ -1,3 +1,63 @@
+table! {
+    use diesel::sql_types::*;
+    use crate::db::types::*;
+
+    approvals (group_id, user_uuid, approver_uuid) {
+        group_id -> Int4,
+        user_uuid -> Uuid,
+        approver_uuid -> Uuid,
+        approved_ts -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::db::types::*;
+
+    email_invitations (email, group_id) {
+        email -> Varchar,
+        group_id -> Int4,
+        invitation_expiration -> Nullable<Timestamp>,
+        group_expiration -> Nullable<Int4>,
+        added_by -> Uuid,
+        added_ts -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::db::types::*;
+
+    email_outbox (email_id) {
+        email_id -> Int4,
+        to_address -> Nullable<Varchar>,
+        bcc -> Nullable<Array<Varchar>>,
+        from_address -> Varchar,
+        subject -> Text,
+        body -> Text,
+        status -> Email_status_type,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+        next_attempt -> Timestamp,
+        created -> Timestamp,
+        sent_at -> Nullable<Timestamp>,
+        html_body -> Nullable<Text>,
+        template -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::db::types::*;
+
+    group_includes (parent_id, child_id) {
+        parent_id -> Int4,
+        child_id -> Int4,
+        added_by -> Uuid,
+        added_ts -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use crate::db::types::*;
@@ -23,6 +83,11 @@ table! {
         trust -> Trust_type,
         group_expiration -> Nullable<Int4>,
         created -> Timestamp,
+        inactive_since -> Nullable<Timestamp>,
+        archived_at -> Nullable<Timestamp>,
+        self_renewal_days -> Nullable<Int4>,
+        max_membership_days -> Nullable<Int4>,
+        required_approvals -> Nullable<Int4>,
     }
 }
 
@@ -36,6 +101,8 @@ table! {
         invitation_expiration -> Nullable<Timestamp>,
         group_expiration -> Nullable<Int4>,
         added_by -> Uuid,
+        starts_at -> Nullable<Timestamp>,
+        token_nonce -> Nullable<Uuid>,
     }
 }
 
@@ -87,6 +154,32 @@ table! {
         role_id -> Int4,
         expiration -> Nullable<Timestamp>,
         added_by -> Uuid,
+        added_ts -> Timestamp,        self_renewed_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::db::types::*;
+
+    notification_preferences (user_uuid, category) {
+        user_uuid -> Uuid,
+        category -> Notification_category_type,
+        enabled -> Bool,
+        updated -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::db::types::*;
+
+    pending_memberships (user_uuid, group_id) {
+        user_uuid -> Uuid,
+        group_id -> Int4,
+        group_expiration -> Nullable<Int4>,
+        starts_at -> Timestamp,
+        added_by -> Uuid,
         added_ts -> Timestamp,
     }
 }
@@ -114,6 +207,7 @@ table! {
         user_uuid -> Uuid,
         created -> Timestamp,
         request_expiration -> Nullable<Timestamp>,
+        justification -> Nullable<Text>,
     }
 }
 
@@ -127,6 +221,7 @@ table! {
         typ -> Role_type,
         name -> Varchar,
         permissions -> Array<Permission_type>,
+        position -> Int4,
     }
 }
 
@@ -149,6 +244,33 @@ table! {
     terms (group_id) {
         group_id -> Int4,
         text -> Text,
+        version -> Int4,
+        require_reacceptance -> Bool,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::db::types::*;
+
+    terms_acceptances (group_id, user_uuid, version) {
+        group_id -> Int4,
+        user_uuid -> Uuid,
+        version -> Int4,
+        accepted_ts -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::db::types::*;
+
+    terms_versions (group_id, version) {
+        group_id -> Int4,
+        version -> Int4,
+        text -> Text,
+        added_by -> Uuid,
+        added_ts -> Timestamp,
     }
 }
 
@@ -237,6 +359,8 @@ table! {
     }
 }
 
+joinable!(approvals -> groups (group_id));
+joinable!(email_invitations -> groups (group_id));
 joinable!(group_rules -> groups (group_id));
 joinable!(group_rules -> rules (rule_id));
 joinable!(invitations -> groups (group_id));
@@ -244,12 +368,19 @@ joinable!(invitationtexts -> groups (group_id));
 joinable!(logs -> groups (group_id));
 joinable!(memberships -> groups (group_id));
 joinable!(memberships -> roles (role_id));
+joinable!(pending_memberships -> groups (group_id));
 joinable!(requests -> groups (group_id));
 joinable!(roles -> groups (group_id));
 joinable!(terms -> groups (group_id));
+joinable!(terms_acceptances -> groups (group_id));
+joinable!(terms_versions -> groups (group_id));
 joinable!(user_ids -> profiles (user_uuid));
 
 allow_tables_to_appear_in_same_query!(
+    approvals,
+    email_invitations,
+    email_outbox,
+    group_includes,
     group_rules,
     groups,
     invitations,
@@ -257,11 +388,15 @@ allow_tables_to_appear_in_same_query!(
     legacy_user_data,
     logs,
     memberships,
+    notification_preferences,
+    pending_memberships,
     profiles,
     requests,
     roles,
     rules,
     terms,
+    terms_acceptances,
+    terms_versions,
     user_ids,
     users_authenticated,
     users_ndaed,
@@ -92,6 +92,20 @@ pub enum PermissionType {
     EditTerms,
 }
 
+impl PermissionType {
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::InviteMember,
+            Self::EditDescription,
+            Self::AddCurator,
+            Self::RemoveCurator,
+            Self::DeleteGroup,
+            Self::RemoveMember,
+            Self::EditTerms,
+        ]
+    }
+}
+
 #[derive(Copy, Clone, DbEnum, Debug, Deserialize, PartialEq, Eq, Serialize)]
 #[DieselType = "Capability_type"]
 pub enum CapabilityType {
@@ -104,29 +118,32 @@ pub enum CapabilityType {
 pub enum RoleType {
     Admin,
     Curator,
+    Custom,
     Member,
 }
 
-/// Custom order to skip database migration.
+/// Ranks roles by privilege, the reverse of the `role_type` enum order in the database which sorts
+/// admins first.
 impl PartialOrd for RoleType {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (self, other) {
-            (Self::Admin, Self::Curator)
-            | (Self::Admin, Self::Member)
-            | (Self::Curator, Self::Member) => Some(Ordering::Greater),
-            (Self::Member, Self::Curator)
-            | (Self::Member, Self::Admin)
-            | (Self::Curator, Self::Admin) => Some(Ordering::Less),
-            _ => Some(Ordering::Equal),
-        }
+        self.rank().partial_cmp(&other.rank())
     }
 }
 
 impl RoleType {
+    fn rank(&self) -> u8 {
+        match *self {
+            Self::Admin => 3,
+            Self::Curator => 2,
+            Self::Custom => 1,
+            Self::Member => 0,
+        }
+    }
+
     pub fn is_curator(&self) -> bool {
         match *self {
             Self::Admin | Self::Curator => true,
-            Self::Member => false,
+            Self::Custom | Self::Member => false,
         }
     }
 }
@@ -159,6 +176,41 @@ pub enum LogOperationType {
     Updated,
 }
 
+#[derive(Copy, Clone, DbEnum, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[DieselType = "Email_status_type"]
+pub enum EmailStatusType {
+    Pending,
+    Sent,
+    Failed,
+}
+
+/// Emails users can opt out of. Emails about changes to their own memberships are always sent.
+#[derive(Copy, Clone, DbEnum, Debug, Deserialize, PartialEq, Eq, Hash, Serialize)]
+#[DieselType = "Notification_category_type"]
+pub enum NotificationCategoryType {
+    Invitations,
+    Expirations,
+    Requests,
+}
+
+impl NotificationCategoryType {
+    pub fn all() -> Vec<Self> {
+        vec![
+            NotificationCategoryType::Invitations,
+            NotificationCategoryType::Expirations,
+            NotificationCategoryType::Requests,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotificationCategoryType::Invitations => "invitations",
+            NotificationCategoryType::Expirations => "expirations",
+            NotificationCategoryType::Requests => "requests",
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -171,5 +223,7 @@ mod test {
     #[test]
     fn test_role_type_order() {
         assert!(RoleType::Admin > RoleType::Member);
+        assert!(RoleType::Curator > RoleType::Custom);
+        assert!(RoleType::Custom > RoleType::Member);
     }
 }
@@ -37,6 +37,7 @@ table! {
         name -> VarChar,
         typ -> Group_type,
         trust -> Trust_type,
+        path -> VarChar,
         members_count -> BigInt,
     }
 }
@@ -17,4 +17,44 @@ pub enum PacksError {
     NoPrimaryEmail,
     #[fail(display = "no_uuid")]
     NoUuid,
+    #[fail(display = "invalid_rule")]
+    InvalidRule,
+    #[fail(display = "admin_role_requires_all_permissions")]
+    AdminRoleRequiresAllPermissions,
+    #[fail(display = "invalid_role_name")]
+    InvalidRoleName,
+    #[fail(display = "invalid_role_type")]
+    InvalidRoleType,
+    #[fail(display = "group_include_cycle")]
+    GroupIncludeCycle,
+    #[fail(display = "included_group_trust_too_low")]
+    IncludedGroupTrustTooLow,
+    #[fail(display = "not_a_member")]
+    NotAMember,
+    #[fail(display = "self_renewal_disabled")]
+    SelfRenewalDisabled,
+    #[fail(display = "self_renewal_not_yet_possible")]
+    SelfRenewalNotYetPossible,
+    #[fail(display = "max_membership_length_reached")]
+    MaxMembershipLengthReached,
+    #[fail(display = "already_self_renewed")]
+    AlreadySelfRenewed,
+    #[fail(display = "ambiguous_bulk_renewal")]
+    AmbiguousBulkRenewal,
+    #[fail(display = "invalid_email")]
+    InvalidEmail,
+    #[fail(display = "invalid_invitation_token")]
+    InvalidInvitationToken,
+    #[fail(display = "terms_not_accepted")]
+    TermsNotAccepted,
+    #[fail(display = "no_terms")]
+    NoTerms,
+    #[fail(display = "outdated_terms_version")]
+    OutdatedTermsVersion,
+    #[fail(display = "no_failed_email")]
+    NoFailedEmail,
+    #[fail(display = "invalid_unsubscribe_token")]
+    InvalidUnsubscribeToken,
+    #[fail(display = "member_required")]
+    MemberRequired,
 }
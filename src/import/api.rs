@@ -3,6 +3,7 @@ use crate::db::types::TrustType;
 use crate::db::Pool;
 use crate::import::ops::*;
 use crate::import::tsv::*;
+use actix_multipart::Field;
 use actix_multipart::Multipart;
 use actix_web::dev::HttpServiceFactory;
 use actix_web::http::header::DispositionParam;
@@ -29,30 +30,53 @@ struct GroupImportQuery {
     trust: Option<TrustType>,
 }
 
+pub(crate) fn field_name(field: &Field) -> Result<String, ApiError> {
+    match field.content_disposition() {
+        Some(cd) => match cd.parameters.first() {
+            Some(DispositionParam::Name(n)) => Ok(n.clone()),
+            _ => Err(ApiError::MultipartError),
+        },
+        None => Err(ApiError::MultipartError),
+    }
+}
+
+pub(crate) async fn read_field(field: Field) -> Result<Vec<u8>, ApiError> {
+    field
+        .try_fold(
+            Vec::<u8>::new(),
+            |mut acc: Vec<u8>, bytes: Bytes| async move {
+                acc.extend(bytes.into_iter());
+                Ok(acc)
+            },
+        )
+        .map_err(|_| ApiError::MultipartError)
+        .await
+}
+
+/// Like `read_field` but fails with `ApiError::InputToLong` once the field exceeds `limit` bytes.
+pub(crate) async fn read_field_limited(
+    mut field: Field,
+    limit: usize,
+) -> Result<Vec<u8>, ApiError> {
+    let mut buf = Vec::new();
+    while let Some(bytes) = field.next().await {
+        let bytes = bytes.map_err(|_| ApiError::MultipartError)?;
+        if buf.len() + bytes.len() > limit {
+            return Err(ApiError::InputToLong);
+        }
+        buf.extend_from_slice(&bytes);
+    }
+    Ok(buf)
+}
+
 async fn legacy_user_data_import(
     mut multipart: Multipart,
     pool: web::Data<Pool>,
 ) -> Result<HttpResponse, ApiError> {
     let mut legacy_user_data = None;
     while let Some(Ok(field)) = multipart.next().await {
-        let typ = if let Some(cd) = field.content_disposition() {
-            match cd.parameters.first() {
-                Some(DispositionParam::Name(n)) => n.clone(),
-                _ => return Err(ApiError::MultipartError),
-            }
-        } else {
-            return Err(ApiError::MultipartError);
-        };
-        let buf = field
-            .try_fold(
-                Vec::<u8>::new(),
-                |mut acc: Vec<u8>, bytes: Bytes| async move {
-                    acc.extend(bytes.into_iter());
-                    Ok(acc)
-                },
-            )
-            .map_err(|_| ApiError::MultipartError)
-            .await?;
+        let typ = field_name(&field)?;
+        let buf = read_field(field).await?;
         let mut rdr = ReaderBuilder::new()
             .delimiter(b'\t')
             .from_reader(buf.as_slice());
@@ -85,24 +109,8 @@ async fn full_group_import<T: AsyncCisClientTrait>(
         ..Default::default()
     };
     while let Some(Ok(field)) = multipart.next().await {
-        let typ = if let Some(cd) = field.content_disposition() {
-            match cd.parameters.first() {
-                Some(DispositionParam::Name(n)) => n.clone(),
-                _ => return Err(ApiError::MultipartError),
-            }
-        } else {
-            return Err(ApiError::MultipartError);
-        };
-        let buf = field
-            .try_fold(
-                Vec::<u8>::new(),
-                |mut acc: Vec<u8>, bytes: Bytes| async move {
-                    acc.extend(bytes.into_iter());
-                    Ok(acc)
-                },
-            )
-            .map_err(|_| ApiError::MultipartError)
-            .await?;
+        let typ = field_name(&field)?;
+        let buf = read_field(field).await?;
         let mut rdr = ReaderBuilder::new()
             .delimiter(b'\t')
             .from_reader(buf.as_slice());
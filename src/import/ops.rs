@@ -121,6 +121,7 @@ pub fn import_group(
         trust,
         capabilities: Default::default(),
         group_expiration: Some(moz_group.expiration),
+        parent: None,
     };
     let creator = User::default();
     let new_group = internal::group::add_group(&creator.user_uuid, connection, new_group)?;
@@ -128,7 +129,13 @@ pub fn import_group(
     internal::admin::add_admin_role(&log_ctx, connection, new_group.id)?;
     internal::member::add_member_role(&creator.user_uuid, connection, new_group.id)?;
     if !moz_group.terms.is_empty() {
-        internal::terms::set_terms(&creator.user_uuid, connection, &group_name, moz_group.terms)?;
+        internal::terms::set_terms(
+            &creator.user_uuid,
+            connection,
+            &group_name,
+            moz_group.terms,
+            None,
+        )?;
     }
     if !moz_group.invitation_email.is_empty() {
         internal::invitation::update_invitation_text(
@@ -175,7 +182,7 @@ async fn import_curator(
         return Ok(());
     }
     internal::admin::add_admin(connection, group_name, &User::default(), &user)?;
-    let groups = internal::member::group_names_for_user(connection, &user.user_uuid)?;
+    let groups = internal::include::effective_group_names_for_user(connection, &user.user_uuid)?;
     _send_groups_to_cis(cis_client, groups, user_profile.profile).await?;
     Ok(())
 }
@@ -249,7 +256,7 @@ pub async fn import_member(
         .execute(connection)
         .map(|_| ())?;
 
-    let groups = internal::member::group_names_for_user(connection, &user.user_uuid)?;
+    let groups = internal::include::effective_group_names_for_user(connection, &user.user_uuid)?;
     _send_groups_to_cis(cis_client, groups, user_profile.profile).await?;
     Ok(())
 }
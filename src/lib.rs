@@ -15,6 +15,7 @@ extern crate failure_derive;
 extern crate dino_park_guard;
 
 pub mod api;
+pub mod capabilities;
 pub mod cis;
 pub mod db;
 pub mod error;
@@ -23,5 +24,6 @@ pub mod import;
 pub mod mail;
 pub mod rules;
 pub mod settings;
+pub mod tokens;
 pub mod user;
 pub mod utils;
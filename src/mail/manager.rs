@@ -1,111 +1,264 @@
+use crate::db::establish_connection_with_size;
+use crate::db::internal;
+use crate::db::types::NotificationCategoryType;
+use crate::db::Pool;
+use crate::mail::outbox;
 use crate::mail::send::EmailSender;
-#[cfg(all(not(test), not(feature = "local")))]
+use crate::mail::send::Sender;
 use crate::mail::send::SesSender;
+use crate::mail::smtp::SmtpSender;
 use crate::mail::templates::Template;
 use crate::mail::templates::TemplateManager;
+use crate::mail::templates::DEFAULT_TEMPLATE_DIR;
 use crate::mail::Email;
-#[cfg(all(not(test), not(feature = "local")))]
 use crate::settings::Settings;
+use crate::tokens::TokenSecret;
+use crate::tokens::UnsubscribeToken;
 use actix_rt::Arbiter;
 use basket::Basket;
 use basket::SubscribeOpts;
 use basket::YesNo;
-#[cfg(all(not(test), not(feature = "local")))]
-use lazy_static::lazy_static;
+use failure::Error;
 use log::error;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use uuid::Uuid;
 
 const MOZILLIAN_NDA_LIST: &str = "mozillians-nda";
+const MAIL_POOL_SIZE: u32 = 4;
 
-#[cfg(all(not(test), not(feature = "local")))]
-lazy_static! {
-    static ref MAIL_MAN: MailMan<SesSender> = {
-        let s = Settings::new().expect("invalid settings");
-        let basket = s.basket.map(|b| Basket::new(b.api_key, b.basket_url));
-        MailMan::<SesSender>::new(s.packs.domain, s.packs.catcher, basket)
-    };
-}
-
-#[cfg(all(not(test), not(feature = "local")))]
-pub fn send_email(to: String, t: &Template) {
-    let message = MAIL_MAN.template_man.render(t);
-    MAIL_MAN.send(Email::with(to, &MAIL_MAN.template_man.domain, message));
-}
-
-#[cfg(all(not(test), not(feature = "local")))]
-pub fn send_emails(to: Vec<String>, t: &Template) {
-    let message = MAIL_MAN.template_man.render(t);
-    MAIL_MAN.send(Email::with_many(to, &MAIL_MAN.template_man.domain, message));
-}
-
-#[cfg(all(not(test), not(feature = "local")))]
-pub fn send_email_raw(mut email: Email) {
-    email.from = format!("no-reply@{}", &MAIL_MAN.template_man.domain);
-    MAIL_MAN.send(email);
-}
-
-#[cfg(any(test, feature = "local"))]
-pub fn send_email(_: String, _: &Template) {}
-
-#[cfg(any(test, feature = "local"))]
-pub fn send_emails(_: Vec<String>, _: &Template) {}
-
-#[cfg(any(test, feature = "local"))]
-pub fn send_email_raw(_: Email) {}
-
-#[cfg(all(not(test), not(feature = "local")))]
-pub fn subscribe_nda(email: impl Into<String>) {
-    MAIL_MAN.subscribe_nda(email);
-}
-
-#[cfg(all(not(test), not(feature = "local")))]
-pub fn unsubscribe_nda(email: impl Into<String>) {
-    MAIL_MAN.unsubscribe_nda(email);
-}
-
-#[cfg(any(test, feature = "local"))]
-pub fn subscribe_nda(_: impl Into<String>) {}
-
-#[cfg(any(test, feature = "local"))]
-pub fn unsubscribe_nda(_: impl Into<String>) {}
-
-pub struct MailMan<T: EmailSender> {
+pub struct MailMan<T: EmailSender = Sender> {
     pub arbiter: Arbiter,
     pub sender: T,
     pub template_man: TemplateManager,
     pub catcher: Option<String>,
     pub basket: Option<Basket>,
+    pub pool: Pool,
+    pub token_secret: TokenSecret,
+}
+
+impl MailMan<Sender> {
+    pub fn from_settings(s: &Settings, token_secret: TokenSecret) -> Result<Self, Error> {
+        let basket = s
+            .basket
+            .as_ref()
+            .map(|b| Basket::new(b.api_key.clone(), b.basket_url.clone()));
+        let pool = establish_connection_with_size(&s.packs.postgres_url, MAIL_POOL_SIZE);
+        let template_dir = s
+            .packs
+            .templates
+            .clone()
+            .unwrap_or_else(|| String::from(DEFAULT_TEMPLATE_DIR));
+        let template_man = TemplateManager::new(s.packs.domain.clone(), template_dir)?;
+        let sender = match s.smtp {
+            Some(ref smtp) => Sender::Smtp(SmtpSender::new(smtp)?),
+            None => Sender::Ses(SesSender::default()),
+        };
+        Ok(MailMan::new(
+            sender,
+            template_man,
+            s.packs.catcher.clone(),
+            basket,
+            pool,
+            token_secret,
+        ))
+    }
 }
 
 impl<T: EmailSender> MailMan<T> {
-    pub fn new(domain: String, catcher: Option<String>, basket: Option<Basket>) -> Self {
+    pub fn new(
+        sender: T,
+        template_man: TemplateManager,
+        catcher: Option<String>,
+        basket: Option<Basket>,
+        pool: Pool,
+        token_secret: TokenSecret,
+    ) -> Self {
         MailMan {
             arbiter: Arbiter::new(),
-            sender: T::default(),
-            template_man: TemplateManager::new(domain),
+            sender,
+            template_man,
             catcher,
             basket,
+            pool,
+            token_secret,
         }
     }
 }
 
 impl<T: EmailSender> MailMan<T> {
+    pub fn send_email(&self, to: String, t: &Template) {
+        self.send_template(vec![to], t, false);
+    }
+
+    pub fn send_emails(&self, to: Vec<String>, t: &Template) {
+        self.send_template(to, t, true);
+    }
+
+    pub fn send_email_raw(&self, mut email: Email) {
+        email.from = format!("no-reply@{}", &self.template_man.domain);
+        self.send(email);
+    }
+
+    /// Sends all queued emails which are due for another attempt.
+    pub async fn deliver_outbox(&self) -> Result<usize, Error> {
+        outbox::deliver_due(&self.pool, &self.sender).await
+    }
+
+    pub fn reload_templates(&self) -> Result<(), Error> {
+        self.template_man.reload()
+    }
+
+    /// Renders `t` in the preferred language of each recipient and sends one email per
+    /// language. With `bcc` all recipients sharing a language are sent a single email.
+    ///
+    /// Recipients who opted out of the category of `t` are skipped. All others get their own
+    /// email with an unsubscribe link, even with `bcc`.
+    pub fn send_template(&self, recipients: Vec<String>, t: &Template, bcc: bool) {
+        let category = t.category();
+        let (recipients, user_uuids) = match category {
+            Some(category) => self.subscribed(recipients, category),
+            None => (recipients, HashMap::default()),
+        };
+        let languages = self.preferred_languages(&recipients);
+        let mut by_locale: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for recipient in recipients {
+            let preferred = languages
+                .get(&recipient)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let locale = self.template_man.locale_for(t, preferred);
+            by_locale.entry(locale).or_default().push(recipient);
+        }
+        let domain = &self.template_man.domain;
+        for (locale, recipients) in by_locale {
+            if let Some(category) = category {
+                for recipient in recipients {
+                    let token = user_uuids.get(&recipient).map(|user_uuid| {
+                        UnsubscribeToken {
+                            user_uuid: *user_uuid,
+                            category,
+                        }
+                        .sign(&self.token_secret)
+                    });
+                    match self.template_man.render(t, &locale, token.as_deref()) {
+                        Ok(message) => {
+                            self.send(Email::with(recipient, domain, message).template(t.name()))
+                        }
+                        Err(e) => error!("Error rendering {} email ({}): {}", t.name(), locale, e),
+                    }
+                }
+                continue;
+            }
+            let message = match self.template_man.render(t, &locale, None) {
+                Ok(message) => message,
+                Err(e) => {
+                    error!("Error rendering {} email ({}): {}", t.name(), locale, e);
+                    continue;
+                }
+            };
+            if bcc {
+                self.send(Email::with_many(recipients, domain, message).template(t.name()));
+            } else {
+                for recipient in recipients {
+                    self.send(Email::with(recipient, domain, message.clone()).template(t.name()));
+                }
+            }
+        }
+    }
+
+    /// Drops recipients who opted out of `category` and looks up the users of the remaining
+    /// ones for their unsubscribe links. Everybody is kept if the preferences are unavailable.
+    fn subscribed(
+        &self,
+        recipients: Vec<String>,
+        category: NotificationCategoryType,
+    ) -> (Vec<String>, HashMap<String, Uuid>) {
+        let lookup = self.pool.get().map_err(Error::from).and_then(|connection| {
+            let opted_out = internal::notification::opted_out(&connection, &recipients, category)?;
+            let user_uuids = internal::user::user_uuids_by_email(&connection, &recipients)?;
+            Ok((opted_out, user_uuids))
+        });
+        match lookup {
+            Ok((opted_out, user_uuids)) => (
+                recipients
+                    .into_iter()
+                    .filter(|recipient| !opted_out.contains(recipient))
+                    .collect(),
+                user_uuids,
+            ),
+            Err(e) => {
+                error!("Unable to retrieve notification preferences: {}", e);
+                (recipients, HashMap::default())
+            }
+        }
+    }
+
+    fn preferred_languages(&self, emails: &[String]) -> HashMap<String, Vec<String>> {
+        match self
+            .pool
+            .get()
+            .map_err(Error::from)
+            .and_then(|connection| internal::user::preferred_languages(&connection, emails))
+        {
+            Ok(languages) => languages,
+            Err(e) => {
+                error!("Unable to retrieve preferred languages: {}", e);
+                HashMap::default()
+            }
+        }
+    }
+
     pub fn send(&self, mut e: Email) {
         if let Some(ref catcher) = self.catcher {
             if let Some(to) = e.to {
                 e.message.body = format!("[to: caught for {}]\n\n{}", to, e.message.body);
+                e.message.html = e
+                    .message
+                    .html
+                    .map(|html| format!("<p>[to: caught for {}]</p>\n{}", to, html));
                 e.to = Some(catcher.to_owned());
             };
             if let Some(bcc) = e.bcc {
                 e.message.body =
                     format!("[bcc: caught for {}]\n\n{}", bcc.join(", "), e.message.body);
+                e.message.html = e
+                    .message
+                    .html
+                    .map(|html| format!("<p>[bcc: caught for {}]</p>\n{}", bcc.join(", "), html));
                 e.to = Some(catcher.to_owned());
                 e.bcc = None;
             };
         }
+        // persist the email first so it survives failed attempts and restarts
+        let queued = self
+            .pool
+            .get()
+            .map_err(Error::from)
+            .and_then(|connection| internal::outbox::enqueue(&connection, &e));
+        match queued {
+            Ok(queued) => self.deliver(queued.id),
+            Err(err) => {
+                error!("Unable to queue email, sending without retries: {}", err);
+                let s = self.sender.clone();
+                let f = Box::pin(async move {
+                    if let Err(e) = s.send_email(e).await {
+                        error!("Error sending email: {}", e);
+                    }
+                });
+                if !self.arbiter.spawn(f) {
+                    error!("arbiter died");
+                }
+            }
+        }
+    }
+
+    pub fn deliver(&self, email_id: i32) {
         let s = self.sender.clone();
+        let pool = self.pool.clone();
         let f = Box::pin(async move {
-            if let Err(e) = s.send_email(e).await {
-                error!("Error sending email: {}", e);
+            if let Err(e) = outbox::deliver_one(&pool, &s, email_id).await {
+                error!("Error delivering email {}: {}", email_id, e);
             }
         });
         if !self.arbiter.spawn(f) {
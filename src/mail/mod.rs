@@ -1,15 +1,19 @@
 pub mod manager;
+pub mod outbox;
 pub mod send;
+pub mod smtp;
 pub mod templates;
 
 use rusoto_ses::Body;
 use rusoto_ses::Content;
 use serde::Deserialize;
 
-#[derive(Clone, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
 pub struct Message {
     pub subject: String,
     pub body: String,
+    #[serde(default)]
+    pub html: Option<String>,
 }
 
 impl From<Message> for rusoto_ses::Message {
@@ -24,18 +28,23 @@ impl From<Message> for rusoto_ses::Message {
                     data: m.body,
                     charset: Some("UTF-8".to_owned()),
                 }),
-                ..Default::default()
+                html: m.html.map(|html| Content {
+                    data: html,
+                    charset: Some("UTF-8".to_owned()),
+                }),
             },
         }
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct Email {
     pub to: Option<String>,
     pub bcc: Option<Vec<String>>,
     pub from: String,
     pub message: Message,
+    /// Name of the template the message was rendered from.
+    pub template: Option<String>,
 }
 
 impl Email {
@@ -45,6 +54,7 @@ impl Email {
             bcc: None,
             from: format!("no-reply@{domain}"),
             message,
+            template: None,
         }
     }
     pub fn with_many(bcc: Vec<String>, domain: &str, message: Message) -> Self {
@@ -53,6 +63,11 @@ impl Email {
             bcc: Some(bcc),
             from: format!("no-reply@{domain}"),
             message,
+            template: None,
         }
     }
+    pub fn template(mut self, name: &str) -> Self {
+        self.template = Some(name.to_owned());
+        self
+    }
 }
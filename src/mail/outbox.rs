@@ -0,0 +1,150 @@
+use crate::db::internal;
+use crate::db::model::OutboxEmail;
+use crate::db::Pool;
+use crate::mail::send::EmailSender;
+use crate::mail::Email;
+use crate::mail::Message;
+use chrono::Duration;
+use chrono::NaiveDateTime;
+use chrono::Utc;
+use failure::Error;
+use log::error;
+use log::info;
+use log::warn;
+
+/// Number of attempts after which an email is moved to the failed state.
+pub const MAX_ATTEMPTS: i32 = 8;
+const BASE_BACKOFF_SECS: i64 = 60;
+const MAX_BACKOFF_SECS: i64 = 6 * 60 * 60;
+const LEASE_SECS: i64 = 5 * 60;
+const BATCH_SIZE: i64 = 100;
+/// Sent emails are kept this long for reference before they are purged.
+const SENT_RETENTION_DAYS: i64 = 30;
+
+impl From<&OutboxEmail> for Email {
+    fn from(e: &OutboxEmail) -> Self {
+        Email {
+            to: e.to_address.clone(),
+            bcc: e.bcc.clone(),
+            from: e.from_address.clone(),
+            message: Message {
+                subject: e.subject.clone(),
+                body: e.body.clone(),
+                html: e.html_body.clone(),
+            },
+            template: e.template.clone(),
+        }
+    }
+}
+
+/// Delay before the next attempt after `attempts` failed ones, doubling from one minute up to
+/// six hours.
+pub fn backoff(attempts: i32) -> Duration {
+    let exp = attempts.max(1).min(16) as u32 - 1;
+    Duration::seconds((BASE_BACKOFF_SECS << exp).min(MAX_BACKOFF_SECS))
+}
+
+fn lease_until() -> NaiveDateTime {
+    Utc::now().naive_utc() + Duration::seconds(LEASE_SECS)
+}
+
+/// Sends all pending emails which are due and returns the number of emails sent. Emails which
+/// can not be processed are left for the next run once their lease is over. Sent emails older
+/// than the retention period are purged afterwards.
+pub async fn deliver_due<T: EmailSender>(pool: &Pool, sender: &T) -> Result<usize, Error> {
+    let emails = {
+        let connection = pool.get()?;
+        internal::outbox::claim_due(&connection, BATCH_SIZE, lease_until())?
+    };
+    let mut sent = 0;
+    for email in emails {
+        let email_id = email.id;
+        match deliver(pool, sender, email).await {
+            Ok(true) => sent += 1,
+            Ok(false) => {}
+            Err(e) => error!("Error delivering email {}: {}", email_id, e),
+        }
+    }
+    let sent_before = Utc::now().naive_utc() - Duration::days(SENT_RETENTION_DAYS);
+    match pool
+        .get()
+        .map_err(Error::from)
+        .and_then(|connection| internal::outbox::purge_sent(&connection, sent_before))
+    {
+        Ok(purged) if purged > 0 => info!("Purged {} sent emails", purged),
+        Ok(_) => {}
+        Err(e) => error!("Error purging sent emails: {}", e),
+    }
+    Ok(sent)
+}
+
+/// Sends a single pending email right away.
+pub async fn deliver_one<T: EmailSender>(
+    pool: &Pool,
+    sender: &T,
+    email_id: i32,
+) -> Result<bool, Error> {
+    let email = {
+        let connection = pool.get()?;
+        internal::outbox::claim(&connection, email_id, lease_until())?
+    };
+    match email {
+        Some(email) => deliver(pool, sender, email).await,
+        None => Ok(false),
+    }
+}
+
+async fn deliver<T: EmailSender>(
+    pool: &Pool,
+    sender: &T,
+    email: OutboxEmail,
+) -> Result<bool, Error> {
+    let result = sender.send_email(Email::from(&email)).await;
+    let connection = pool.get()?;
+    match result {
+        Ok(_) => {
+            internal::outbox::mark_sent(&connection, email.id)?;
+            Ok(true)
+        }
+        Err(e) => {
+            let attempts = email.attempts + 1;
+            let next_attempt = if attempts < MAX_ATTEMPTS {
+                warn!(
+                    "Error sending email {} (attempt {}): {}",
+                    email.id, attempts, e
+                );
+                Some(Utc::now().naive_utc() + backoff(attempts))
+            } else {
+                error!(
+                    "Giving up on email {} after {} attempts: {}",
+                    email.id, attempts, e
+                );
+                None
+            };
+            internal::outbox::mark_attempt_failed(
+                &connection,
+                email.id,
+                &e.to_string(),
+                next_attempt,
+            )?;
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), Duration::minutes(1));
+        assert_eq!(backoff(2), Duration::minutes(2));
+        assert_eq!(backoff(4), Duration::minutes(8));
+        assert_eq!(
+            backoff(MAX_ATTEMPTS),
+            Duration::hours(2) + Duration::minutes(8)
+        );
+        assert_eq!(backoff(12), Duration::hours(6));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::mail::smtp::SmtpSender;
 use crate::mail::Email;
 use failure::Error;
 use rusoto_ses::Destination;
@@ -6,6 +7,10 @@ use rusoto_ses::Ses;
 use rusoto_ses::SesClient;
 use std::future::Future;
 use std::pin::Pin;
+#[cfg(feature = "local")]
+use std::sync::Arc;
+#[cfg(feature = "local")]
+use std::sync::Mutex;
 
 pub trait EmailSender: Clone + Default + Send + Sync + Unpin + 'static {
     fn send_email(&self, email: Email) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
@@ -41,6 +46,68 @@ impl From<Email> for SendEmailRequest {
     }
 }
 
+/// Keeps sent emails in memory instead of delivering them, so tests can assert on them. Only
+/// built with the `local` feature.
+#[cfg(feature = "local")]
+#[derive(Clone, Default)]
+pub struct RecordingSender {
+    emails: Arc<Mutex<Vec<Email>>>,
+}
+
+#[cfg(feature = "local")]
+impl RecordingSender {
+    pub fn emails(&self) -> Vec<Email> {
+        self.emails
+            .lock()
+            .map(|emails| emails.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut emails) = self.emails.lock() {
+            emails.clear();
+        }
+    }
+}
+
+#[cfg(feature = "local")]
+impl EmailSender for RecordingSender {
+    fn send_email(&self, email: Email) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> {
+        let recorded = self
+            .emails
+            .lock()
+            .map(|mut emails| emails.push(email))
+            .map_err(|_| failure::err_msg("poisoned email recorder"));
+        Box::pin(async move { recorded })
+    }
+}
+
+/// The sender configured in the settings. SES is used unless SMTP is configured.
+#[derive(Clone)]
+pub enum Sender {
+    Ses(SesSender),
+    Smtp(SmtpSender),
+    #[cfg(feature = "local")]
+    Recording(RecordingSender),
+}
+
+impl Default for Sender {
+    fn default() -> Self {
+        Sender::Ses(SesSender::default())
+    }
+}
+
+impl EmailSender for Sender {
+    fn send_email(&self, email: Email) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> {
+        match self {
+            Sender::Ses(sender) => sender.send_email(email),
+            Sender::Smtp(sender) => sender.send_email(email),
+            #[cfg(feature = "local")]
+            Sender::Recording(sender) => sender.send_email(email),
+        }
+    }
+}
+
 const SES_TO_CHUNK_SIZE: usize = 50;
 
 impl EmailSender for SesSender {
@@ -58,6 +125,7 @@ impl EmailSender for SesSender {
                     bcc: Some(bcc),
                     from: email.from.clone(),
                     message: email.message.clone(),
+                    template: email.template.clone(),
                 };
 
                 client.send_email(part_email.into()).await.map(|_| ())?;
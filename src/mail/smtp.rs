@@ -0,0 +1,122 @@
+use crate::mail::send::EmailSender;
+use crate::mail::Email;
+use crate::settings::Smtp;
+use failure::Error;
+use lettre::message::header::ContentType;
+use lettre::message::MultiPart;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::AsyncSmtpTransport;
+use lettre::AsyncTransport;
+use lettre::Message;
+use lettre::Tokio1Executor;
+use std::future::Future;
+use std::pin::Pin;
+
+const DEFAULT_SMTP_HOST: &str = "localhost";
+const DEFAULT_SMTP_PORT: u16 = 25;
+
+#[derive(Clone)]
+pub struct SmtpSender {
+    pub transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpSender {
+    pub fn new(settings: &Smtp) -> Result<Self, Error> {
+        let mut builder = if settings.tls {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+        };
+        if let Some(port) = settings.port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (&settings.username, &settings.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        Ok(SmtpSender {
+            transport: builder.build(),
+        })
+    }
+}
+
+impl Default for SmtpSender {
+    fn default() -> Self {
+        SmtpSender {
+            transport: AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(DEFAULT_SMTP_HOST)
+                .port(DEFAULT_SMTP_PORT)
+                .build(),
+        }
+    }
+}
+
+fn to_message(email: Email) -> Result<Message, Error> {
+    let mut builder = Message::builder()
+        .from(email.from.parse()?)
+        .subject(email.message.subject);
+    if let Some(to) = email.to {
+        builder = builder.to(to.parse()?);
+    }
+    for bcc in email.bcc.unwrap_or_default() {
+        builder = builder.bcc(bcc.parse()?);
+    }
+    let message = match email.message.html {
+        Some(html) => {
+            builder.multipart(MultiPart::alternative_plain_html(email.message.body, html))?
+        }
+        None => builder
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.message.body)?,
+    };
+    Ok(message)
+}
+
+impl EmailSender for SmtpSender {
+    fn send_email(&self, email: Email) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> {
+        let transport = self.transport.clone();
+        Box::pin(async move {
+            let message = to_message(email)?;
+            transport.send(message).await?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mail::Message as MailMessage;
+
+    #[test]
+    fn test_to_message() -> Result<(), Error> {
+        let email = Email {
+            to: None,
+            bcc: Some(vec![
+                String::from("hknall@mozilla.com"),
+                String::from("kklein@mozilla.com"),
+            ]),
+            from: String::from("no-reply@people.mozilla.org"),
+            message: MailMessage {
+                subject: String::from("subject"),
+                body: String::from("text"),
+                html: Some(String::from("<p>html</p>")),
+            },
+            template: None,
+        };
+        let message = to_message(email)?;
+        assert_eq!(message.envelope().to().len(), 2);
+        let formatted = String::from_utf8(message.formatted())?;
+        assert!(formatted.contains("multipart/alternative"));
+        assert!(formatted.contains("Subject: subject"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_address() {
+        let email = Email {
+            to: Some(String::from("not an address")),
+            from: String::from("no-reply@people.mozilla.org"),
+            ..Default::default()
+        };
+        assert!(to_message(email).is_err());
+    }
+}
@@ -1,310 +1,373 @@
+use crate::db::types::NotificationCategoryType;
 use crate::mail::Message;
-
-fn invitation(group_name: &str, domain: &str) -> Message {
-    Message {
-        subject: format!("[{domain}] You have been invited to join the '{group_name}' group"),
-        body: format!(
-            "\
-Dear Mozillian,
-you've been invited to join the access group '{group_name}'.
-Please visit https://{domain}/a/{group_name} to accept the invitation.
-
-Cheers,
-The Mozilla IAM Team"
-        ),
+use failure::Error;
+use handlebars::Handlebars;
+use serde_json::json;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+pub const DEFAULT_LOCALE: &str = "en";
+pub const DEFAULT_TEMPLATE_DIR: &str = "templates/mail";
+
+const SUBJECT: &str = "subject";
+const TEXT: &str = "txt";
+const HTML: &str = "html";
+const EXTENSION: &str = "hbs";
+
+fn invitation_link(group_name: &str, domain: &str, token: Option<&str>) -> String {
+    match token {
+        Some(token) => format!("https://{domain}/a/{group_name}?invitation={token}"),
+        None => format!("https://{domain}/a/{group_name}"),
     }
 }
 
-fn custom_invitation(group_name: &str, domain: &str, copy: &str) -> Message {
-    Message {
-        subject: format!("[{domain}] You have been invited to join the '{group_name}' group"),
-        body: format!(
-            "\
-Dear Mozillian,
-you've been invited to join the access group '{group_name}'.
-The message from the curator is:
-
-{copy}
-
-Please visit https://{domain}/a/{group_name} to accept the invitation.
-
-Cheers,
-The Mozilla IAM Team"
-        ),
-    }
+fn unsubscribe_link(domain: &str, token: &str) -> String {
+    format!("https://{domain}/groups/api/v1/unsubscribe/{token}")
 }
 
-fn reject_request(group_name: &str, domain: &str) -> Message {
-    Message {
-        subject: format!(
-            "[{domain}] Your invitation request for the '{group_name}' group has been rejected"
-        ),
-        body: format!(
-            "\
-Dear Mozillian,
-your request to be invited to the '{group_name}' access group has been rejected.
-Please make sure to read the group description at https://{domain}/a/{group_name}
-
-Cheers,
-The Mozilla IAM Team"
-        ),
-    }
+/// Templates are loaded from `<dir>/<locale>/<name>.<part>.hbs` where `part` is one of
+/// `subject`, `txt` or `html`. The html part is optional.
+struct Registry {
+    text: Handlebars<'static>,
+    html: Handlebars<'static>,
 }
 
-fn delete_invitation(group_name: &str, domain: &str) -> Message {
-    Message {
-        subject: format!(
-            "[{domain}] Your invitation for the '{group_name}' group has been revoked"
-        ),
-        body: format!(
-            "\
-Dear Mozillian,
-your invitation to the '{group_name}' access group has been revoked.
-Please make sure to read the group description at https://{domain}/a/{group_name}
-
-Cheers,
-The Mozilla IAM Team"
-        ),
+impl Registry {
+    fn load(dir: &Path) -> Result<Self, Error> {
+        let mut text = Handlebars::new();
+        text.register_escape_fn(handlebars::no_escape);
+        let mut html = Handlebars::new();
+        for locale in fs::read_dir(dir)? {
+            let locale = locale?;
+            if !locale.file_type()?.is_dir() {
+                continue;
+            }
+            let locale_name = locale.file_name().to_string_lossy().to_lowercase();
+            for file in fs::read_dir(locale.path())? {
+                let path = file?.path();
+                if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+                    continue;
+                }
+                let stem = match path.file_stem().and_then(|s| s.to_str()) {
+                    Some(stem) => stem,
+                    None => continue,
+                };
+                let key = format!("{}/{}", locale_name, stem);
+                let source = fs::read_to_string(&path)?;
+                if stem.ends_with(&format!(".{}", HTML)) {
+                    html.register_template_string(&key, source)?;
+                } else {
+                    text.register_template_string(&key, source)?;
+                }
+            }
+        }
+        Ok(Registry { text, html })
     }
-}
-
-fn demote_curator(group_name: &str, domain: &str) -> Message {
-    Message {
-        subject: format!(
-            "[{domain}] Your membership for the '{group_name}' group has been revoked"
-        ),
-        body: format!(
-            "\
-Dear Mozillian,
-your curator status for the '{group_name}' access group has been revoked.
-You are still a member and can see your status here: https://{domain}/a/{group_name}
 
-Cheers,
-The Mozilla IAM Team"
-        ),
+    fn has_locale(&self, locale: &str, name: &str) -> bool {
+        self.text
+            .has_template(&format!("{}/{}.{}", locale, name, TEXT))
     }
 }
 
-fn delete_member(group_name: &str, domain: &str) -> Message {
-    Message {
-        subject: format!(
-            "[{domain}] Your membership for the '{group_name}' group has been revoked"
-        ),
-        body: format!(
-            "\
-Dear Mozillian,
-your membership to the '{group_name}' access group has been revoked.
-If you have any questions make sure to read the group description at https://{domain}/a/{group_name}
-
-Cheers,
-The Mozilla IAM Team"
-        ),
-    }
+#[derive(Clone)]
+pub struct TemplateManager {
+    pub domain: String,
+    dir: PathBuf,
+    registry: Arc<RwLock<Registry>>,
 }
 
-fn first_host_expiration(group_name: &str, user: &str, domain: &str) -> Message {
-    Message {
-        subject: format!(
-            "[{domain}] {user}'s membership of the '{group_name}' group is about to expire"
-        ),
-        body: format!(
-            "\
-Dear Curator,
-{user}'s membership of the '{group_name}' group will expire in 14 days.
-
-Please visit https://{domain}/a/{group_name}/edit?section=members to renew the \
-membership if applicable.
-
-Or visit {user}'s profile first: https://{domain}/p/{user}
-
-Cheers,
-The Mozilla IAM Team"
-        ),
+impl TemplateManager {
+    pub fn new(domain: String, dir: impl Into<PathBuf>) -> Result<Self, Error> {
+        let dir = dir.into();
+        let registry = Registry::load(&dir)?;
+        Ok(TemplateManager {
+            domain,
+            dir,
+            registry: Arc::new(RwLock::new(registry)),
+        })
     }
-}
-
-fn second_host_expiration(group_name: &str, user: &str, domain: &str) -> Message {
-    Message {
-        subject: format!(
-            "[{domain}] {user}'s membership of the '{group_name}' group is about to expire"
-        ),
-        body: format!(
-            "\
-Dear Curator,
-{user}'s membership of the '{group_name}' group will expire in 7 days.
 
-Please visit https://{domain}/a/{group_name}/edit?section=members to renew the \
-membership if applicable.
-
-Or visit {user}'s profile first: https://{domain}/p/{user}
-
-Cheers,
-The Mozilla IAM Team"
-        ),
+    /// Reads all templates from disk again. The current templates are kept if loading fails.
+    pub fn reload(&self) -> Result<(), Error> {
+        let registry = Registry::load(&self.dir)?;
+        *self
+            .registry
+            .write()
+            .map_err(|_| failure::err_msg("poisoned template registry"))? = registry;
+        Ok(())
     }
-}
 
-fn member_expiration(group_name: &str, domain: &str) -> Message {
-    Message {
-        subject: format!(
-            "[{domain}] Your membership of the '{group_name}' group is about to expire"
-        ),
-        body: format!(
-            "\
-Dear Mozillian,
-As per the terms of your membership to group '{group_name} your membership will expire in 7 days \
-unless you are renewed by your group’s curators.
-
-Your inviter has also been sent a notice for your renewal and will approve or reject your \
-membership renewal in the next 7 days.
-
-For more information visit the group page: https://{domain}/a/{group_name}
+    /// Picks the first of the `preferred` languages a template exists for. Regional variants
+    /// like `de-AT` fall back to `de` and everything else to [`DEFAULT_LOCALE`].
+    pub fn locale_for(&self, t: &Template, preferred: &[String]) -> String {
+        let registry = match self.registry.read() {
+            Ok(registry) => registry,
+            Err(_) => return DEFAULT_LOCALE.to_owned(),
+        };
+        preferred
+            .iter()
+            .map(|language| language.to_lowercase().replace('_', "-"))
+            .flat_map(|language| {
+                let base = language.split('-').next().map(String::from);
+                std::iter::once(language).chain(base)
+            })
+            .find(|locale| registry.has_locale(locale, t.name()))
+            .unwrap_or_else(|| DEFAULT_LOCALE.to_owned())
+    }
 
-Cheers,
-The Mozilla IAM Team"
-        ),
+    /// Renders `t` in `locale`. With an `unsubscribe` token the email links to the unsubscribe
+    /// page.
+    pub fn render(
+        &self,
+        t: &Template,
+        locale: &str,
+        unsubscribe: Option<&str>,
+    ) -> Result<Message, Error> {
+        let registry = self
+            .registry
+            .read()
+            .map_err(|_| failure::err_msg("poisoned template registry"))?;
+        let locale = if registry.has_locale(locale, t.name()) {
+            locale
+        } else {
+            DEFAULT_LOCALE
+        };
+        let key = |part: &str| format!("{}/{}.{}", locale, t.name(), part);
+        let mut data = t.data(&self.domain);
+        if let Some(token) = unsubscribe {
+            data["unsubscribe"] = Value::from(unsubscribe_link(&self.domain, token));
+        }
+        let subject = registry.text.render(&key(SUBJECT), &data)?;
+        let body = registry.text.render(&key(TEXT), &data)?;
+        let html = if registry.html.has_template(&key(HTML)) {
+            Some(registry.html.render(&key(HTML), &data)?)
+        } else {
+            None
+        };
+        Ok(Message {
+            subject: subject.trim().to_owned(),
+            body: body.trim_end().to_owned(),
+            html,
+        })
     }
 }
 
-fn pending_request(group_name: &str, count: usize, domain: &str) -> Message {
-    let pending = match count {
-        1 => String::from("is 1 pending request"),
-        c => format!("are {c} pending requests"),
-    };
-    Message {
-        subject: format!("[{domain}] There {pending} in the '{group_name}' group"),
-        body: format!(
-            "\
-Dear Curator,
-there are {pending} mozillians asking for invitation in the access group '{group_name}.
-For further action please visit: https://{domain}/a/{group_name}/edit?section=invitations
+pub enum Template {
+    Invitation(String, Option<String>),
+    CustomInvitation(String, String, Option<String>),
+    RejectRequest(String, Option<String>),
+    ApproveRequest(String, bool),
+    DeleteInvitation(String),
+    DemoteCurator(String),
+    DeleteMember(String),
+    MemberExpiration(String),
+    FirstHostExpiration(String, String),
+    SecondHostExpiration(String, String),
+    PendingRequest(String, usize),
+    GroupDeleted(String, String),
+    SelfRenewal(String, String),
+    GroupArchivalWarning(String, i64),
+    AnonymousMember,
+}
 
-Cheers,
-The Mozilla IAM Team"
-        ),
+impl Template {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Template::Invitation(_, _) | Template::CustomInvitation(_, _, _) => "invitation",
+            Template::RejectRequest(_, _) => "reject_request",
+            Template::ApproveRequest(_, _) => "approve_request",
+            Template::DeleteInvitation(_) => "delete_invitation",
+            Template::DemoteCurator(_) => "demote_curator",
+            Template::DeleteMember(_) => "delete_member",
+            Template::MemberExpiration(_) => "member_expiration",
+            Template::FirstHostExpiration(_, _) | Template::SecondHostExpiration(_, _) => {
+                "host_expiration"
+            }
+            Template::PendingRequest(_, _) => "pending_request",
+            Template::GroupDeleted(_, _) => "group_deleted",
+            Template::SelfRenewal(_, _) => "self_renewal",
+            Template::GroupArchivalWarning(_, _) => "group_archival_warning",
+            Template::AnonymousMember => "anonymous_member",
+        }
     }
-}
 
-fn group_deleted(group_name: &str, user: &str, domain: &str) -> Message {
-    Message {
-        subject: format!("[{domain}] The '{group_name}' group has been deleted"),
-        body: format!(
-            "\
-Dear Curator,
-the '{group_name}' group has been deleted by https://{domain}/p/{user}
+    /// The category users can opt out of. Templates without one are always sent.
+    pub fn category(&self) -> Option<NotificationCategoryType> {
+        match self {
+            Template::Invitation(_, _)
+            | Template::CustomInvitation(_, _, _)
+            | Template::DeleteInvitation(_) => Some(NotificationCategoryType::Invitations),
+            Template::MemberExpiration(_)
+            | Template::FirstHostExpiration(_, _)
+            | Template::SecondHostExpiration(_, _) => Some(NotificationCategoryType::Expirations),
+            Template::PendingRequest(_, _) => Some(NotificationCategoryType::Requests),
+            _ => None,
+        }
+    }
 
-Cheers,
-The Mozilla IAM Team"
-        ),
+    fn data(&self, domain: &str) -> Value {
+        match self {
+            Template::Invitation(ref group_name, ref token) => json!({
+                "domain": domain,
+                "group_name": group_name,
+                "link": invitation_link(group_name, domain, token.as_deref()),
+                "copy": null,
+            }),
+            Template::CustomInvitation(ref group_name, ref copy, ref token) => json!({
+                "domain": domain,
+                "group_name": group_name,
+                "link": invitation_link(group_name, domain, token.as_deref()),
+                "copy": copy,
+            }),
+            Template::RejectRequest(ref group_name, ref reason) => json!({
+                "domain": domain,
+                "group_name": group_name,
+                "reason": reason,
+            }),
+            Template::ApproveRequest(ref group_name, joined) => json!({
+                "domain": domain,
+                "group_name": group_name,
+                "joined": joined,
+            }),
+            Template::DeleteInvitation(ref group_name)
+            | Template::DemoteCurator(ref group_name)
+            | Template::DeleteMember(ref group_name)
+            | Template::MemberExpiration(ref group_name) => json!({
+                "domain": domain,
+                "group_name": group_name,
+            }),
+            Template::FirstHostExpiration(ref group_name, ref user) => json!({
+                "domain": domain,
+                "group_name": group_name,
+                "user": user,
+                "days": 14,
+            }),
+            Template::SecondHostExpiration(ref group_name, ref user) => json!({
+                "domain": domain,
+                "group_name": group_name,
+                "user": user,
+                "days": 7,
+            }),
+            Template::PendingRequest(ref group_name, count) => json!({
+                "domain": domain,
+                "group_name": group_name,
+                "count": count,
+            }),
+            Template::GroupDeleted(ref group_name, ref user) => json!({
+                "domain": domain,
+                "group_name": group_name,
+                "user": user,
+            }),
+            Template::SelfRenewal(ref group_name, ref expiration) => json!({
+                "domain": domain,
+                "group_name": group_name,
+                "expiration": expiration,
+            }),
+            Template::GroupArchivalWarning(ref group_name, days) => json!({
+                "domain": domain,
+                "group_name": group_name,
+                "days": days,
+            }),
+            Template::AnonymousMember => json!({ "domain": domain }),
+        }
     }
 }
 
-fn anonymous_member(domain: &str) -> Message {
-    Message {
-        subject: format!("[{domain}] mozillians.org decommissioning - PLEASE READ"),
-        body: format!(
-            "\
-Dear Mozillian,
-
-You are receiving this email because you are part of an access group 
-(mozillians.org/en-US/groups/) and your profile needs attention.
-
-As we prepare to decommission mozillians.org in a couple of weeks, we have
-finalized moving access groups data from mozillians.org to {domain}.
-
-How does this impact you?
-
-If you want to keep the access provided by the groups you're a member of,
-you will need to create an account on {domain}.
-To do this, please follow these steps:
-
-1. Go to {domain}
-2. Create and account by clicking the Log in/Sign up button
-3. When logging in, use the login method that you generally use to single sign
-on*
-4. Change your username to something to your liking
-5. Change your *email address* field level visibility settings from 'private'
-to 'NDA'd' so that group curators can see who you are when they need to renew
-your membership**
-6. (Optional) To further ensure curators can verify your identity consider
-changing the *first_name*/*last_name* field level visibility settings from
-private to 'NDA'd or take other adjustments like sharing a profile picture.
+#[cfg(test)]
+mod test {
+    use super::*;
 
-Pro tip: If you need to configure an additional profile on {domain} (because
-you currently have multiple mozillians.org identities), avoid being auto logged
-in by the system by logging out on sso.mozilla.com first.
-
-*It is important to note that in mozillians.org you were able to have multiple
-identities linked to your account. This will not be possible in {domain}
-anymore.
-
-If you currently use multiple identities within the mozilla ecosystem and
-you're experiencing problems, contact us on the #iam Slack channel so that we
-can manually check your account.
-
-**By failing to do so, you take the risk of showing as 'Anonymous user'
-to curators of the access groups you're part of, who will not extend your
-membership when it's due to expire.
-
-Thank you,
-The Mozilla IAM Team"
-        ),
+    fn template_man() -> TemplateManager {
+        TemplateManager::new(String::from("people.mozilla.org"), DEFAULT_TEMPLATE_DIR).unwrap()
     }
-}
 
-#[derive(Clone)]
-pub struct TemplateManager {
-    pub domain: String,
-}
+    #[test]
+    fn test_render_all_templates() -> Result<(), Error> {
+        let template_man = template_man();
+        let templates = vec![
+            Template::Invitation("nda".into(), Some("token".into())),
+            Template::CustomInvitation("nda".into(), "hi <there>".into(), None),
+            Template::RejectRequest("nda".into(), None),
+            Template::ApproveRequest("nda".into(), true),
+            Template::DeleteInvitation("nda".into()),
+            Template::DemoteCurator("nda".into()),
+            Template::DeleteMember("nda".into()),
+            Template::MemberExpiration("nda".into()),
+            Template::FirstHostExpiration("nda".into(), "hknall".into()),
+            Template::SecondHostExpiration("nda".into(), "hknall".into()),
+            Template::PendingRequest("nda".into(), 2),
+            Template::GroupDeleted("nda".into(), "hknall".into()),
+            Template::SelfRenewal("nda".into(), "2026-11-01".into()),
+            Template::GroupArchivalWarning("nda".into(), 30),
+            Template::AnonymousMember,
+        ];
+        for t in templates {
+            let message = template_man.render(&t, DEFAULT_LOCALE, None)?;
+            assert!(message.subject.starts_with("[people.mozilla.org]"));
+            assert!(!message.subject.contains('\n'));
+            assert!(message.body.ends_with("Team"));
+        }
+        Ok(())
+    }
 
-impl TemplateManager {
-    pub fn new(domain: String) -> Self {
-        TemplateManager { domain }
+    #[test]
+    fn test_render_parts() -> Result<(), Error> {
+        let template_man = template_man();
+        let message = template_man.render(
+            &Template::CustomInvitation("nda".into(), "hi <there>".into(), None),
+            DEFAULT_LOCALE,
+            None,
+        )?;
+        assert!(message.body.contains("hi <there>"));
+        let html = message.html.unwrap();
+        assert!(html.contains("hi &lt;there&gt;"));
+
+        let message = template_man.render(
+            &Template::PendingRequest("nda".into(), 1),
+            DEFAULT_LOCALE,
+            None,
+        )?;
+        assert_eq!(
+            message.subject,
+            "[people.mozilla.org] There is 1 pending request in the 'nda' group"
+        );
+        Ok(())
     }
 
-    pub fn render(&self, t: &Template) -> Message {
-        match t {
-            Template::Invitation(ref group_name) => invitation(group_name, &self.domain),
-            Template::CustomInvitation(ref group_name, copy) => {
-                custom_invitation(group_name, &self.domain, copy)
-            }
-            Template::RejectRequest(ref group_name) => reject_request(group_name, &self.domain),
-            Template::DeleteInvitation(ref group_name) => {
-                delete_invitation(group_name, &self.domain)
-            }
-            Template::DemoteCurator(ref group_name) => demote_curator(group_name, &self.domain),
-            Template::DeleteMember(ref group_name) => delete_member(group_name, &self.domain),
-            Template::MemberExpiration(ref group_name) => {
-                member_expiration(group_name, &self.domain)
-            }
-            Template::FirstHostExpiration(ref group_name, ref user) => {
-                first_host_expiration(group_name, user, &self.domain)
-            }
-            Template::SecondHostExpiration(ref group_name, ref user) => {
-                second_host_expiration(group_name, user, &self.domain)
-            }
-            Template::PendingRequest(ref group_name, count) => {
-                pending_request(group_name, *count, &self.domain)
-            }
-            Template::GroupDeleted(ref group_name, ref user) => {
-                group_deleted(group_name, user, &self.domain)
-            }
-            Template::AnonymousMember => anonymous_member(&self.domain),
-        }
+    #[test]
+    fn test_render_unsubscribe_link() -> Result<(), Error> {
+        let template_man = template_man();
+        let t = Template::PendingRequest("nda".into(), 2);
+        let link = "https://people.mozilla.org/groups/api/v1/unsubscribe/token";
+        let message = template_man.render(&t, DEFAULT_LOCALE, Some("token"))?;
+        assert!(message.body.ends_with(link));
+        assert!(message.html.unwrap().contains(link));
+        let message = template_man.render(&t, DEFAULT_LOCALE, None)?;
+        assert!(!message.body.contains("unsubscribe"));
+        assert!(!message.html.unwrap().contains("unsubscribe"));
+        Ok(())
     }
-}
 
-pub enum Template {
-    Invitation(String),
-    CustomInvitation(String, String),
-    RejectRequest(String),
-    DeleteInvitation(String),
-    DemoteCurator(String),
-    DeleteMember(String),
-    MemberExpiration(String),
-    FirstHostExpiration(String, String),
-    SecondHostExpiration(String, String),
-    PendingRequest(String, usize),
-    GroupDeleted(String, String),
-    AnonymousMember,
+    #[test]
+    fn test_locale_fallback() -> Result<(), Error> {
+        let template_man = template_man();
+        let invitation = Template::Invitation("nda".into(), None);
+        let deleted = Template::DeleteInvitation("nda".into());
+        let preferred = vec![String::from("de-AT"), String::from("en")];
+        assert_eq!(template_man.locale_for(&invitation, &preferred), "de");
+        assert_eq!(template_man.locale_for(&deleted, &preferred), "en");
+        assert_eq!(template_man.locale_for(&invitation, &[]), DEFAULT_LOCALE);
+        assert_eq!(
+            template_man.locale_for(&invitation, &[String::from("fr")]),
+            DEFAULT_LOCALE
+        );
+        let message = template_man.render(&deleted, "de", None)?;
+        assert!(message.body.starts_with("Dear Mozillian"));
+        Ok(())
+    }
 }
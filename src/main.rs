@@ -30,11 +30,27 @@ async fn main() -> std::io::Result<()> {
     debug!("DEBUG logging enabled");
 
     let s = settings::Settings::new().map_err(map_io_err)?;
+    if s.packs.token_secret.is_empty() {
+        return Err(Error::other(
+            "packs.token_secret is empty, set it via DP_PACKS__TOKEN_SECRET",
+        ));
+    }
     let cis_client = CisClient::from_settings(&s.cis).await.map_err(map_io_err)?;
 
     let pool = db::establish_connection(&s.packs.postgres_url);
     embedded_migrations::run_with_output(&pool.get().map_err(map_io_err)?, &mut std::io::stdout())
         .map_err(map_io_err)?;
+    let token_secret = tokens::TokenSecret::new(s.packs.token_secret.clone());
+    let mail_man = web::Data::new(
+        mail::manager::MailMan::from_settings(&s, token_secret.clone()).map_err(map_io_err)?,
+    );
+    let token_secret = web::Data::new(token_secret);
+
+    #[cfg(feature = "local")]
+    let providers = capabilities::local::providers();
+    #[cfg(not(feature = "local"))]
+    let providers = capabilities::Providers::default();
+    let providers = web::Data::new(providers);
 
     let provider = Provider::from_issuer(&s.auth).await.map_err(map_io_err)?;
     HttpServer::new(move || {
@@ -42,10 +58,14 @@ async fn main() -> std::io::Result<()> {
         App::new()
             .app_data(web::Data::new(cis_client.clone()))
             .app_data(web::Data::new(pool.clone()))
+            .app_data(mail_man.clone())
+            .app_data(providers.clone())
+            .app_data(token_secret.clone())
             .wrap(Logger::default().exclude("/healthz"))
             .service(healthz::healthz_app())
             .service(api::internal::internal_app::<CisClient>())
             .service(import::api::import_app::<CisClient>())
+            .service(api::notifications::unsubscribe_app())
             .service(
                 web::scope("/groups/api/v1")
                     .wrap(scope_middleware)
@@ -53,10 +73,14 @@ async fn main() -> std::io::Result<()> {
                     .service(api::members::members_app::<CisClient>())
                     .service(api::current::current_app::<CisClient>())
                     .service(api::invitations::invitations_app())
+                    .service(api::notifications::notifications_app())
                     .service(api::terms::terms_app())
                     .service(api::users::users_app())
                     .service(api::admins::admins_app::<CisClient>())
-                    .service(api::requests::requests_app())
+                    .service(api::permissions::permissions_app())
+                    .service(api::requests::requests_app::<CisClient>())
+                    .service(api::roles::roles_app())
+                    .service(api::rules::rules_app::<CisClient>())
                     .service(api::sudo::sudo_app::<CisClient>())
                     .service(api::forms::forms_app::<CisClient>())
                     .service(api::info::info_app(provider.clone())),
@@ -3,74 +3,212 @@ use crate::rules::functions::*;
 use crate::rules::RuleContext;
 use dino_park_trust::GroupsTrust;
 use log::info;
+use serde::Serialize;
+use serde::Serializer;
 
 pub const CREATE_GROUP: Engine = Engine {
-    rules: &[&rule_is_creator, &rule_valid_group_name],
+    rules: &[
+        ("is_creator", &rule_is_creator),
+        ("valid_group_name", &rule_valid_group_name),
+    ],
+};
+
+pub const CREATE_SUBGROUP: Engine = Engine {
+    rules: &[("host_is_curator", &rule_host_is_curator)],
 };
 
 pub const CURRENT_USER_CAN_JOIN: Engine = Engine {
-    rules: &[&current_user_can_join],
+    rules: &[("current_user_can_join", &current_user_can_join)],
 };
 
 pub const CURRENT_USER_CAN_REQUEST: Engine = Engine {
-    rules: &[&current_user_can_join, &is_reviewed_group],
+    rules: &[
+        ("current_user_can_join", &current_user_can_join),
+        ("is_reviewed_group", &is_reviewed_group),
+    ],
 };
 
 pub const ADMIN_CAN_ADD_MEMBER: Engine = Engine {
-    rules: &[&rule_only_admins, &member_can_join],
+    rules: &[
+        ("only_admins", &rule_only_admins),
+        ("member_can_join", &member_can_join),
+    ],
 };
 
 pub const SEARCH_USERS: Engine = Engine {
-    rules: &[&rule_host_can_invite],
+    rules: &[("host_can_invite", &rule_host_can_invite)],
 };
 
 pub const DELETE_INVITATION: Engine = Engine {
-    rules: &[&rule_host_can_invite],
+    rules: &[("host_can_invite", &rule_host_can_invite)],
+};
+
+pub const INVITE_EMAIL: Engine = Engine {
+    rules: &[("host_can_invite", &rule_host_can_invite)],
+};
+
+pub const BULK_INVITE: Engine = Engine {
+    rules: &[("host_can_invite", &rule_host_can_invite)],
 };
 
 pub const INVITE_MEMBER: Engine = Engine {
-    rules: &[&rule_host_can_invite, &member_can_join, &user_not_a_member],
+    rules: &[
+        ("host_can_invite", &rule_host_can_invite),
+        ("member_can_join", &member_can_join),
+        ("user_not_a_member", &user_not_a_member),
+    ],
+};
+
+/// Admins do not skip the quorum of a group, run this with `Engine::run_strict`.
+pub const MEMBER_IS_APPROVED: Engine = Engine {
+    rules: &[("member_has_approvals", &member_has_approvals)],
 };
 
 pub const RENEW_MEMBER: Engine = Engine {
-    rules: &[&rule_host_can_invite, &rule_user_has_member_role],
+    rules: &[
+        ("host_can_invite", &rule_host_can_invite),
+        ("user_has_member_role", &rule_user_has_member_role),
+        ("member_accepted_terms", &member_accepted_terms),
+    ],
+};
+
+pub const BULK_RENEW: Engine = Engine {
+    rules: &[("host_can_invite", &rule_host_can_invite)],
+};
+
+pub const SELF_RENEW: Engine = Engine {
+    rules: &[("member_accepted_terms", &member_accepted_terms)],
 };
 
 pub const REMOVE_MEMBER: Engine = Engine {
-    rules: &[&rule_host_can_remove],
+    rules: &[("host_can_remove", &rule_host_can_remove)],
 };
 
 pub const EDIT_TERMS: Engine = Engine {
-    rules: &[&rule_host_can_edit_terms],
+    rules: &[("host_can_edit_terms", &rule_host_can_edit_terms)],
 };
 
 pub const CAN_ADD_CURATOR: Engine = Engine {
-    rules: &[&rule_host_is_curator, &member_is_ndaed],
+    rules: &[
+        ("host_can_add_curator", &rule_host_can_add_curator),
+        ("member_is_ndaed", &member_is_ndaed),
+    ],
+};
+
+pub const REMOVE_CURATOR: Engine = Engine {
+    rules: &[("host_can_remove_curator", &rule_host_can_remove_curator)],
+};
+
+pub const ASSIGN_ROLE: Engine = Engine {
+    rules: &[
+        ("host_is_group_admin", &rule_host_is_group_admin),
+        ("user_has_member_role", &rule_user_has_member_role),
+    ],
+};
+
+pub const UPDATE_GROUP: Engine = Engine {
+    rules: &[("host_can_edit_description", &rule_host_can_edit_description)],
+};
+
+pub const DELETE_GROUP: Engine = Engine {
+    rules: &[("host_can_delete_group", &rule_host_can_delete_group)],
 };
 
 pub const HOST_IS_CURATOR: Engine = Engine {
-    rules: &[&rule_host_is_curator],
+    rules: &[("host_is_curator", &rule_host_is_curator)],
 };
 
 pub const HOST_IS_GROUP_ADMIN: Engine = Engine {
-    rules: &[&rule_host_is_group_admin],
+    rules: &[("host_is_group_admin", &rule_host_is_group_admin)],
 };
 
 pub const ONLY_ADMINS: Engine = Engine {
-    rules: &[&rule_only_admins],
+    rules: &[("only_admins", &rule_only_admins)],
 };
 
+/// A rule with the stable name `Engine::explain` reports it under.
+pub type NamedRule = (&'static str, &'static Rule);
+
 pub struct Engine<'a> {
-    pub rules: &'a [&'static Rule],
+    pub rules: &'a [NamedRule],
+}
+
+fn maybe_rule_error<S>(error: &Option<RuleError>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match error {
+        Some(e) => serializer.serialize_some(&e.to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Outcome of a single rule, in the order the rules are listed in the engine.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct RuleOutcome {
+    pub rule: &'static str,
+    pub passed: bool,
+    #[serde(serialize_with = "maybe_rule_error")]
+    pub error: Option<RuleError>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Explanation {
+    pub allowed: bool,
+    pub admin_override: bool,
+    pub rules: Vec<RuleOutcome>,
+}
+
+impl Explanation {
+    /// Whether the action is allowed when ignoring the rules that need a member in the
+    /// `RuleContext`, i.e. whether it is allowed for at least some members.
+    pub fn allowed_without_member(&self) -> bool {
+        self.admin_override
+            || self.rules.iter().all(|outcome| {
+                outcome.passed || outcome.error == Some(RuleError::InvalidRuleContext)
+            })
+    }
 }
 
 impl<'a> Engine<'a> {
     pub fn run(&self, ctx: &RuleContext) -> Result<(), RuleError> {
-        let ok = self.rules.iter().try_for_each(|rule| rule(ctx));
+        let ok = self.run_strict(ctx);
         if ok.is_err() && ctx.scope_and_user.groups_scope == GroupsTrust::Admin {
             info!("using admin privileges for {}", ctx.host_uuid);
             return Ok(());
         }
         ok
     }
+
+    /// Like `run` but without the admin override.
+    pub fn run_strict(&self, ctx: &RuleContext) -> Result<(), RuleError> {
+        self.rules.iter().try_for_each(|(_, rule)| rule(ctx))
+    }
+
+    /// Like `run` but evaluates every rule instead of stopping at the first failure.
+    pub fn explain(&self, ctx: &RuleContext) -> Explanation {
+        let rules: Vec<RuleOutcome> = self
+            .rules
+            .iter()
+            .map(|(name, rule)| match rule(ctx) {
+                Ok(()) => RuleOutcome {
+                    rule: name,
+                    passed: true,
+                    error: None,
+                },
+                Err(e) => RuleOutcome {
+                    rule: name,
+                    passed: false,
+                    error: Some(e),
+                },
+            })
+            .collect();
+        let passed = rules.iter().all(|outcome| outcome.passed);
+        let admin_override = !passed && ctx.scope_and_user.groups_scope == GroupsTrust::Admin;
+        Explanation {
+            allowed: passed || admin_override,
+            admin_override,
+            rules,
+        }
+    }
 }
@@ -22,6 +22,14 @@ pub enum RuleError {
     NotAMember,
     #[fail(display = "rule_not_allowed_to_edit_terms")]
     NotAllowedToEditTerms,
+    #[fail(display = "rule_not_allowed_to_edit_description")]
+    NotAllowedToEditDescription,
+    #[fail(display = "rule_not_allowed_to_add_curator")]
+    NotAllowedToAddCurator,
+    #[fail(display = "rule_not_allowed_to_remove_curator")]
+    NotAllowedToRemoveCurator,
+    #[fail(display = "rule_not_allowed_to_delete_group")]
+    NotAllowedToDeleteGroup,
     #[fail(display = "rule_never_allowed")]
     NeverAllowed,
     #[fail(display = "rule_invalid_context")]
@@ -32,4 +40,10 @@ pub enum RuleError {
     AlreadyMember,
     #[fail(display = "rule_invalid_group_name")]
     InvalidGroupName,
+    #[fail(display = "rule_admission_rule_not_met")]
+    AdmissionRuleNotMet,
+    #[fail(display = "rule_not_enough_approvals")]
+    NotEnoughApprovals,
+    #[fail(display = "rule_terms_not_accepted")]
+    TermsNotAccepted,
 }
@@ -1,9 +1,13 @@
 use crate::db::internal;
+use crate::db::model::Group;
 use crate::db::types::*;
 use crate::rules::error::RuleError;
 use crate::rules::RuleContext;
 use crate::utils::valid_group_name;
+use diesel::PgConnection;
 use dino_park_trust::GroupsTrust;
+use std::collections::BTreeSet;
+use uuid::Uuid;
 
 /*
 The other places these groups are handled.
@@ -35,34 +39,34 @@ pub fn rule_is_creator(ctx: &RuleContext) -> Result<(), RuleError> {
     }
 }
 
-/// Check if the host is either `RoleType::Admin` or has `InviteMember` permissions for the given
-/// group.
-pub fn rule_host_can_invite(ctx: &RuleContext) -> Result<(), RuleError> {
+fn host_has_permission(
+    ctx: &RuleContext,
+    permission: PermissionType,
+    error: RuleError,
+) -> Result<(), RuleError> {
     let connection = ctx.pool.get().map_err(|_| RuleError::PoolError)?;
     match internal::member::role_for(&connection, ctx.host_uuid, ctx.group) {
-        Ok(Some(role))
-            if role.typ == RoleType::Admin
-                || role.permissions.contains(&PermissionType::InviteMember) =>
-        {
-            Ok(())
-        }
-        _ => Err(RuleError::NotAllowedToInviteMember),
+        Ok(Some(role)) if role.permissions.contains(&permission) => Ok(()),
+        _ => Err(error),
     }
 }
 
-/// Check if the host is either `RoleType::Admin` or has `RemoveMember` permissions for the given
-/// group.
+/// Check if the host's role has `InviteMember` permissions for the given group.
+pub fn rule_host_can_invite(ctx: &RuleContext) -> Result<(), RuleError> {
+    host_has_permission(
+        ctx,
+        PermissionType::InviteMember,
+        RuleError::NotAllowedToInviteMember,
+    )
+}
+
+/// Check if the host's role has `RemoveMember` permissions for the given group.
 pub fn rule_host_can_remove(ctx: &RuleContext) -> Result<(), RuleError> {
-    let connection = ctx.pool.get().map_err(|_| RuleError::PoolError)?;
-    match internal::member::role_for(&connection, ctx.host_uuid, ctx.group) {
-        Ok(Some(role))
-            if role.typ == RoleType::Admin
-                || role.permissions.contains(&PermissionType::RemoveMember) =>
-        {
-            Ok(())
-        }
-        _ => Err(RuleError::NotAllowedToRemoveMember),
-    }
+    host_has_permission(
+        ctx,
+        PermissionType::RemoveMember,
+        RuleError::NotAllowedToRemoveMember,
+    )
 }
 
 pub fn user_not_a_member(ctx: &RuleContext) -> Result<(), RuleError> {
@@ -92,17 +96,25 @@ pub fn member_is_ndaed(ctx: &RuleContext) -> Result<(), RuleError> {
 /// Check if the user is nda'd or the group is the nda group
 pub fn member_can_join(ctx: &RuleContext) -> Result<(), RuleError> {
     let connection = ctx.pool.get().map_err(|_| RuleError::PoolError)?;
-    let trust = internal::user::user_trust(
-        &connection,
-        ctx.member_uuid.ok_or(RuleError::InvalidRuleContext)?,
-    )
-    .map_err(|_| RuleError::UserNotFound)?;
+    let member_uuid = ctx.member_uuid.ok_or(RuleError::InvalidRuleContext)?;
     let group =
         internal::group::get_group(&connection, ctx.group).map_err(|_| RuleError::DBError)?;
-    if trust >= group.trust {
-        return Ok(());
+    member_can_join_group(&connection, &group, member_uuid)
+}
+
+/// The check behind `member_can_join` for callers without a `RuleContext`: the member needs
+/// the group's trust level and must meet its admission rules.
+pub fn member_can_join_group(
+    connection: &PgConnection,
+    group: &Group,
+    member_uuid: &Uuid,
+) -> Result<(), RuleError> {
+    let trust =
+        internal::user::user_trust(connection, member_uuid).map_err(|_| RuleError::UserNotFound)?;
+    if trust < group.trust {
+        return Err(RuleError::NotAllowedToJoinGroup);
     }
-    Err(RuleError::NotAllowedToJoinGroup)
+    admission_rules_met(connection, group, trust, member_uuid)
 }
 
 /// Check if the current user is nda'd or the group is the nda group
@@ -110,10 +122,70 @@ pub fn current_user_can_join(ctx: &RuleContext) -> Result<(), RuleError> {
     let connection = ctx.pool.get().map_err(|_| RuleError::PoolError)?;
     let group =
         internal::group::get_group(&connection, ctx.group).map_err(|_| RuleError::DBError)?;
-    if TrustType::from(&ctx.scope_and_user.scope) >= group.trust {
-        return Ok(());
+    let trust = TrustType::from(&ctx.scope_and_user.scope);
+    if trust < group.trust {
+        return Err(RuleError::NotAllowedToJoinGroup);
+    }
+    let user = internal::user::user_by_id(&connection, &ctx.scope_and_user.user_id)
+        .map_err(|_| RuleError::UserNotFound)?;
+    admission_rules_met(&connection, &group, trust, &user.user_uuid)
+}
+
+/// Evaluate the admission rules stored for a group (`rules` and `group_rules`).
+fn admission_rules_met(
+    connection: &PgConnection,
+    group: &Group,
+    trust: TrustType,
+    user_uuid: &Uuid,
+) -> Result<(), RuleError> {
+    match internal::rule::admission_rules_met(
+        connection,
+        group,
+        trust,
+        user_uuid,
+        &mut BTreeSet::new(),
+    ) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(RuleError::AdmissionRuleNotMet),
+        Err(_) => Err(RuleError::DBError),
+    }
+}
+
+/// Check if the member collected the number of curator approvals the group requires
+pub fn member_has_approvals(ctx: &RuleContext) -> Result<(), RuleError> {
+    let member_uuid = ctx.member_uuid.ok_or(RuleError::InvalidRuleContext)?;
+    let connection = ctx.pool.get().map_err(|_| RuleError::PoolError)?;
+    let group =
+        internal::group::get_group(&connection, ctx.group).map_err(|_| RuleError::DBError)?;
+    let required = match group.required_approvals {
+        Some(required) if required > 1 => i64::from(required),
+        _ => return Ok(()),
+    };
+    let approvals = internal::approval::count_for(&connection, group.id, member_uuid)
+        .map_err(|_| RuleError::DBError)?;
+    if approvals >= required {
+        Ok(())
+    } else {
+        Err(RuleError::NotEnoughApprovals)
+    }
+}
+
+/// Check if the member accepted the current terms in case the group requires re-acceptance
+pub fn member_accepted_terms(ctx: &RuleContext) -> Result<(), RuleError> {
+    let member_uuid = ctx.member_uuid.ok_or(RuleError::InvalidRuleContext)?;
+    let connection = ctx.pool.get().map_err(|_| RuleError::PoolError)?;
+    let group =
+        internal::group::get_group(&connection, ctx.group).map_err(|_| RuleError::DBError)?;
+    let version = match internal::terms::current_terms(&connection, &group) {
+        Ok(Some(terms)) if terms.require_reacceptance => terms.version,
+        Ok(_) => return Ok(()),
+        Err(_) => return Err(RuleError::DBError),
+    };
+    match internal::terms::has_accepted(&connection, group.id, member_uuid, version) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(RuleError::TermsNotAccepted),
+        Err(_) => Err(RuleError::DBError),
     }
-    Err(RuleError::NotAllowedToJoinGroup)
 }
 
 /// Check if the groups is of type `Reviewed`
@@ -131,7 +203,7 @@ pub fn is_reviewed_group(ctx: &RuleContext) -> Result<(), RuleError> {
 pub fn rule_host_is_curator(ctx: &RuleContext) -> Result<(), RuleError> {
     let connection = ctx.pool.get().map_err(|_| RuleError::PoolError)?;
     match internal::member::role_for(&connection, ctx.host_uuid, ctx.group) {
-        Ok(Some(role)) if role.typ == RoleType::Admin || role.typ == RoleType::Curator => Ok(()),
+        Ok(Some(role)) if role.typ.is_curator() => Ok(()),
         _ => Err(RuleError::NotACurator),
     }
 }
@@ -145,29 +217,60 @@ pub fn rule_host_is_group_admin(ctx: &RuleContext) -> Result<(), RuleError> {
     }
 }
 
-/// Check if the member is either `RoleType::Member` for the given group
+/// Check if the member is either `RoleType::Member` or has a `RoleType::Custom` role for the
+/// given group
 pub fn rule_user_has_member_role(ctx: &RuleContext) -> Result<(), RuleError> {
     let member_uuid = ctx.member_uuid.ok_or(RuleError::InvalidRuleContext)?;
     let connection = ctx.pool.get().map_err(|_| RuleError::PoolError)?;
     match internal::member::role_for(&connection, member_uuid, ctx.group) {
-        Ok(Some(role)) if role.typ == RoleType::Member => Ok(()),
+        Ok(Some(role)) if !role.typ.is_curator() => Ok(()),
         _ => Err(RuleError::NotAMember),
     }
 }
 
-/// Check if the host is either `RoleType::Admin` or has `EditTerms` permissions for the given
-/// group.
+/// Check if the host's role has `EditTerms` permissions for the given group.
 pub fn rule_host_can_edit_terms(ctx: &RuleContext) -> Result<(), RuleError> {
-    let connection = ctx.pool.get().map_err(|_| RuleError::PoolError)?;
-    match internal::member::role_for(&connection, ctx.host_uuid, ctx.group) {
-        Ok(Some(role))
-            if role.typ == RoleType::Admin
-                || role.permissions.contains(&PermissionType::EditTerms) =>
-        {
-            Ok(())
-        }
-        _ => Err(RuleError::NotAllowedToEditTerms),
-    }
+    host_has_permission(
+        ctx,
+        PermissionType::EditTerms,
+        RuleError::NotAllowedToEditTerms,
+    )
+}
+
+/// Check if the host's role has `EditDescription` permissions for the given group.
+pub fn rule_host_can_edit_description(ctx: &RuleContext) -> Result<(), RuleError> {
+    host_has_permission(
+        ctx,
+        PermissionType::EditDescription,
+        RuleError::NotAllowedToEditDescription,
+    )
+}
+
+/// Check if the host's role has `AddCurator` permissions for the given group.
+pub fn rule_host_can_add_curator(ctx: &RuleContext) -> Result<(), RuleError> {
+    host_has_permission(
+        ctx,
+        PermissionType::AddCurator,
+        RuleError::NotAllowedToAddCurator,
+    )
+}
+
+/// Check if the host's role has `RemoveCurator` permissions for the given group.
+pub fn rule_host_can_remove_curator(ctx: &RuleContext) -> Result<(), RuleError> {
+    host_has_permission(
+        ctx,
+        PermissionType::RemoveCurator,
+        RuleError::NotAllowedToRemoveCurator,
+    )
+}
+
+/// Check if the host's role has `DeleteGroup` permissions for the given group.
+pub fn rule_host_can_delete_group(ctx: &RuleContext) -> Result<(), RuleError> {
+    host_has_permission(
+        ctx,
+        PermissionType::DeleteGroup,
+        RuleError::NotAllowedToDeleteGroup,
+    )
 }
 
 /// Check if the group name is in a valid format
@@ -9,6 +9,8 @@ pub struct Packs {
     pub postgres_url: String,
     pub domain: String,
     pub catcher: Option<String>,
+    pub token_secret: String,
+    pub templates: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -17,12 +19,23 @@ pub struct Basket {
     pub basket_url: Url,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct Smtp {
+    pub host: String,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    #[serde(default)]
+    pub tls: bool,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Settings {
     pub auth: String,
     pub cis: CisSettings,
     pub packs: Packs,
     pub basket: Option<Basket>,
+    pub smtp: Option<Smtp>,
 }
 
 impl Settings {
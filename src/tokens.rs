@@ -0,0 +1,207 @@
+use crate::db::types::NotificationCategoryType;
+use crate::error::PacksError;
+use hmac::Hmac;
+use hmac::Mac;
+use hmac::NewMac;
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Key all tokens are signed with. It is read once from the settings on startup and handed to
+/// the `MailMan` for signing and to the handlers verifying tokens as `web::Data`.
+#[derive(Clone)]
+pub struct TokenSecret(Vec<u8>);
+
+impl TokenSecret {
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        TokenSecret(secret.into())
+    }
+}
+
+/// Signed reference to a single invitation used in invitation links. The nonce is stored with
+/// the invitation, so a token is only valid as long as the invitation it was issued for.
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvitationToken {
+    pub group_id: i32,
+    pub user_uuid: Uuid,
+    pub nonce: Uuid,
+}
+
+/// Signed reference to a notification category of a single user used in unsubscribe links.
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnsubscribeToken {
+    pub user_uuid: Uuid,
+    pub category: NotificationCategoryType,
+}
+
+fn mac(secret: &TokenSecret, payload: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(&secret.0).expect("HMAC takes keys of any size");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+fn sign(secret: &TokenSecret, payload: String) -> String {
+    let signature = mac(secret, &payload).finalize().into_bytes();
+    format!(
+        "{}.{}",
+        payload,
+        base64::encode_config(signature, base64::URL_SAFE_NO_PAD)
+    )
+}
+
+/// Returns the payload of `token` if its signature is valid.
+fn verified_payload<'a>(secret: &TokenSecret, token: &'a str) -> Option<&'a str> {
+    let mut parts = token.rsplitn(2, '.');
+    let (signature, payload) = match (parts.next(), parts.next()) {
+        (Some(signature), Some(payload)) => (signature, payload),
+        _ => return None,
+    };
+    let signature = base64::decode_config(signature, base64::URL_SAFE_NO_PAD).ok()?;
+    mac(secret, payload).verify(&signature).ok()?;
+    Some(payload)
+}
+
+impl InvitationToken {
+    fn payload(&self) -> String {
+        format!(
+            "{}.{}.{}",
+            self.group_id,
+            self.user_uuid.to_simple(),
+            self.nonce.to_simple()
+        )
+    }
+
+    pub fn sign(&self, secret: &TokenSecret) -> String {
+        sign(secret, self.payload())
+    }
+
+    pub fn verify(token: &str, secret: &TokenSecret) -> Result<Self, PacksError> {
+        let payload = verified_payload(secret, token).ok_or(PacksError::InvalidInvitationToken)?;
+        let mut fields = payload.splitn(3, '.');
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some(group_id), Some(user_uuid), Some(nonce)) => Ok(InvitationToken {
+                group_id: group_id
+                    .parse()
+                    .map_err(|_| PacksError::InvalidInvitationToken)?,
+                user_uuid: Uuid::parse_str(user_uuid)
+                    .map_err(|_| PacksError::InvalidInvitationToken)?,
+                nonce: Uuid::parse_str(nonce).map_err(|_| PacksError::InvalidInvitationToken)?,
+            }),
+            _ => Err(PacksError::InvalidInvitationToken),
+        }
+    }
+}
+
+impl UnsubscribeToken {
+    fn payload(&self) -> String {
+        format!("{}.{}", self.user_uuid.to_simple(), self.category.name())
+    }
+
+    pub fn sign(&self, secret: &TokenSecret) -> String {
+        sign(secret, self.payload())
+    }
+
+    pub fn verify(token: &str, secret: &TokenSecret) -> Result<Self, PacksError> {
+        let payload = verified_payload(secret, token).ok_or(PacksError::InvalidUnsubscribeToken)?;
+        let mut fields = payload.splitn(2, '.');
+        match (fields.next(), fields.next()) {
+            (Some(user_uuid), Some(category)) => Ok(UnsubscribeToken {
+                user_uuid: Uuid::parse_str(user_uuid)
+                    .map_err(|_| PacksError::InvalidUnsubscribeToken)?,
+                category: NotificationCategoryType::all()
+                    .into_iter()
+                    .find(|c| c.name() == category)
+                    .ok_or(PacksError::InvalidUnsubscribeToken)?,
+            }),
+            _ => Err(PacksError::InvalidUnsubscribeToken),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn secret() -> TokenSecret {
+        TokenSecret::new("dino-park-packs-test")
+    }
+
+    #[test]
+    fn test_invitation_token_roundtrip() {
+        let token = InvitationToken {
+            group_id: 23,
+            user_uuid: Uuid::new_v4(),
+            nonce: Uuid::new_v4(),
+        };
+        assert_eq!(
+            InvitationToken::verify(&token.sign(&secret()), &secret()),
+            Ok(token)
+        );
+    }
+
+    #[test]
+    fn test_tampered_invitation_token() {
+        let token = InvitationToken {
+            group_id: 23,
+            user_uuid: Uuid::new_v4(),
+            nonce: Uuid::new_v4(),
+        }
+        .sign(&secret());
+        let tampered = token.replacen("23.", "24.", 1);
+        assert_eq!(
+            InvitationToken::verify(&tampered, &secret()),
+            Err(PacksError::InvalidInvitationToken)
+        );
+        assert_eq!(
+            InvitationToken::verify("garbage", &secret()),
+            Err(PacksError::InvalidInvitationToken)
+        );
+        let other = InvitationToken {
+            group_id: 23,
+            user_uuid: Uuid::new_v4(),
+            nonce: Uuid::new_v4(),
+        }
+        .sign(&TokenSecret::new("another-secret"));
+        assert_eq!(
+            InvitationToken::verify(&other, &secret()),
+            Err(PacksError::InvalidInvitationToken)
+        );
+    }
+
+    #[test]
+    fn test_unsubscribe_token_roundtrip() {
+        let token = UnsubscribeToken {
+            user_uuid: Uuid::new_v4(),
+            category: NotificationCategoryType::Requests,
+        };
+        assert_eq!(
+            UnsubscribeToken::verify(&token.sign(&secret()), &secret()),
+            Ok(token)
+        );
+    }
+
+    #[test]
+    fn test_tampered_unsubscribe_token() {
+        let token = UnsubscribeToken {
+            user_uuid: Uuid::new_v4(),
+            category: NotificationCategoryType::Requests,
+        }
+        .sign(&secret());
+        let tampered = token.replacen(".requests.", ".invitations.", 1);
+        assert_eq!(
+            UnsubscribeToken::verify(&tampered, &secret()),
+            Err(PacksError::InvalidUnsubscribeToken)
+        );
+        let invitation = InvitationToken {
+            group_id: 23,
+            user_uuid: Uuid::new_v4(),
+            nonce: Uuid::new_v4(),
+        }
+        .sign(&secret());
+        assert_eq!(
+            UnsubscribeToken::verify(&invitation, &secret()),
+            Err(PacksError::InvalidUnsubscribeToken)
+        );
+    }
+}
@@ -17,6 +17,21 @@ pub fn valid_group_name(group_name: &str) -> bool {
             .all(|c| (c.is_ascii_lowercase() || c.is_ascii_digit()) || c == '-' || c == '_')
 }
 
+pub const ROOT_GROUP_PATH: &str = "/";
+
+/// The path of groups created below the group `name` living at `path`.
+pub fn child_path(path: &str, name: &str) -> String {
+    format!("{}{}/", path, name)
+}
+
+/// The names of all parent groups for a group living at `path`, starting at the root.
+pub fn breadcrumb(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
 pub fn maybe_to_utc<S>(naive: &Option<NaiveDateTime>, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -49,6 +64,14 @@ mod test {
         assert!(!valid_group_name("gg"));
     }
 
+    #[test]
+    fn test_breadcrumb() {
+        assert!(breadcrumb(ROOT_GROUP_PATH).is_empty());
+        let path = child_path(&child_path(ROOT_GROUP_PATH, "mozilla"), "it");
+        assert_eq!(path, "/mozilla/it/");
+        assert_eq!(breadcrumb(&path), vec!["mozilla", "it"]);
+    }
+
     #[test]
     fn test_to_utc() -> Result<(), Error> {
         #[derive(Serialize)]
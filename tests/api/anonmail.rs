@@ -1,13 +1,15 @@
 use crate::helpers::api::*;
 use crate::helpers::db::get_pool;
 use crate::helpers::db::reset;
-use crate::helpers::misc::test_app_and_cis;
+use crate::helpers::mail::mail_man;
+use crate::helpers::misc::test_app_cis_and_mail;
 use crate::helpers::misc::Soa;
 use crate::helpers::users::basic_user;
 use crate::helpers::users::user_uuid;
 use actix_web::test;
 use actix_web::App;
 use cis_profile::schema::Display;
+use dino_park_packs::capabilities::Providers;
 use dino_park_packs::db::operations::members::get_anonymous_member_emails;
 use dino_park_packs::db::operations::users::update_user_cache;
 use failure::Error;
@@ -17,7 +19,9 @@ use std::sync::Arc;
 #[actix_rt::test]
 async fn hidden_member_emails() -> Result<(), Error> {
     reset()?;
-    let (service, cis_client) = test_app_and_cis().await;
+    let (service, cis_client, recorder) = test_app_cis_and_mail().await;
+    let mail_man = mail_man(&recorder);
+    let providers = Providers::default();
     let cis_client = Arc::new(cis_client);
     let app = App::new().service(service);
     let mut app = test::init_service(app).await;
@@ -60,33 +64,68 @@ async fn hidden_member_emails() -> Result<(), Error> {
     assert!(res.status().is_success());
 
     user3.primary_email.metadata.display = Some(Display::Staff);
-    update_user_cache(&pool, &user3, Arc::clone(&cis_client)).await?;
+    update_user_cache(
+        &pool,
+        &user3,
+        Arc::clone(&cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
     let emails = get_anonymous_member_emails(&pool, &admin.clone().into())?;
     assert_eq!(emails, vec![String::from("hans11@knall.org")]);
 
     user3.primary_email.metadata.display = Some(Display::Ndaed);
-    update_user_cache(&pool, &user3, Arc::clone(&cis_client)).await?;
+    update_user_cache(
+        &pool,
+        &user3,
+        Arc::clone(&cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
     let emails = get_anonymous_member_emails(&pool, &admin.clone().into())?;
     assert!(emails.is_empty());
 
     user3.primary_email.metadata.display = Some(Display::Ndaed);
     user3.first_name.metadata.display = Some(Display::Staff);
     user3.last_name.metadata.display = Some(Display::Staff);
-    update_user_cache(&pool, &user3, Arc::clone(&cis_client)).await?;
+    update_user_cache(
+        &pool,
+        &user3,
+        Arc::clone(&cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
     let emails = get_anonymous_member_emails(&pool, &admin.clone().into())?;
     assert_eq!(emails, vec![String::from("hans11@knall.org")]);
 
     user3.primary_email.metadata.display = Some(Display::Ndaed);
     user3.first_name.metadata.display = Some(Display::Ndaed);
     user3.last_name.metadata.display = Some(Display::Staff);
-    update_user_cache(&pool, &user3, Arc::clone(&cis_client)).await?;
+    update_user_cache(
+        &pool,
+        &user3,
+        Arc::clone(&cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
     let emails = get_anonymous_member_emails(&pool, &admin.clone().into())?;
     assert!(emails.is_empty());
 
     user3.primary_email.metadata.display = Some(Display::Ndaed);
     user3.first_name.metadata.display = Some(Display::Staff);
     user3.last_name.metadata.display = Some(Display::Ndaed);
-    update_user_cache(&pool, &user3, Arc::clone(&cis_client)).await?;
+    update_user_cache(
+        &pool,
+        &user3,
+        Arc::clone(&cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
     let emails = get_anonymous_member_emails(&pool, &admin.into())?;
     assert!(emails.is_empty());
 
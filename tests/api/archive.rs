@@ -0,0 +1,143 @@
+use crate::helpers::api::*;
+use crate::helpers::db::reset;
+use crate::helpers::misc::read_json;
+use crate::helpers::misc::test_app;
+use crate::helpers::misc::Soa;
+use crate::helpers::users::basic_user;
+use crate::helpers::users::user_uuid;
+use actix_web::test;
+use actix_web::App;
+use failure::Error;
+use serde_json::json;
+
+#[actix_rt::test]
+async fn archive_and_reactivate() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let member = Soa::from(&basic_user(2, true)).aal_medium();
+    let admin = Soa::from(&basic_user(4, true)).admin().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/sudo/groups/reserve/archive-test",
+        json!({}),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    for name in &["archive-kept", "archive-curated"] {
+        let res = post(
+            &mut app,
+            "/groups/api/v1/groups",
+            json!({ "name": name, "description": "a group" }),
+            &host,
+        )
+        .await;
+        assert!(res.status().is_success());
+    }
+
+    for name in &["archive-kept", "archive-test"] {
+        let res = post(
+            &mut app,
+            &format!("/groups/api/v1/sudo/member/{}", name),
+            json!({ "user_uuid": user_uuid(&basic_user(2, true)) }),
+            &admin,
+        )
+        .await;
+        assert!(res.status().is_success());
+    }
+
+    let res = post(
+        &mut app,
+        "/internal/archive/groups?days=0",
+        json!({}),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j["flagged"], 1);
+    assert_eq!(j["archived"], 0);
+
+    let res = post(
+        &mut app,
+        "/internal/archive/groups?days=0",
+        json!({}),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j["archived"], 1);
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/groups/archive-test/details",
+        &host,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    for name in &["archive-kept", "archive-curated"] {
+        let res = get(
+            &mut app,
+            &format!("/groups/api/v1/groups/{}/details", name),
+            &host,
+        )
+        .await;
+        assert!(res.status().is_success());
+    }
+
+    // reactivation is left to admins, neither the warned members nor other curators may do it
+    for non_admin in &[&member, &host] {
+        let res = post(
+            &mut app,
+            "/groups/api/v1/groups/archive-test/reactivate",
+            json!({}),
+            non_admin,
+        )
+        .await;
+        assert_eq!(res.status().as_u16(), 403);
+    }
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/groups/archive-test/details",
+        &host,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups/archive-test/reactivate",
+        json!({}),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/groups/archive-test/details",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups/archive-kept/reactivate",
+        json!({}),
+        &host,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    Ok(())
+}
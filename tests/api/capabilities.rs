@@ -0,0 +1,83 @@
+use crate::helpers::api::*;
+use crate::helpers::db::reset;
+use crate::helpers::misc::test_app_with_providers;
+use crate::helpers::misc::Soa;
+use crate::helpers::users::basic_user;
+use crate::helpers::users::user_uuid;
+use actix_web::test;
+use actix_web::App;
+use dino_park_packs::capabilities::local::LocalProvider;
+use dino_park_packs::capabilities::MembershipChange;
+use dino_park_packs::capabilities::Providers;
+use dino_park_packs::db::types::CapabilityType;
+use failure::Error;
+use serde_json::json;
+use std::sync::Arc;
+
+#[actix_rt::test]
+async fn capability_provider_sync() -> Result<(), Error> {
+    reset()?;
+    let provider = LocalProvider::new(CapabilityType::Gdrive);
+    let mut providers = Providers::default();
+    providers.register(Arc::new(provider.clone()));
+    let app = App::new().service(test_app_with_providers(providers).await.0);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let member_user = basic_user(2, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let admin = Soa::from(&basic_user(3, true)).admin().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "gdrive-test", "description": "a group", "capabilities": ["Gdrive"] }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "plain-test", "description": "a group" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    for group_name in &["gdrive-test", "plain-test"] {
+        let res = post(
+            &mut app,
+            &format!("/groups/api/v1/sudo/member/{}", group_name),
+            json!({ "user_uuid": user_uuid(&member_user) }),
+            &admin,
+        )
+        .await;
+        assert!(res.status().is_success());
+    }
+
+    let events = provider.events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].group_name, "gdrive-test");
+    assert_eq!(events[0].user_uuid.to_string(), user_uuid(&member_user));
+    assert_eq!(events[0].change, MembershipChange::Added);
+
+    let res = delete(
+        &mut app,
+        &format!(
+            "/groups/api/v1/members/gdrive-test/{}",
+            user_uuid(&member_user)
+        ),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let events = provider.events();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].group_name, "gdrive-test");
+    assert_eq!(events[1].change, MembershipChange::Removed);
+
+    Ok(())
+}
@@ -1,12 +1,20 @@
 use crate::helpers::api::*;
+use crate::helpers::db::get_pool;
 use crate::helpers::db::reset;
+use crate::helpers::mail::wait_for_emails;
 use crate::helpers::misc::read_json;
 use crate::helpers::misc::test_app;
+use crate::helpers::misc::test_app_and_mail;
 use crate::helpers::misc::Soa;
 use crate::helpers::users::basic_user;
+use crate::helpers::users::user_email;
 use crate::helpers::users::user_uuid;
 use actix_web::test;
 use actix_web::App;
+use chrono::Duration;
+use chrono::Utc;
+use diesel::prelude::*;
+use dino_park_packs::db::schema;
 use failure::Error;
 use serde_json::json;
 
@@ -65,3 +73,329 @@ async fn revoke_nda() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[actix_rt::test]
+async fn self_renewal() -> Result<(), Error> {
+    reset()?;
+    let service = test_app().await;
+    let app = App::new().service(service);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let member_user = basic_user(2, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let member = Soa::from(&member_user).aal_medium();
+    let admin = Soa::from(&basic_user(4, true)).admin().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "renew-test", "description": "a group", "group_expiration": 30 }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/sudo/member/renew-test",
+        json!({ "user_uuid": user_uuid(&member_user), "group_expiration": 1 }),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/renew-test/renew",
+        json!({}),
+        &member,
+    )
+    .await;
+    assert!(!res.status().is_success());
+    assert_eq!(read_json(res).await["error"], "self_renewal_disabled");
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/groups/renew-test",
+        json!({ "self_renewal_days": 7, "max_membership_days": 10 }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/groups/renew-test/details",
+        &member,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await["group"]["self_renewal_days"], 7);
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/renew-test/renew",
+        json!({}),
+        &member,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert!(read_json(res).await["expiration"].is_string());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/renew-test/renew",
+        json!({}),
+        &member,
+    )
+    .await;
+    assert!(!res.status().is_success());
+    assert_eq!(
+        read_json(res).await["error"],
+        "self_renewal_not_yet_possible"
+    );
+
+    // a window longer than the group expiration must not allow renewing over and over
+    let res = put(
+        &mut app,
+        "/groups/api/v1/groups/renew-test",
+        json!({ "self_renewal_days": 40, "max_membership_days": null }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/renew-test/renew",
+        json!({}),
+        &member,
+    )
+    .await;
+    assert!(!res.status().is_success());
+    assert_eq!(read_json(res).await["error"], "already_self_renewed");
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn bulk_renewal() -> Result<(), Error> {
+    reset()?;
+    let service = test_app().await;
+    let app = App::new().service(service);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let member_user_1 = basic_user(2, true);
+    let member_user_2 = basic_user(3, true);
+    let outsider_user = basic_user(5, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let member = Soa::from(&member_user_1).aal_medium();
+    let admin = Soa::from(&basic_user(4, true)).admin().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "bulk-renew-test", "description": "a group" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    for user in &[&member_user_1, &member_user_2] {
+        let res = post(
+            &mut app,
+            "/groups/api/v1/sudo/member/bulk-renew-test",
+            json!({ "user_uuid": user_uuid(user), "group_expiration": 1 }),
+            &admin,
+        )
+        .await;
+        assert!(res.status().is_success());
+    }
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/members/bulk-renew-test/renew",
+        json!({ "user_uuids": [user_uuid(&member_user_2)], "group_expiration": 0 }),
+        &member,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 403);
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/members/bulk-renew-test/renew",
+        json!({ "group_expiration": 0 }),
+        &member,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 403);
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/members/bulk-renew-test/renew",
+        json!({
+            "user_uuids": [user_uuid(&member_user_2)],
+            "expires_before": Utc::now().to_rfc3339(),
+            "group_expiration": 0
+        }),
+        &host,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 400);
+    assert_eq!(read_json(res).await["error"], "ambiguous_bulk_renewal");
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/members/bulk-renew-test/renew",
+        json!({
+            "user_uuids": [user_uuid(&member_user_1), user_uuid(&outsider_user)],
+            "group_expiration": 0
+        }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j[0]["renewed"], true);
+    assert_eq!(j[1]["renewed"], false);
+    assert!(j[1]["error"].is_string());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/members/bulk-renew-test/renew",
+        json!({ "group_expiration": 0 }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j.as_array().map(|r| r.len()), Some(1));
+    assert_eq!(j[0]["user_uuid"], user_uuid(&member_user_2));
+    assert_eq!(j[0]["renewed"], true);
+
+    // admins pass the rules for anyone, failing members are still reported one by one
+    let res = post(
+        &mut app,
+        "/groups/api/v1/members/bulk-renew-test/renew",
+        json!({
+            "user_uuids": [user_uuid(&outsider_user), user_uuid(&member_user_1)],
+            "group_expiration": 0
+        }),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j[0]["renewed"], false);
+    assert_eq!(j[0]["error"], "not_a_member");
+    assert_eq!(j[1]["renewed"], true);
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/members/bulk-renew-test?r=Member",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let members = read_json(res).await;
+    assert_eq!(members["members"].as_array().map(|a| a.len()), Some(2));
+    assert_eq!(members["members"][0]["expiration"].as_null(), Some(()));
+    assert_eq!(members["members"][1]["expiration"].as_null(), Some(()));
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn expiration_emails() -> Result<(), Error> {
+    reset()?;
+    let (service, recorder) = test_app_and_mail().await;
+    let app = App::new().service(service);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let member_user_1 = basic_user(2, true);
+    let member_user_2 = basic_user(3, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let admin = Soa::from(&basic_user(4, true)).admin().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "exp-mail-test", "description": "a group" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    for (user, days) in &[(&member_user_1, 14), (&member_user_2, 7)] {
+        let res = post(
+            &mut app,
+            "/groups/api/v1/sudo/member/exp-mail-test",
+            json!({ "user_uuid": user_uuid(user), "group_expiration": days }),
+            &admin,
+        )
+        .await;
+        assert!(res.status().is_success());
+    }
+
+    recorder.clear();
+    let res = post(&mut app, "/internal/notify/expiration", json!({}), &host).await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j["expire_first"], 1);
+    assert_eq!(j["expire_second"], 1);
+
+    // both host notifications go to the curators, the member is only notified the second time
+    let emails = wait_for_emails(&recorder, 3).await;
+    assert_eq!(emails.len(), 3);
+    let host_emails = emails
+        .iter()
+        .filter(|e| e.template.as_deref() == Some("host_expiration"))
+        .collect::<Vec<_>>();
+    assert_eq!(host_emails.len(), 2);
+    assert!(host_emails
+        .iter()
+        .all(|e| e.to == Some(user_email(&host_user))));
+    let member_emails = emails
+        .iter()
+        .filter(|e| e.template.as_deref() == Some("member_expiration"))
+        .collect::<Vec<_>>();
+    assert_eq!(member_emails.len(), 1);
+    assert_eq!(member_emails[0].to, Some(user_email(&member_user_2)));
+
+    {
+        use schema::memberships as m;
+        let connection = get_pool().get()?;
+        let user_uuid = uuid::Uuid::parse_str(&user_uuid(&member_user_2))?;
+        diesel::update(m::table.filter(m::user_uuid.eq(user_uuid)))
+            .set(m::expiration.eq((Utc::now() - Duration::minutes(1)).naive_utc()))
+            .execute(&connection)?;
+    }
+
+    recorder.clear();
+    let res = post(&mut app, "/internal/expire/all", json!({}), &host).await;
+    assert!(res.status().is_success());
+
+    let emails = wait_for_emails(&recorder, 1).await;
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].to, Some(user_email(&member_user_2)));
+    assert_eq!(emails[0].template.as_deref(), Some("delete_member"));
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/members/exp-mail-test?r=Member",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let members = read_json(res).await;
+    assert_eq!(members["members"].as_array().map(|a| a.len()), Some(1));
+    assert_eq!(
+        members["members"][0]["user_uuid"],
+        user_uuid(&member_user_1)
+    );
+
+    Ok(())
+}
@@ -1,8 +1,9 @@
 use crate::helpers::api::*;
 use crate::helpers::db::get_pool;
 use crate::helpers::db::reset;
+use crate::helpers::mail::mail_man;
 use crate::helpers::misc::read_json;
-use crate::helpers::misc::test_app_and_cis;
+use crate::helpers::misc::test_app_cis_and_mail;
 use crate::helpers::misc::Soa;
 use crate::helpers::sudo::add_to_group;
 use crate::helpers::users::basic_user;
@@ -11,6 +12,7 @@ use actix_web::test;
 use actix_web::App;
 use cis_client::getby::GetBy;
 use cis_client::AsyncCisClientTrait;
+use dino_park_packs::capabilities::Providers;
 use dino_park_packs::db::operations::users::_update_user_cache;
 use dino_park_packs::db::operations::users::update_user_cache;
 use failure::Error;
@@ -20,7 +22,9 @@ use std::sync::Arc;
 #[actix_rt::test]
 async fn update_inactive() -> Result<(), Error> {
     reset()?;
-    let (service, cis_client) = test_app_and_cis().await;
+    let (service, cis_client, recorder) = test_app_cis_and_mail().await;
+    let mail_man = mail_man(&recorder);
+    let providers = Providers::default();
     let cis_client = Arc::new(cis_client);
     let app = App::new().service(service);
     let mut app = test::init_service(app).await;
@@ -53,7 +57,14 @@ async fn update_inactive() -> Result<(), Error> {
 
     let pool = get_pool();
     staff_user_2.active.value = Some(false);
-    update_user_cache(&pool, &staff_user_2, Arc::clone(&cis_client)).await?;
+    update_user_cache(
+        &pool,
+        &staff_user_2,
+        Arc::clone(&cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
 
     let res = get(&mut app, "/groups/api/v1/members/inactive-test", &host).await;
     assert!(res.status().is_success());
@@ -61,14 +72,28 @@ async fn update_inactive() -> Result<(), Error> {
     assert_eq!(members["members"].as_array().map(|a| a.len()), Some(2));
 
     // updating an inactive profile must not fail
-    update_user_cache(&pool, &staff_user_2, Arc::clone(&cis_client)).await?;
+    update_user_cache(
+        &pool,
+        &staff_user_2,
+        Arc::clone(&cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
 
     let mut staff_user_2_reactivated = cis_client
         .get_user_by(&user_id(&staff_user_2), &GetBy::Uuid, None)
         .await?;
     // enabling a user again with groups resets groups to db state
     staff_user_2_reactivated.active.value = Some(true);
-    update_user_cache(&pool, &staff_user_2_reactivated, Arc::clone(&cis_client)).await?;
+    update_user_cache(
+        &pool,
+        &staff_user_2_reactivated,
+        Arc::clone(&cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
     assert_eq!(
         cis_client
             .get_user_by(&user_id(&staff_user_2), &GetBy::Uuid, None)
@@ -0,0 +1,238 @@
+use crate::helpers::api::*;
+use crate::helpers::cis::CisFakeClient;
+use crate::helpers::db::reset;
+use crate::helpers::misc::read_json;
+use crate::helpers::misc::test_app_and_cis;
+use crate::helpers::misc::Soa;
+use crate::helpers::users::basic_user;
+use crate::helpers::users::user_id;
+use crate::helpers::users::user_uuid;
+use actix_web::test;
+use actix_web::App;
+use cis_client::getby::GetBy;
+use cis_client::AsyncCisClientTrait;
+use cis_profile::schema::Profile;
+use failure::Error;
+use serde_json::json;
+use std::sync::Arc;
+
+async fn cis_groups(cis_client: &CisFakeClient, user: &Profile) -> Vec<String> {
+    let profile = cis_client
+        .get_user_by(&user_id(user), &GetBy::UserId, None)
+        .await
+        .unwrap();
+    let mut groups = profile
+        .access_information
+        .mozilliansorg
+        .values
+        .map(|kv| kv.0.keys().cloned().collect::<Vec<String>>())
+        .unwrap_or_default();
+    groups.sort();
+    groups
+}
+
+#[actix_rt::test]
+async fn nested_groups() -> Result<(), Error> {
+    reset()?;
+    let (service, cis_client) = test_app_and_cis().await;
+    let cis_client = Arc::new(cis_client);
+    let app = App::new().service(service);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let member_user = basic_user(2, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let admin = Soa::from(&basic_user(3, true)).admin().aal_medium();
+
+    for group_name in &["all-tooling", "team-a", "team-b"] {
+        let res = post(
+            &mut app,
+            "/groups/api/v1/groups",
+            json!({ "name": group_name, "description": "a group" }),
+            &host,
+        )
+        .await;
+        assert!(res.status().is_success());
+    }
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/sudo/member/team-b",
+        json!({ "user_uuid": user_uuid(&member_user) }),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups/all-tooling/includes",
+        json!({ "group_name": "team-a" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups/team-a/includes",
+        json!({ "group_name": "team-b" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups/team-b/includes",
+        json!({ "group_name": "all-tooling" }),
+        &host,
+    )
+    .await;
+    assert!(!res.status().is_success());
+    assert_eq!(read_json(res).await["error"], "group_include_cycle");
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/groups/all-tooling/includes",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await, json!(["team-a"]));
+
+    assert_eq!(
+        cis_groups(&cis_client, &member_user).await,
+        vec!["all-tooling", "team-a", "team-b"]
+    );
+
+    let res = delete(
+        &mut app,
+        "/groups/api/v1/groups/all-tooling/includes/team-a",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    assert_eq!(
+        cis_groups(&cis_client, &member_user).await,
+        vec!["team-a", "team-b"]
+    );
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn inherited_members_need_admission() -> Result<(), Error> {
+    reset()?;
+    let (service, cis_client) = test_app_and_cis().await;
+    let cis_client = Arc::new(cis_client);
+    let app = App::new().service(service);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let member_user = basic_user(2, true);
+    let child_host_user = basic_user(4, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let child_host = Soa::from(&child_host_user).creator().aal_medium();
+    let admin = Soa::from(&basic_user(3, true)).admin().aal_medium();
+
+    for (group_name, creator) in &[
+        ("inherit-parent", &host),
+        ("inherit-required", &host),
+        ("inherit-child", &child_host),
+    ] {
+        let res = post(
+            &mut app,
+            "/groups/api/v1/groups",
+            json!({ "name": group_name, "description": "a group" }),
+            creator,
+        )
+        .await;
+        assert!(res.status().is_success());
+    }
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/rules/inherit-parent",
+        json!({ "type": "Group", "payload": "inherit-required" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let rule_id = read_json(res).await["id"].as_i64().unwrap();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/sudo/member/inherit-child",
+        json!({ "user_uuid": user_uuid(&member_user) }),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups/inherit-parent/includes",
+        json!({ "group_name": "inherit-child" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    assert_eq!(
+        cis_groups(&cis_client, &member_user).await,
+        vec!["inherit-child"]
+    );
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/sudo/member/inherit-required",
+        json!({ "user_uuid": user_uuid(&child_host_user) }),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    assert_eq!(
+        cis_groups(&cis_client, &child_host_user).await,
+        vec!["inherit-child", "inherit-parent", "inherit-required"]
+    );
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/members/inherit-parent?e=true&r=Member",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let members = read_json(res).await;
+    assert_eq!(members["members"].as_array().map(|a| a.len()), Some(1));
+    assert_eq!(
+        members["members"][0]["user_uuid"],
+        user_uuid(&child_host_user)
+    );
+    assert_eq!(members["members"][0]["role"], "Member");
+
+    let res = get(&mut app, "/groups/api/v1/groups?by=NameAsc", &host).await;
+    assert!(res.status().is_success());
+    let groups = read_json(res).await;
+    assert_eq!(groups["groups"][1]["name"], "inherit-parent");
+    assert_eq!(groups["groups"][1]["member_count"], 2);
+
+    // dropping the rule admits the other inherited member right away
+    let res = delete(
+        &mut app,
+        &format!("/groups/api/v1/rules/inherit-parent/{}", rule_id),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    assert_eq!(
+        cis_groups(&cis_client, &member_user).await,
+        vec!["inherit-child", "inherit-parent"]
+    );
+
+    Ok(())
+}
@@ -1,14 +1,26 @@
 use crate::helpers::api::*;
+use crate::helpers::db::get_pool;
 use crate::helpers::db::reset;
+use crate::helpers::mail::wait_for_emails;
 use crate::helpers::misc::read_json;
 use crate::helpers::misc::test_app;
+use crate::helpers::misc::test_app_and_cis;
+use crate::helpers::misc::test_app_and_mail;
+use crate::helpers::misc::token_secret;
 use crate::helpers::misc::Soa;
 use crate::helpers::users::basic_user;
+use crate::helpers::users::user_email;
+use crate::helpers::users::user_id;
 use crate::helpers::users::user_uuid;
 use actix_web::test;
 use actix_web::App;
+use cis_client::AsyncCisClientTrait;
+use diesel::prelude::*;
+use dino_park_packs::db::schema;
+use dino_park_packs::tokens::InvitationToken;
 use failure::Error;
 use serde_json::json;
+use uuid::Uuid;
 
 #[actix_rt::test]
 async fn invite_order() -> Result<(), Error> {
@@ -122,3 +134,412 @@ async fn invitation_text() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[actix_rt::test]
+async fn bulk_invite() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let invite_user_1 = basic_user(2, true);
+    let invite_user_2 = basic_user(3, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "bulk-invite-test", "description": "a group" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let csv = "user,group_expiration\nHans2,\nHans3@Knall.org,30\nnobody,\nHans1,";
+
+    let res = post_multipart(
+        &mut app,
+        "/groups/api/v1/invitations/bulk-invite-test/bulk?dry_run=true",
+        "invitations",
+        csv,
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let report = read_json(res).await;
+    assert_eq!(report["dry_run"], true);
+    assert_eq!(report["results"][0]["status"], "invited");
+    assert_eq!(report["results"][0]["user_uuid"], user_uuid(&invite_user_1));
+    assert_eq!(report["results"][1]["status"], "invited");
+    assert_eq!(report["results"][1]["user_uuid"], user_uuid(&invite_user_2));
+    assert_eq!(report["results"][2]["status"], "not_found");
+    assert_eq!(report["results"][3]["status"], "already_member");
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/invitations/bulk-invite-test",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await.as_array().map(|a| a.len()), Some(0));
+
+    let res = post_multipart(
+        &mut app,
+        "/groups/api/v1/invitations/bulk-invite-test/bulk",
+        "invitations",
+        &csv.replace(',', "\t"),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let report = read_json(res).await;
+    assert_eq!(report["dry_run"], false);
+    assert_eq!(report["results"][0]["status"], "invited");
+    assert_eq!(report["results"][1]["status"], "invited");
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/invitations/bulk-invite-test",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await.as_array().map(|a| a.len()), Some(2));
+
+    let too_many_rows = (0..1001).fold(String::from("user\n"), |mut csv, i| {
+        csv.push_str(&format!("nobody{}\n", i));
+        csv
+    });
+    let too_large = format!("user\n{}", "x".repeat(513 * 1024));
+    for csv in &[too_many_rows, too_large] {
+        let res = post_multipart(
+            &mut app,
+            "/groups/api/v1/invitations/bulk-invite-test/bulk?dry_run=true",
+            "invitations",
+            csv,
+            &host,
+        )
+        .await;
+        assert_eq!(res.status().as_u16(), 400);
+        assert_eq!(read_json(res).await["error"], "input_to_long");
+    }
+
+    let member = Soa::from(&invite_user_1).aal_medium();
+    let res = post_multipart(
+        &mut app,
+        "/groups/api/v1/invitations/bulk-invite-test/bulk",
+        "invitations",
+        csv,
+        &member,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 403);
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn invite_by_email() -> Result<(), Error> {
+    reset()?;
+    let (service, cis_client) = test_app_and_cis().await;
+    let app = App::new().service(service);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let existing_user = basic_user(2, true);
+    let new_user = basic_user(42, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "email-invite-test", "description": "a group" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/email-invite-test/by-email",
+        json!({ "email": "not an email" }),
+        &host,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 400);
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/email-invite-test/by-email",
+        json!({ "email": "Hans42@knall.org", "invitation_expiration": 7 }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/email-invite-test/by-email",
+        json!({ "email": "hans2@knall.org" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/invitations/email-invite-test/by-email",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let email_invitations = read_json(res).await;
+    assert_eq!(email_invitations.as_array().map(|a| a.len()), Some(1));
+    assert_eq!(email_invitations[0]["email"], "hans42@knall.org");
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/invitations/email-invite-test",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let invitations = read_json(res).await;
+    assert_eq!(invitations.as_array().map(|a| a.len()), Some(1));
+    assert_eq!(invitations[0]["user_uuid"], user_uuid(&existing_user));
+
+    cis_client
+        .update_user(&user_id(&new_user), new_user.clone())
+        .await
+        .unwrap();
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/invitations/email-invite-test/by-email",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await.as_array().map(|a| a.len()), Some(0));
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/invitations/email-invite-test",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await.as_array().map(|a| a.len()), Some(2));
+
+    let new = Soa::from(&new_user).aal_medium();
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/invitations/email-invite-test",
+        json!({}),
+        &new,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    Ok(())
+}
+
+fn invitation_token(group_name: &str, user_uuid: &str) -> Result<String, Error> {
+    let connection = get_pool().get()?;
+    let user_uuid = Uuid::parse_str(user_uuid)?;
+    let (group_id, nonce) = schema::invitations::table
+        .inner_join(schema::groups::table)
+        .filter(schema::groups::name.eq(group_name))
+        .filter(schema::invitations::user_uuid.eq(user_uuid))
+        .select((
+            schema::invitations::group_id,
+            schema::invitations::token_nonce,
+        ))
+        .first::<(i32, Option<Uuid>)>(&connection)?;
+    Ok(InvitationToken {
+        group_id,
+        user_uuid,
+        nonce: nonce.unwrap(),
+    }
+    .sign(&token_secret()))
+}
+
+#[actix_rt::test]
+async fn accept_with_token() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let invite_user_1 = basic_user(2, true);
+    let invite_user_2 = basic_user(3, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let invited_1 = Soa::from(&invite_user_1).aal_medium();
+    let invited_2 = Soa::from(&invite_user_2).aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "token-test", "description": "a group" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    for user in &[&invite_user_1, &invite_user_2] {
+        let res = post(
+            &mut app,
+            "/groups/api/v1/invitations/token-test",
+            json!({ "user_uuid": user_uuid(user), "invitation_expiration": 7 }),
+            &host,
+        )
+        .await;
+        assert!(res.status().is_success());
+    }
+    let token_1 = invitation_token("token-test", &user_uuid(&invite_user_1))?;
+    let token_2 = invitation_token("token-test", &user_uuid(&invite_user_2))?;
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/invitations/token-test/accept",
+        json!({ "token": format!("{}x", token_1) }),
+        &invited_1,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 400);
+    assert_eq!(read_json(res).await["error"], "invalid_invitation_token");
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/invitations/token-test/accept",
+        json!({ "token": token_1 }),
+        &invited_2,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 400);
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/invitations/token-test/accept",
+        json!({ "token": token_1 }),
+        &invited_1,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/invitations/token-test/accept",
+        json!({ "token": token_1 }),
+        &invited_1,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 400);
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/terms/token-test",
+        json!({ "text": "be nice" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/invitations/token-test/accept",
+        json!({ "token": token_2 }),
+        &invited_2,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 400);
+    assert_eq!(read_json(res).await["error"], "terms_not_accepted");
+
+    let res = delete(
+        &mut app,
+        &format!(
+            "/groups/api/v1/invitations/token-test/{}",
+            user_uuid(&invite_user_2)
+        ),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/invitations/token-test/accept",
+        json!({ "token": token_2, "accepted_terms": true }),
+        &invited_2,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 400);
+    assert_eq!(read_json(res).await["error"], "invalid_invitation_token");
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn invitation_emails() -> Result<(), Error> {
+    reset()?;
+    let (service, recorder) = test_app_and_mail().await;
+    let app = App::new().service(service);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let invite_user_1 = basic_user(2, true);
+    let invite_user_2 = basic_user(3, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "invitation-mail-test", "description": "a group" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/invitation-mail-test",
+        json!({ "user_uuid": user_uuid(&invite_user_1) }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let emails = wait_for_emails(&recorder, 1).await;
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].to, Some(user_email(&invite_user_1)));
+    assert_eq!(emails[0].template.as_deref(), Some("invitation"));
+    assert!(emails[0].message.body.contains("invitation-mail-test"));
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/invitation-mail-test/email",
+        json!({ "body": "welcome to the club" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    recorder.clear();
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/invitation-mail-test",
+        json!({ "user_uuid": user_uuid(&invite_user_2) }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let emails = wait_for_emails(&recorder, 1).await;
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].to, Some(user_email(&invite_user_2)));
+    assert_eq!(emails[0].template.as_deref(), Some("invitation"));
+    assert!(emails[0].message.body.contains("welcome to the club"));
+
+    Ok(())
+}
@@ -1,5 +1,7 @@
 mod anonmail;
+mod archive;
 mod basics;
+mod capabilities;
 mod create;
 mod delete;
 mod details;
@@ -8,9 +10,17 @@ mod expiration;
 mod groups;
 mod import;
 mod inactive;
+mod includes;
 mod invitations;
 mod join;
+mod notifications;
+mod permissions;
 mod requests;
 mod revoke;
+mod roles;
+mod rules;
+mod scheduled;
+mod subgroups;
 mod sudo;
+mod terms;
 mod upgrade;
@@ -0,0 +1,173 @@
+use crate::helpers::api::*;
+use crate::helpers::db::reset;
+use crate::helpers::mail::wait_for_emails;
+use crate::helpers::misc::nobody_soa;
+use crate::helpers::misc::read_json;
+use crate::helpers::misc::test_app;
+use crate::helpers::misc::test_app_and_mail;
+use crate::helpers::misc::Soa;
+use crate::helpers::users::basic_user;
+use crate::helpers::users::user_email;
+use crate::helpers::users::user_uuid;
+use actix_web::test;
+use actix_web::App;
+use failure::Error;
+use serde_json::json;
+
+const UNSUBSCRIBE_PATH: &str = "/groups/api/v1/unsubscribe/";
+
+#[actix_rt::test]
+async fn notification_preferences() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let user = Soa::from(&basic_user(2, true));
+
+    let res = get(&mut app, "/groups/api/v1/notifications", &user).await;
+    assert!(res.status().is_success());
+    assert_eq!(
+        read_json(res).await,
+        json!({ "invitations": true, "expirations": true, "requests": true })
+    );
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/notifications",
+        json!({ "requests": false }),
+        &user,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(
+        read_json(res).await,
+        json!({ "invitations": true, "expirations": true, "requests": false })
+    );
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/notifications",
+        json!({ "expirations": false, "requests": true }),
+        &user,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(&mut app, "/groups/api/v1/notifications", &user).await;
+    assert!(res.status().is_success());
+    assert_eq!(
+        read_json(res).await,
+        json!({ "invitations": true, "expirations": false, "requests": true })
+    );
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn unsubscribe_from_invitations() -> Result<(), Error> {
+    reset()?;
+    let (service, recorder) = test_app_and_mail().await;
+    let app = App::new().service(service);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let invite_user_1 = basic_user(2, true);
+    let invite_user_2 = basic_user(3, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+
+    for name in &["unsubscribe-a", "unsubscribe-b"] {
+        let res = post(
+            &mut app,
+            "/groups/api/v1/groups",
+            json!({ "name": name, "description": "a group" }),
+            &host,
+        )
+        .await;
+        assert!(res.status().is_success());
+    }
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/unsubscribe-a",
+        json!({ "user_uuid": user_uuid(&invite_user_1) }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let emails = wait_for_emails(&recorder, 1).await;
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].to, Some(user_email(&invite_user_1)));
+    assert_eq!(emails[0].template.as_deref(), Some("invitation"));
+    let token = emails[0]
+        .message
+        .body
+        .split(UNSUBSCRIBE_PATH)
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .map(String::from)
+        .expect("no unsubscribe link");
+
+    let res = get(
+        &mut app,
+        &format!("{}{}x", UNSUBSCRIBE_PATH, token),
+        &nobody_soa(),
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 400);
+    assert_eq!(read_json(res).await["error"], "invalid_unsubscribe_token");
+
+    // following the link only asks for confirmation
+    let res = get(
+        &mut app,
+        &format!("{}{}", UNSUBSCRIBE_PATH, token),
+        &nobody_soa(),
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/notifications",
+        &Soa::from(&invite_user_1),
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await["invitations"], true);
+
+    let res = post(
+        &mut app,
+        &format!("{}{}", UNSUBSCRIBE_PATH, token),
+        json!(null),
+        &nobody_soa(),
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/notifications",
+        &Soa::from(&invite_user_1),
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await["invitations"], false);
+
+    recorder.clear();
+    for user in &[&invite_user_1, &invite_user_2] {
+        let res = post(
+            &mut app,
+            "/groups/api/v1/invitations/unsubscribe-b",
+            json!({ "user_uuid": user_uuid(user) }),
+            &host,
+        )
+        .await;
+        assert!(res.status().is_success());
+    }
+
+    let emails = wait_for_emails(&recorder, 1).await;
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].to, Some(user_email(&invite_user_2)));
+
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use crate::helpers::api::*;
+use crate::helpers::db::reset;
+use crate::helpers::misc::read_json;
+use crate::helpers::misc::test_app;
+use crate::helpers::misc::Soa;
+use crate::helpers::users::basic_user;
+use crate::helpers::users::user_uuid;
+use actix_web::test;
+use actix_web::App;
+use failure::Error;
+use serde_json::json;
+
+#[actix_rt::test]
+async fn explain_invite() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let member_user = basic_user(2, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let other = Soa::from(&basic_user(3, true)).aal_medium();
+    let admin = other.clone().admin();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "explain-test", "description": "a group" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(
+        &mut app,
+        &format!(
+            "/groups/api/v1/permissions/explain-test?action=invite&member={}",
+            user_uuid(&member_user)
+        ),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j["allowed"], true);
+    assert_eq!(j["admin_override"], false);
+    assert_eq!(
+        j["rules"][0],
+        json!({ "rule": "host_can_invite", "passed": true, "error": null })
+    );
+    assert_eq!(j["rules"][1]["rule"], "member_can_join");
+
+    let res = get(
+        &mut app,
+        &format!(
+            "/groups/api/v1/permissions/explain-test?action=invite&member={}",
+            user_uuid(&member_user)
+        ),
+        &other,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 403);
+
+    let res = get(
+        &mut app,
+        &format!(
+            "/groups/api/v1/permissions/explain-test?action=invite&member={}",
+            user_uuid(&basic_user(3, true))
+        ),
+        &other,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j["allowed"], false);
+    assert_eq!(j["rules"][0]["error"], "rule_not_allowed_to_invite_member");
+    assert_eq!(j["rules"][1]["passed"], true);
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/permissions/explain-test?action=delete_group",
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j["allowed"], true);
+    assert_eq!(j["admin_override"], true);
+    assert_eq!(j["rules"][0]["error"], "rule_not_allowed_to_delete_group");
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/permissions/explain-test?action=invite",
+        &host,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 400);
+    assert_eq!(read_json(res).await["error"], "member_required");
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/permissions/explain-test?action=fly",
+        &host,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn effective_permissions() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let member_user = basic_user(2, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let member = Soa::from(&member_user).aal_medium();
+    let admin = Soa::from(&basic_user(3, true)).admin().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "effective-test", "description": "a group" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/sudo/member/effective-test",
+        json!({ "user_uuid": user_uuid(&member_user) }),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let all = json!([
+        "invite",
+        "remove",
+        "renew",
+        "edit_terms",
+        "add_curator",
+        "remove_curator",
+        "assign_role",
+        "edit_description",
+        "delete_group"
+    ]);
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/self/permissions/effective-test",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await, all);
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/self/permissions/effective-test",
+        &member,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await, json!([]));
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/self/permissions/effective-test",
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await, all);
+
+    Ok(())
+}
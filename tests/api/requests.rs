@@ -1,10 +1,13 @@
 use crate::helpers::api::*;
 use crate::helpers::db::reset;
+use crate::helpers::mail::wait_for_emails;
 use crate::helpers::misc::nobody_soa;
 use crate::helpers::misc::read_json;
 use crate::helpers::misc::test_app;
+use crate::helpers::misc::test_app_and_mail;
 use crate::helpers::misc::Soa;
 use crate::helpers::users::basic_user;
+use crate::helpers::users::user_email;
 use crate::helpers::users::user_uuid;
 use actix_web::test;
 use actix_web::App;
@@ -130,3 +133,523 @@ async fn reject_reviewed() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[actix_rt::test]
+async fn approve_reviewed() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let requester_user_1 = basic_user(2, true);
+    let requester_user_2 = basic_user(3, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let requester_1 = Soa::from(&requester_user_1);
+    let requester_2 = Soa::from(&requester_user_2);
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({
+            "name": "approve-test",
+            "description": "a reviewed group",
+            "type": "Reviewed",
+            "group_expiration": 30
+        }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    for requester in &[&requester_1, &requester_2] {
+        let res = post(
+            &mut app,
+            "/groups/api/v1/self/requests/approve-test",
+            json!(null),
+            requester,
+        )
+        .await;
+        assert!(res.status().is_success());
+    }
+
+    let res = post(
+        &mut app,
+        &format!(
+            "/groups/api/v1/requests/approve-test/{}/approve",
+            user_uuid(&requester_user_1)
+        ),
+        json!({}),
+        &requester_1,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    let res = post(
+        &mut app,
+        &format!(
+            "/groups/api/v1/requests/approve-test/{}/approve",
+            user_uuid(&requester_user_1)
+        ),
+        json!({}),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await["status"], "joined");
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/members/approve-test?r=Member",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let members = read_json(res).await;
+    assert_eq!(members["members"].as_array().map(|a| a.len()), Some(1));
+    assert_eq!(
+        members["members"][0]["user_uuid"],
+        user_uuid(&requester_user_1)
+    );
+    assert!(!members["members"][0]["expiration"].is_null());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/terms/approve-test",
+        json!({ "text": "be nice" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        &format!(
+            "/groups/api/v1/requests/approve-test/{}/approve",
+            user_uuid(&requester_user_2)
+        ),
+        json!({ "group_expiration": 7 }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await["status"], "invited");
+
+    let res = get(&mut app, "/groups/api/v1/invitations/approve-test", &host).await;
+    assert!(res.status().is_success());
+    let invitations = read_json(res).await;
+    assert_eq!(invitations[0]["user_uuid"], user_uuid(&requester_user_2));
+    assert_eq!(invitations[0]["group_expiration"], 7);
+
+    let res = get(&mut app, "/groups/api/v1/requests/approve-test", &host).await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await.as_array().map(|a| a.len()), Some(0));
+
+    let res = post(
+        &mut app,
+        &format!(
+            "/groups/api/v1/requests/approve-test/{}/approve",
+            user_uuid(&requester_user_2)
+        ),
+        json!({}),
+        &host,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn justification_and_rejection_reason() -> Result<(), Error> {
+    reset()?;
+    let (service, recorder) = test_app_and_mail().await;
+    let app = App::new().service(service);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let requester_user = basic_user(2, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let requester = Soa::from(&requester_user);
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "justify-test", "description": "a reviewed group", "type": "Reviewed" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/requests/justify-test",
+        json!({ "justification": "x".repeat(2001) }),
+        &requester,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 400);
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/requests/justify-test",
+        json!({ "justification": "I need access for the offsite" }),
+        &requester,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(&mut app, "/groups/api/v1/self/requests", &requester).await;
+    assert!(res.status().is_success());
+    assert_eq!(
+        read_json(res).await[0]["justification"],
+        "I need access for the offsite"
+    );
+
+    let res = get(&mut app, "/groups/api/v1/requests/justify-test", &host).await;
+    assert!(res.status().is_success());
+    assert_eq!(
+        read_json(res).await[0]["justification"],
+        "I need access for the offsite"
+    );
+
+    let res = post(
+        &mut app,
+        &format!(
+            "/groups/api/v1/requests/justify-test/{}/reject",
+            user_uuid(&requester_user)
+        ),
+        json!({ "reason": "the offsite is over" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let emails = wait_for_emails(&recorder, 1).await;
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].to, Some(user_email(&requester_user)));
+    assert_eq!(emails[0].template.as_deref(), Some("reject_request"));
+    assert!(emails[0].message.body.contains("the offsite is over"));
+
+    let res = get(&mut app, "/groups/api/v1/requests/justify-test", &host).await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await.as_array().map(|a| a.len()), Some(0));
+
+    let admin = Soa::from(&host_user).admin().aal_medium();
+    let res = get(
+        &mut app,
+        &format!(
+            "/groups/api/v1/sudo/user/data/{}",
+            user_uuid(&requester_user)
+        ),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let logs = read_json(res).await["logs"].clone();
+    let bodies = logs
+        .as_array()
+        .map(|logs| logs.iter().map(|l| l["body"].clone()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    assert!(bodies
+        .iter()
+        .any(|b| b["justification"] == "I need access for the offsite"));
+    assert!(bodies
+        .iter()
+        .any(|b| b["comment"] == "rejected" && b["reason"] == "the offsite is over"));
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn multiple_approvals() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let curator_user = basic_user(2, true);
+    let requester_user = basic_user(3, true);
+    let invited_user = basic_user(4, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let curator = Soa::from(&curator_user).aal_medium();
+    let requester = Soa::from(&requester_user);
+    let invited = Soa::from(&invited_user);
+    let admin = Soa::from(&basic_user(5, true)).admin().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "approvals-test", "description": "a sensitive group", "type": "Reviewed" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/curators/approvals-test",
+        json!({ "member_uuid": user_uuid(&curator_user) }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/groups/approvals-test",
+        json!({ "required_approvals": 1 }),
+        &curator,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/groups/approvals-test",
+        json!({ "required_approvals": 2 }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/groups/approvals-test/details",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await["group"]["required_approvals"], 2);
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/requests/approvals-test",
+        json!(null),
+        &requester,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let approve = format!(
+        "/groups/api/v1/requests/approvals-test/{}/approve",
+        user_uuid(&requester_user)
+    );
+    for _ in 0..2 {
+        let res = post(&mut app, &approve, json!({}), &host).await;
+        assert!(res.status().is_success());
+        assert_eq!(read_json(res).await["status"], "pending");
+    }
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/groups/approvals-test/approvals",
+        &curator,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let approvals = read_json(res).await;
+    assert_eq!(approvals.as_array().map(|a| a.len()), Some(1));
+    assert_eq!(approvals[0]["user_uuid"], user_uuid(&requester_user));
+    assert_eq!(approvals[0]["approver_uuid"], user_uuid(&host_user));
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/groups/approvals-test/approvals",
+        &requester,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    // admins may approve but do not skip the quorum
+    let res = post(&mut app, &approve, json!({}), &admin).await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await["status"], "pending");
+
+    let res = post(&mut app, &approve, json!({}), &curator).await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await["status"], "joined");
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/approvals-test",
+        json!({ "user_uuid": user_uuid(&invited_user) }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/invitations/approvals-test",
+        json!({}),
+        &invited,
+    )
+    .await;
+    assert!(!res.status().is_success());
+    assert_eq!(read_json(res).await["error"], "rule_not_enough_approvals");
+
+    let res = post(
+        &mut app,
+        &format!(
+            "/groups/api/v1/invitations/approvals-test/{}/approve",
+            user_uuid(&invited_user)
+        ),
+        json!(null),
+        &curator,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    // approvals of former curators do not count
+    let downgrade = format!(
+        "/groups/api/v1/curators/approvals-test/{}/downgrade",
+        user_uuid(&curator_user)
+    );
+    let res = post(&mut app, &downgrade, json!({}), &host).await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/invitations/approvals-test",
+        json!({}),
+        &invited,
+    )
+    .await;
+    assert!(!res.status().is_success());
+    assert_eq!(read_json(res).await["error"], "rule_not_enough_approvals");
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/curators/approvals-test",
+        json!({ "member_uuid": user_uuid(&curator_user) }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/invitations/approvals-test",
+        json!({}),
+        &invited,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(&mut app, "/groups/api/v1/members/approvals-test", &host).await;
+    assert!(res.status().is_success());
+    let members = read_json(res).await;
+    assert_eq!(members["members"].as_array().map(|a| a.len()), Some(4));
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/groups/approvals-test/approvals",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await.as_array().map(|a| a.len()), Some(0));
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn custom_role_approvals() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let moderator_user = basic_user(2, true);
+    let requester_user = basic_user(3, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let moderator = Soa::from(&moderator_user).aal_medium();
+    let requester = Soa::from(&requester_user);
+    let admin = Soa::from(&basic_user(4, true)).admin().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "custom-approvals-test", "description": "a sensitive group", "type": "Reviewed" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/groups/custom-approvals-test",
+        json!({ "required_approvals": 2 }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/sudo/member/custom-approvals-test",
+        json!({ "user_uuid": user_uuid(&moderator_user) }),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/roles/custom-approvals-test/custom/moderator",
+        json!({ "permissions": ["InviteMember"], "position": 1 }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = put(
+        &mut app,
+        &format!(
+            "/groups/api/v1/members/custom-approvals-test/{}/role",
+            user_uuid(&moderator_user)
+        ),
+        json!({ "role_name": "moderator" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/requests/custom-approvals-test",
+        json!(null),
+        &requester,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let approve = format!(
+        "/groups/api/v1/requests/custom-approvals-test/{}/approve",
+        user_uuid(&requester_user)
+    );
+    let res = post(&mut app, &approve, json!({}), &host).await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await["status"], "pending");
+
+    // hosts of custom roles allowed to invite complete the quorum
+    let res = post(&mut app, &approve, json!({}), &moderator).await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await["status"], "joined");
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/members/custom-approvals-test?r=Member",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let members = read_json(res).await;
+    assert_eq!(members["members"].as_array().map(|a| a.len()), Some(1));
+    assert_eq!(
+        members["members"][0]["user_uuid"],
+        user_uuid(&requester_user)
+    );
+
+    Ok(())
+}
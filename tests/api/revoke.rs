@@ -1,15 +1,17 @@
 use crate::helpers::api::*;
 use crate::helpers::db::get_pool;
 use crate::helpers::db::reset;
+use crate::helpers::mail::mail_man;
 use crate::helpers::misc::create_nda;
 use crate::helpers::misc::read_json;
-use crate::helpers::misc::test_app_and_cis;
+use crate::helpers::misc::test_app_cis_and_mail;
 use crate::helpers::misc::Soa;
 use crate::helpers::sudo::add_to_group;
 use crate::helpers::users::basic_user;
 use crate::helpers::users::user_uuid;
 use actix_web::test;
 use actix_web::App;
+use dino_park_packs::capabilities::Providers;
 use dino_park_packs::db::operations::users::update_user_cache;
 use failure::Error;
 use serde_json::json;
@@ -18,7 +20,9 @@ use std::sync::Arc;
 #[actix_rt::test]
 async fn revoke_nda() -> Result<(), Error> {
     reset()?;
-    let (service, cis_client) = test_app_and_cis().await;
+    let (service, cis_client, recorder) = test_app_cis_and_mail().await;
+    let mail_man = mail_man(&recorder);
+    let providers = Providers::default();
     let cis_client = Arc::new(cis_client);
     let app = App::new().service(service);
     let mut app = test::init_service(app).await;
@@ -78,7 +82,14 @@ async fn revoke_nda() -> Result<(), Error> {
 
     let pool = get_pool();
     staff_user_2.staff_information.staff.value = Some(false);
-    update_user_cache(&pool, &staff_user_2, Arc::clone(&cis_client)).await?;
+    update_user_cache(
+        &pool,
+        &staff_user_2,
+        Arc::clone(&cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
 
     let res = get(&mut app, "/groups/api/v1/members/revoke-test", &host).await;
     assert!(res.status().is_success());
@@ -91,7 +102,9 @@ async fn revoke_nda() -> Result<(), Error> {
 #[actix_rt::test]
 async fn revoke_staff() -> Result<(), Error> {
     reset()?;
-    let (service, cis_client) = test_app_and_cis().await;
+    let (service, cis_client, recorder) = test_app_cis_and_mail().await;
+    let mail_man = mail_man(&recorder);
+    let providers = Providers::default();
     let cis_client = Arc::new(cis_client);
     let app = App::new().service(service);
     let mut app = test::init_service(app).await;
@@ -127,7 +140,14 @@ async fn revoke_staff() -> Result<(), Error> {
 
     let pool = get_pool();
     staff_user_2.staff_information.staff.value = Some(false);
-    update_user_cache(&pool, &staff_user_2, Arc::clone(&cis_client)).await?;
+    update_user_cache(
+        &pool,
+        &staff_user_2,
+        Arc::clone(&cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
 
     let res = get(&mut app, "/groups/api/v1/members/revoke-test", &host).await;
     assert!(res.status().is_success());
@@ -135,7 +155,14 @@ async fn revoke_staff() -> Result<(), Error> {
     assert_eq!(members["members"].as_array().map(|a| a.len()), Some(2));
 
     staff_user_1.staff_information.staff.value = Some(false);
-    update_user_cache(&pool, &staff_user_1, Arc::clone(&cis_client)).await?;
+    update_user_cache(
+        &pool,
+        &staff_user_1,
+        Arc::clone(&cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
 
     let res = get(&mut app, "/groups/api/v1/members/revoke-test", &host).await;
     assert!(res.status().is_success());
@@ -0,0 +1,289 @@
+use crate::helpers::api::*;
+use crate::helpers::db::reset;
+use crate::helpers::misc::read_json;
+use crate::helpers::misc::test_app;
+use crate::helpers::misc::Soa;
+use crate::helpers::users::basic_user;
+use crate::helpers::users::user_uuid;
+use actix_web::test;
+use actix_web::App;
+use failure::Error;
+use serde_json::json;
+
+#[actix_rt::test]
+async fn role_permissions() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let member_user = basic_user(2, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let member = Soa::from(&member_user).aal_medium();
+    let admin = Soa::from(&basic_user(4, true)).admin().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "roles-test", "description": "a group" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(&mut app, "/groups/api/v1/roles/roles-test", &host).await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j[0]["typ"], "Admin");
+    assert_eq!(j[0]["permissions"].as_array().map(|p| p.len()), Some(7));
+    assert_eq!(j[1]["typ"], "Member");
+    assert_eq!(j[1]["permissions"], json!([]));
+
+    let res = get(&mut app, "/groups/api/v1/roles/roles-test", &member).await;
+    assert_eq!(res.status().as_u16(), 403);
+    assert_eq!(read_json(res).await["error"], "rule_not_a_curator");
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/sudo/member/roles-test",
+        json!({ "user_uuid": user_uuid(&member_user) }),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/roles-test",
+        json!({ "user_uuid": user_uuid(&basic_user(3, true)), "invitation_expiration": 7 }),
+        &member,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/roles/roles-test/Member",
+        json!({ "permissions": ["InviteMember"] }),
+        &member,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/roles/roles-test/Member",
+        json!({ "permissions": ["InviteMember"] }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/roles-test",
+        json!({ "user_uuid": user_uuid(&basic_user(3, true)), "invitation_expiration": 7 }),
+        &member,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/roles/roles-test/Admin",
+        json!({ "permissions": [] }),
+        &host,
+    )
+    .await;
+    assert!(!res.status().is_success());
+    assert_eq!(
+        read_json(res).await["error"],
+        "admin_role_requires_all_permissions"
+    );
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/roles/roles-test/Admin",
+        json!({ "permissions": ["InviteMember", "EditDescription", "EditTerms"] }),
+        &host,
+    )
+    .await;
+    assert!(!res.status().is_success());
+    assert_eq!(
+        read_json(res).await["error"],
+        "admin_role_requires_all_permissions"
+    );
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/roles/roles-test/Admin",
+        json!({ "permissions": [
+            "EditTerms",
+            "InviteMember",
+            "EditDescription",
+            "AddCurator",
+            "RemoveCurator",
+            "DeleteGroup",
+            "RemoveMember"
+        ] }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/roles/roles-test/Curator",
+        json!({ "permissions": ["EditTerms"] }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(&mut app, "/groups/api/v1/roles/roles-test", &host).await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j[1]["typ"], "Curator");
+    assert_eq!(j[1]["permissions"], json!(["EditTerms"]));
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn custom_roles() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let member_user = basic_user(2, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let member = Soa::from(&member_user).aal_medium();
+    let admin = Soa::from(&basic_user(4, true)).admin().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "custom-roles-test", "description": "a group" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/sudo/member/custom-roles-test",
+        json!({ "user_uuid": user_uuid(&member_user) }),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/roles/custom-roles-test/custom/admin",
+        json!({ "permissions": [] }),
+        &host,
+    )
+    .await;
+    assert!(!res.status().is_success());
+    assert_eq!(read_json(res).await["error"], "invalid_role_name");
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/roles/custom-roles-test/custom/moderator",
+        json!({ "permissions": ["InviteMember"], "position": 1 }),
+        &member,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/roles/custom-roles-test/custom/moderator",
+        json!({ "permissions": ["InviteMember"], "position": 1 }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j["typ"], "Custom");
+    assert_eq!(j["name"], "moderator");
+
+    let res = put(
+        &mut app,
+        &format!(
+            "/groups/api/v1/members/custom-roles-test/{}/role",
+            user_uuid(&member_user)
+        ),
+        json!({ "role_name": "moderator" }),
+        &member,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    let res = put(
+        &mut app,
+        &format!(
+            "/groups/api/v1/members/custom-roles-test/{}/role",
+            user_uuid(&member_user)
+        ),
+        json!({ "role_name": "moderator" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/groups/custom-roles-test/details",
+        &member,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j["membership"]["role"], "Custom");
+    assert_eq!(j["membership"]["role_name"], "moderator");
+    assert_eq!(j["curator"], false);
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/custom-roles-test",
+        json!({ "user_uuid": user_uuid(&basic_user(3, true)), "invitation_expiration": 7 }),
+        &member,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/members/custom-roles-test?r=Member",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j["members"].as_array().map(|m| m.len()), Some(1));
+    assert_eq!(j["members"][0]["role"], "Custom");
+
+    let res = delete(
+        &mut app,
+        "/groups/api/v1/roles/custom-roles-test/custom/moderator",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/groups/custom-roles-test/details",
+        &member,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j["membership"]["role"], "Member");
+    assert_eq!(j["membership"]["role_name"], "member");
+
+    Ok(())
+}
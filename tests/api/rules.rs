@@ -0,0 +1,170 @@
+use crate::helpers::api::*;
+use crate::helpers::db::reset;
+use crate::helpers::misc::read_json;
+use crate::helpers::misc::test_app;
+use crate::helpers::misc::Soa;
+use crate::helpers::users::basic_user;
+use crate::helpers::users::user_uuid;
+use actix_web::test;
+use actix_web::App;
+use failure::Error;
+use serde_json::json;
+
+#[actix_rt::test]
+async fn group_rule() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let member_user = basic_user(2, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let admin = Soa::from(&host_user).admin().aal_medium();
+    let other = Soa::from(&basic_user(3, true)).aal_medium();
+
+    for name in &["rules-required", "rules-test"] {
+        let res = post(
+            &mut app,
+            "/groups/api/v1/groups",
+            json!({ "name": name, "description": "a group" }),
+            &host,
+        )
+        .await;
+        assert!(res.status().is_success());
+    }
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/rules/rules-test",
+        json!({ "type": "Group", "payload": "rules-required" }),
+        &other,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 403);
+    assert_eq!(read_json(res).await["error"], "rule_not_a_curator");
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/rules/rules-test",
+        json!({ "type": "Group", "payload": "rules-test" }),
+        &host,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/rules/rules-test",
+        json!({ "type": "Group", "payload": "rules-required" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let rule_id = read_json(res).await["id"].as_i64().unwrap();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/rules/rules-test",
+        json!({ "type": "Group", "payload": "rules-required" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await["id"].as_i64().unwrap(), rule_id);
+
+    let res = get(&mut app, "/groups/api/v1/rules/rules-test", &host).await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j.as_array().unwrap().len(), 1);
+    assert_eq!(j[0]["typ"], "Group");
+    assert_eq!(j[0]["payload"], "rules-required");
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/rules-test",
+        json!({ "user_uuid": user_uuid(&member_user), "invitation_expiration": 7 }),
+        &host,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/sudo/member/rules-required",
+        json!({ "user_uuid": user_uuid(&member_user) }),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/rules-test",
+        json!({ "user_uuid": user_uuid(&member_user), "invitation_expiration": 7 }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = delete(
+        &mut app,
+        &format!("/groups/api/v1/rules/rules-test/{}", rule_id),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(&mut app, "/groups/api/v1/rules/rules-test", &host).await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await, json!([]));
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn staff_rule() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "rules-staff", "description": "a group", "trust": "Authenticated" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/rules/rules-staff",
+        json!({ "type": "Staff" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/rules-staff",
+        json!({ "user_uuid": user_uuid(&basic_user(11, false)), "invitation_expiration": 7 }),
+        &host,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/rules-staff",
+        json!({ "user_uuid": user_uuid(&basic_user(2, true)), "invitation_expiration": 7 }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    Ok(())
+}
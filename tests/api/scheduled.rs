@@ -0,0 +1,233 @@
+use crate::helpers::api::*;
+use crate::helpers::db::get_pool;
+use crate::helpers::db::reset;
+use crate::helpers::mail::mail_man;
+use crate::helpers::misc::read_json;
+use crate::helpers::misc::test_app;
+use crate::helpers::misc::test_app_cis_and_mail;
+use crate::helpers::misc::Soa;
+use crate::helpers::users::basic_user;
+use crate::helpers::users::user_uuid;
+use actix_web::test;
+use actix_web::App;
+use chrono::Duration;
+use chrono::Utc;
+use diesel::dsl::count_star;
+use diesel::prelude::*;
+use dino_park_packs::capabilities::Providers;
+use dino_park_packs::db::operations::users::update_user_cache;
+use dino_park_packs::db::schema;
+use failure::Error;
+use serde_json::json;
+use std::sync::Arc;
+
+#[actix_rt::test]
+async fn scheduled_memberships() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let scheduled_user = basic_user(2, true);
+    let invited_user = basic_user(3, true);
+    let curator_user = basic_user(5, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let invited = Soa::from(&invited_user).aal_medium();
+    let admin = Soa::from(&basic_user(4, true)).admin().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "scheduled-test", "description": "a group" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/sudo/member/scheduled-test",
+        json!({
+            "user_uuid": user_uuid(&scheduled_user),
+            "starts_at": (Utc::now() + Duration::seconds(2)).to_rfc3339(),
+        }),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/sudo/member/scheduled-test",
+        json!({
+            "user_uuid": user_uuid(&curator_user),
+            "starts_at": (Utc::now() + Duration::seconds(2)).to_rfc3339(),
+        }),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    // becoming a curator in the meantime must survive the activation
+    let res = post(
+        &mut app,
+        "/groups/api/v1/curators/scheduled-test",
+        json!({ "member_uuid": user_uuid(&curator_user) }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/scheduled-test",
+        json!({
+            "user_uuid": user_uuid(&invited_user),
+            "invitation_expiration": 7,
+            "starts_at": (Utc::now() + Duration::days(7)).to_rfc3339(),
+        }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(&mut app, "/groups/api/v1/self/invitations", &invited).await;
+    assert!(res.status().is_success());
+    assert!(read_json(res).await[0]["starts_at"].is_string());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/invitations/scheduled-test",
+        json!({}),
+        &invited,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/members/scheduled-test?r=Member",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(
+        read_json(res).await["members"].as_array().map(|m| m.len()),
+        Some(0)
+    );
+
+    let res = post(&mut app, "/internal/activate/all", json!({}), &host).await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await["activated"], 0);
+
+    actix_rt::time::sleep(std::time::Duration::from_secs(3)).await;
+
+    let res = post(&mut app, "/internal/activate/all", json!({}), &host).await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await["activated"], 2);
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/members/scheduled-test?r=Member",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j["members"].as_array().map(|m| m.len()), Some(1));
+    assert_eq!(j["members"][0]["user_uuid"], user_uuid(&scheduled_user));
+
+    Ok(())
+}
+
+fn pending_count() -> Result<i64, Error> {
+    let connection = get_pool().get()?;
+    schema::pending_memberships::table
+        .select(count_star())
+        .first(&connection)
+        .map_err(Into::into)
+}
+
+#[actix_rt::test]
+async fn scheduled_memberships_of_revoked_users() -> Result<(), Error> {
+    reset()?;
+    let (service, cis_client, recorder) = test_app_cis_and_mail().await;
+    let mail_man = mail_man(&recorder);
+    let providers = Providers::default();
+    let cis_client = Arc::new(cis_client);
+    let app = App::new().service(service);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let mut demoted_user = basic_user(2, true);
+    let mut deleted_user = basic_user(3, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let admin = Soa::from(&basic_user(4, true)).admin().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "scheduled-staff-test", "description": "a group", "trust": "Staff" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    for user in &[&demoted_user, &deleted_user] {
+        let res = post(
+            &mut app,
+            "/groups/api/v1/sudo/member/scheduled-staff-test",
+            json!({
+                "user_uuid": user_uuid(user),
+                "starts_at": (Utc::now() + Duration::seconds(2)).to_rfc3339(),
+            }),
+            &admin,
+        )
+        .await;
+        assert!(res.status().is_success());
+    }
+    assert_eq!(pending_count()?, 2);
+
+    let pool = get_pool();
+    demoted_user.staff_information.staff.value = Some(false);
+    update_user_cache(
+        &pool,
+        &demoted_user,
+        Arc::clone(&cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
+    assert_eq!(pending_count()?, 1);
+
+    deleted_user.active.value = Some(false);
+    update_user_cache(
+        &pool,
+        &deleted_user,
+        Arc::clone(&cis_client),
+        &mail_man,
+        &providers,
+    )
+    .await?;
+    assert_eq!(pending_count()?, 0);
+
+    actix_rt::time::sleep(std::time::Duration::from_secs(3)).await;
+
+    let res = post(&mut app, "/internal/activate/all", json!({}), &host).await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await["activated"], 0);
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/members/scheduled-staff-test?r=Member",
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(
+        read_json(res).await["members"].as_array().map(|m| m.len()),
+        Some(0)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use crate::helpers::api::*;
+use crate::helpers::db::reset;
+use crate::helpers::misc::read_json;
+use crate::helpers::misc::test_app;
+use crate::helpers::misc::Soa;
+use crate::helpers::users::basic_user;
+use crate::helpers::users::user_uuid;
+use actix_web::test;
+use actix_web::App;
+use failure::Error;
+use serde_json::json;
+
+#[actix_rt::test]
+async fn subgroups() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let curator_user = basic_user(2, true);
+    let member_user = basic_user(3, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let curator = Soa::from(&curator_user).aal_medium();
+    let member = Soa::from(&member_user).aal_medium();
+    let admin = Soa::from(&basic_user(4, true)).admin().aal_medium();
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "mozilla", "description": "a group" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/sudo/member/mozilla",
+        json!({ "user_uuid": user_uuid(&member_user) }),
+        &admin,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/curators/mozilla",
+        json!({ "member_uuid": user_uuid(&curator_user) }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups/mozilla/subgroups",
+        json!({ "name": "it", "description": "a sub group" }),
+        &member,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups/mozilla/subgroups",
+        json!({ "name": "it", "description": "a sub group" }),
+        &curator,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups/it/subgroups",
+        json!({ "name": "iam", "description": "a sub sub group" }),
+        &curator,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups/does-not-exist/subgroups",
+        json!({ "name": "orphan", "description": "a sub group" }),
+        &host,
+    )
+    .await;
+    assert!(!res.status().is_success());
+
+    let res = get(&mut app, "/groups/api/v1/groups?parent=mozilla", &curator).await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j["groups"].as_array().map(|g| g.len()), Some(1));
+    assert_eq!(j["groups"][0]["name"], "it");
+
+    let res = get(&mut app, "/groups/api/v1/groups/iam/details", &curator).await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j["breadcrumb"], json!(["mozilla", "it"]));
+
+    let res = delete(
+        &mut app,
+        "/groups/api/v1/groups/it",
+        &Soa::from(&curator_user).creator().aal_medium(),
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(&mut app, "/groups/api/v1/groups/iam/details", &curator).await;
+    assert!(res.status().is_success());
+    let j = read_json(res).await;
+    assert_eq!(j["breadcrumb"], json!(["mozilla"]));
+
+    Ok(())
+}
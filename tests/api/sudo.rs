@@ -1,4 +1,5 @@
 use crate::helpers::api::*;
+use crate::helpers::db::get_pool;
 use crate::helpers::db::reset;
 use crate::helpers::misc::read_json;
 use crate::helpers::misc::test_app;
@@ -8,6 +9,9 @@ use crate::helpers::users::user_email;
 use crate::helpers::users::user_uuid;
 use actix_web::test;
 use actix_web::App;
+use diesel::prelude::*;
+use dino_park_packs::db::schema;
+use dino_park_packs::db::types::EmailStatusType;
 use failure::Error;
 use serde_json::json;
 
@@ -314,3 +318,76 @@ async fn raw_data() -> Result<(), Error> {
 
     Ok(())
 }
+
+fn failed_email(to: &str) -> Result<i32, Error> {
+    use schema::email_outbox as o;
+    let connection = get_pool().get()?;
+    diesel::insert_into(o::table)
+        .values((
+            o::to_address.eq(to),
+            o::from_address.eq("no-reply@localhost"),
+            o::subject.eq("subject"),
+            o::body.eq("body"),
+            o::status.eq(EmailStatusType::Failed),
+            o::attempts.eq(8),
+            o::last_error.eq("bounced"),
+        ))
+        .returning(o::email_id)
+        .get_result(&connection)
+        .map_err(Into::into)
+}
+
+#[actix_rt::test]
+async fn failed_emails() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let some_user = basic_user(2, true);
+    let host = Soa::from(&host_user).admin().aal_medium();
+    let some = Soa::from(&some_user).aal_medium();
+
+    let email_id = failed_email(&user_email(&some_user))?;
+
+    let res = get(&mut app, "/groups/api/v1/sudo/mail/failed", &some).await;
+    assert!(res.status().is_client_error());
+
+    let res = get(&mut app, "/groups/api/v1/sudo/mail/failed", &host).await;
+    assert!(res.status().is_success());
+    let failed = read_json(res).await;
+    assert_eq!(failed[0]["id"], email_id);
+    assert_eq!(failed[0]["to_address"], user_email(&some_user));
+    assert_eq!(failed[0]["last_error"], "bounced");
+
+    let res = post(
+        &mut app,
+        &format!("/groups/api/v1/sudo/mail/{}/resend", email_id),
+        json!({}),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(&mut app, "/groups/api/v1/sudo/mail/failed", &host).await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await.as_array().map(|a| a.len()), Some(0));
+
+    let status = schema::email_outbox::table
+        .filter(schema::email_outbox::email_id.eq(email_id))
+        .select(schema::email_outbox::status)
+        .first::<EmailStatusType>(&get_pool().get()?)?;
+    assert_eq!(status, EmailStatusType::Pending);
+
+    let res = post(
+        &mut app,
+        &format!("/groups/api/v1/sudo/mail/{}/resend", email_id),
+        json!({}),
+        &host,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 400);
+    assert_eq!(read_json(res).await["error"], "no_failed_email");
+
+    Ok(())
+}
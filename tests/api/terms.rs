@@ -0,0 +1,151 @@
+use crate::helpers::api::*;
+use crate::helpers::db::reset;
+use crate::helpers::misc::read_json;
+use crate::helpers::misc::test_app;
+use crate::helpers::misc::Soa;
+use crate::helpers::users::basic_user;
+use crate::helpers::users::user_uuid;
+use actix_web::test;
+use actix_web::App;
+use failure::Error;
+use serde_json::json;
+
+#[actix_rt::test]
+async fn terms_versions_and_reacceptance() -> Result<(), Error> {
+    reset()?;
+    let app = App::new().service(test_app().await);
+    let mut app = test::init_service(app).await;
+
+    let host_user = basic_user(1, true);
+    let member_user = basic_user(2, true);
+    let host = Soa::from(&host_user).creator().aal_medium();
+    let member = Soa::from(&member_user);
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/groups",
+        json!({ "name": "terms-test", "description": "a group with terms" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/terms/terms-test",
+        json!({ "text": "be nice" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/invitations/terms-test",
+        json!({ "user_uuid": user_uuid(&member_user) }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/invitations/terms-test",
+        json!({}),
+        &member,
+    )
+    .await;
+    assert_eq!(res.status().as_u16(), 400);
+    assert_eq!(read_json(res).await["error"], "terms_not_accepted");
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/self/invitations/terms-test",
+        json!({ "accepted_terms": true }),
+        &member,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(&mut app, "/groups/api/v1/terms/terms-test/pending", &host).await;
+    assert!(res.status().is_success());
+    let pending = read_json(res).await;
+    assert_eq!(pending.as_array().map(|a| a.len()), Some(1));
+    assert_eq!(pending[0]["user_uuid"], user_uuid(&host_user));
+
+    let res = get(&mut app, "/groups/api/v1/terms/terms-test/pending", &member).await;
+    assert!(!res.status().is_success());
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/terms/terms-test",
+        json!({ "text": "be very nice", "require_reacceptance": true }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = put(
+        &mut app,
+        "/groups/api/v1/terms/terms-test",
+        json!({ "text": "be very nice" }),
+        &host,
+    )
+    .await;
+    assert!(res.status().is_success());
+
+    let res = get(
+        &mut app,
+        "/groups/api/v1/terms/terms-test/versions",
+        &member,
+    )
+    .await;
+    assert!(res.status().is_success());
+    let versions = read_json(res).await;
+    assert_eq!(versions.as_array().map(|a| a.len()), Some(2));
+    assert_eq!(versions[0]["version"], 2);
+    assert_eq!(versions[0]["text"], "be very nice");
+    assert_eq!(versions[1]["text"], "be nice");
+
+    let res = get(&mut app, "/groups/api/v1/terms/terms-test/pending", &host).await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await.as_array().map(|a| a.len()), Some(2));
+
+    let renew = format!(
+        "/groups/api/v1/members/terms-test/{}/renew",
+        user_uuid(&member_user)
+    );
+    let res = post(&mut app, &renew, json!({ "group_expiration": 30 }), &host).await;
+    assert!(!res.status().is_success());
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/terms/terms-test/accept",
+        json!({ "version": 1 }),
+        &member,
+    )
+    .await;
+    assert!(!res.status().is_success());
+    assert_eq!(read_json(res).await["error"], "outdated_terms_version");
+
+    let res = post(
+        &mut app,
+        "/groups/api/v1/terms/terms-test/accept",
+        json!({ "version": 2 }),
+        &member,
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(read_json(res).await["version"], 2);
+
+    let res = post(&mut app, &renew, json!({ "group_expiration": 30 }), &host).await;
+    assert!(res.status().is_success());
+
+    let res = get(&mut app, "/groups/api/v1/terms/terms-test/pending", &host).await;
+    assert!(res.status().is_success());
+    let pending = read_json(res).await;
+    assert_eq!(pending.as_array().map(|a| a.len()), Some(1));
+    assert_eq!(pending[0]["user_uuid"], user_uuid(&host_user));
+
+    Ok(())
+}
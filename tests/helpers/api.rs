@@ -63,3 +63,30 @@ where
         .to_request();
     test::call_service(&mut app, req).await
 }
+
+pub async fn post_multipart<S, B, E>(
+    mut app: &mut S,
+    endpoint: &str,
+    field: &str,
+    data: &str,
+    scope: &Soa,
+) -> S::Response
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = E>,
+    E: std::fmt::Debug,
+{
+    let boundary = "dino-park-packs-boundary";
+    let body = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"{field}\"; filename=\"{field}.csv\"\r\nContent-Type: text/csv\r\n\r\n{data}\r\n--{boundary}--\r\n"
+    );
+    let req = test::TestRequest::post()
+        .append_header(("sau", scope.encode()))
+        .append_header((
+            "content-type",
+            format!("multipart/form-data; boundary={boundary}"),
+        ))
+        .uri(endpoint)
+        .set_payload(body)
+        .to_request();
+    test::call_service(&mut app, req).await
+}
@@ -0,0 +1,40 @@
+use crate::helpers::db::get_pool;
+use crate::helpers::misc::token_secret;
+use actix_rt::time::sleep;
+use dino_park_packs::mail::manager::MailMan;
+use dino_park_packs::mail::send::RecordingSender;
+use dino_park_packs::mail::send::Sender;
+use dino_park_packs::mail::templates::TemplateManager;
+use dino_park_packs::mail::templates::DEFAULT_TEMPLATE_DIR;
+use dino_park_packs::mail::Email;
+use std::time::Duration;
+
+const DOMAIN: &str = "people.mozilla.org";
+const POLL_INTERVAL_MS: u64 = 50;
+const POLL_ATTEMPTS: usize = 100;
+const SETTLE_MS: u64 = 250;
+
+pub fn mail_man(recorder: &RecordingSender) -> MailMan {
+    let template_man = TemplateManager::new(DOMAIN.into(), DEFAULT_TEMPLATE_DIR).unwrap();
+    MailMan::new(
+        Sender::Recording(recorder.clone()),
+        template_man,
+        None,
+        None,
+        get_pool(),
+        token_secret(),
+    )
+}
+
+/// Emails are delivered on the mail arbiter, so wait until at least `n` of them arrived. Waits a
+/// little longer for stragglers, so callers can assert on the exact number of emails sent.
+pub async fn wait_for_emails(recorder: &RecordingSender, n: usize) -> Vec<Email> {
+    for _ in 0..POLL_ATTEMPTS {
+        if recorder.emails().len() >= n {
+            break;
+        }
+        sleep(Duration::from_millis(POLL_INTERVAL_MS)).await;
+    }
+    sleep(Duration::from_millis(SETTLE_MS)).await;
+    recorder.emails()
+}
@@ -1,5 +1,6 @@
 use crate::helpers::cis::CisFakeClient;
 use crate::helpers::db::get_pool;
+use crate::helpers::mail::mail_man;
 use crate::helpers::users::basic_user;
 use actix_web::body::MessageBody;
 use actix_web::dev::*;
@@ -12,6 +13,9 @@ use base64::encode;
 use cis_client::AsyncCisClientTrait;
 use cis_profile::schema::Profile;
 use dino_park_gate::scope::ScopeAndUser;
+use dino_park_packs::capabilities::Providers;
+use dino_park_packs::mail::send::RecordingSender;
+use dino_park_packs::tokens::TokenSecret;
 use dino_park_trust::AALevel;
 use dino_park_trust::GroupsTrust;
 use dino_park_trust::Trust;
@@ -23,6 +27,10 @@ use std::sync::Arc;
 
 use dino_park_packs::*;
 
+pub fn token_secret() -> TokenSecret {
+    TokenSecret::new("dino-park-packs-test")
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Soa {
     pub user_id: String,
@@ -136,16 +144,37 @@ pub async fn test_app() -> impl HttpServiceFactory {
 }
 
 pub async fn test_app_and_cis() -> (impl HttpServiceFactory, CisFakeClient) {
+    let (app, cis_client, _) = test_app_cis_and_mail().await;
+    (app, cis_client)
+}
+
+pub async fn test_app_and_mail() -> (impl HttpServiceFactory, RecordingSender) {
+    let (app, _, recorder) = test_app_cis_and_mail().await;
+    (app, recorder)
+}
+
+pub async fn test_app_cis_and_mail() -> (impl HttpServiceFactory, CisFakeClient, RecordingSender) {
+    test_app_with_providers(Providers::default()).await
+}
+
+pub async fn test_app_with_providers(
+    providers: Providers,
+) -> (impl HttpServiceFactory, CisFakeClient, RecordingSender) {
     let pool = get_pool();
     let cis_client = CisFakeClient::new(pool.clone());
+    let recorder = RecordingSender::default();
     populate(&cis_client).await;
     (
         web::scope("")
             .app_data(web::Data::new(cis_client.clone()))
             .app_data(web::Data::new(pool.clone()))
+            .app_data(web::Data::new(mail_man(&recorder)))
+            .app_data(web::Data::new(providers))
+            .app_data(web::Data::new(token_secret()))
             .service(healthz::healthz_app())
             .service(api::internal::internal_app::<CisFakeClient>())
             .service(import::api::import_app::<CisFakeClient>())
+            .service(api::notifications::unsubscribe_app())
             .service(
                 web::scope("/groups/api/v1")
                     .wrap_fn(|req, srv| {
@@ -157,13 +186,18 @@ pub async fn test_app_and_cis() -> (impl HttpServiceFactory, CisFakeClient) {
                     .service(api::members::members_app::<CisFakeClient>())
                     .service(api::current::current_app::<CisFakeClient>())
                     .service(api::invitations::invitations_app())
+                    .service(api::notifications::notifications_app())
                     .service(api::terms::terms_app())
                     .service(api::users::users_app())
                     .service(api::admins::admins_app::<CisFakeClient>())
-                    .service(api::requests::requests_app())
+                    .service(api::permissions::permissions_app())
+                    .service(api::requests::requests_app::<CisFakeClient>())
+                    .service(api::roles::roles_app())
+                    .service(api::rules::rules_app::<CisFakeClient>())
                     .service(api::sudo::sudo_app::<CisFakeClient>()),
             ),
         cis_client,
+        recorder,
     )
 }
 
@@ -177,6 +211,7 @@ pub async fn create_nda(cis_client: Arc<impl AsyncCisClientTrait>) -> Result<(),
         trust: db::types::TrustType::Authenticated,
         capabilities: Default::default(),
         group_expiration: None,
+        parent: None,
     };
 
     db::operations::groups::add_new_group(&pool, &host.into(), nda_group, cis_client).await
@@ -1,6 +1,7 @@
 pub mod api;
 pub mod cis;
 pub mod db;
+pub mod mail;
 pub mod misc;
 pub mod sudo;
 pub mod users;
@@ -31,7 +31,7 @@ fn simple_rule_stuct_creator_success() -> Result<(), Error> {
         member: None,
     };
     let engine = Engine {
-        rules: &[&rule_is_creator],
+        rules: &[("is_creator", &rule_is_creator)],
     };
     let ok = engine.run(&ctx);
     assert!(ok.is_ok());
@@ -58,9 +58,75 @@ fn simple_rule_stuct_creator_fail() -> Result<(), Error> {
         member: None,
     };
     let engine = Engine {
-        rules: &[&rule_is_creator],
+        rules: &[("is_creator", &rule_is_creator)],
     };
     let ok = engine.run(&ctx);
     assert_eq!(ok, Err::<(), _>(RuleError::NotAllowedToCreateGroups));
     Ok(())
 }
+
+#[test]
+fn explain_reports_every_rule() -> Result<(), Error> {
+    reset()?;
+    let pool = get_pool();
+    let scope_and_user = ScopeAndUser {
+        user_id: String::from("some_id"),
+        scope: Trust::Staff,
+        groups_scope: GroupsTrust::None,
+        aa_level: AALevel::Medium,
+    };
+    let ctx = RuleContext::minimal(&pool, &scope_and_user, "test", &Uuid::nil());
+    let engine = Engine {
+        rules: &[
+            ("is_creator", &rule_is_creator),
+            ("valid_group_name", &rule_valid_group_name),
+            ("only_admins", &rule_only_admins),
+        ],
+    };
+    let explanation = engine.explain(&ctx);
+    assert!(!explanation.allowed);
+    assert!(!explanation.admin_override);
+    assert_eq!(
+        explanation.rules,
+        vec![
+            RuleOutcome {
+                rule: "is_creator",
+                passed: false,
+                error: Some(RuleError::NotAllowedToCreateGroups)
+            },
+            RuleOutcome {
+                rule: "valid_group_name",
+                passed: true,
+                error: None
+            },
+            RuleOutcome {
+                rule: "only_admins",
+                passed: false,
+                error: Some(RuleError::NeverAllowed)
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn explain_reports_admin_override() -> Result<(), Error> {
+    reset()?;
+    let pool = get_pool();
+    let scope_and_user = ScopeAndUser {
+        user_id: String::from("some_id"),
+        scope: Trust::Staff,
+        groups_scope: GroupsTrust::Admin,
+        aa_level: AALevel::Medium,
+    };
+    let ctx = RuleContext::minimal(&pool, &scope_and_user, "test", &Uuid::nil());
+    let engine = Engine {
+        rules: &[("host_is_group_admin", &rule_host_is_group_admin)],
+    };
+    let explanation = engine.explain(&ctx);
+    assert!(explanation.allowed);
+    assert!(explanation.admin_override);
+    assert_eq!(explanation.rules[0].rule, "host_is_group_admin");
+    assert_eq!(explanation.rules[0].error, Some(RuleError::NotAnAdmin));
+    Ok(())
+}